@@ -11,6 +11,7 @@ use gw_types::{
     core::ScriptHashType,
     offchain::RunResult,
     packed::BlockInfo,
+    packed::L2BlockCommittedInfo,
     packed::{RawL2Transaction, RollupConfig, SUDTArgs, SUDTTransfer, Script},
     prelude::*,
 };
@@ -50,6 +51,13 @@ impl ChainStore for DummyChainStore {
     fn get_block_hash_by_number(&self, _number: u64) -> Result<Option<H256>, gw_db::error::Error> {
         Err("dummy chain store".to_string().into())
     }
+
+    fn get_block_committed_info(
+        &self,
+        _block_hash: &H256,
+    ) -> Result<Option<L2BlockCommittedInfo>, gw_db::error::Error> {
+        Err("dummy chain store".to_string().into())
+    }
 }
 
 fn new_block_info(block_producer_id: u32, number: u64, timestamp: u64) -> BlockInfo {
@@ -75,10 +83,7 @@ fn run_contract_get_result<S: State + CodeStore>(
         .build();
     let backend_manage = build_backend_manage(rollup_config);
     let account_lock_manage = AccountLockManage::default();
-    let rollup_ctx = RollupContext {
-        rollup_config: rollup_config.clone(),
-        rollup_script_hash: [42u8; 32].into(),
-    };
+    let rollup_ctx = RollupContext::new_unchecked([42u8; 32].into(), rollup_config.clone());
     let generator = Generator::new(backend_manage, account_lock_manage, rollup_ctx);
     let chain_view = DummyChainStore;
     let run_result = generator.execute_transaction(&chain_view, tree, block_info, &raw_tx)?;
@@ -59,14 +59,14 @@ fn generate_custodian_cells(
                     .deposition_lock_args(deposition_lock_args)
                     .build();
 
-                let rollup_type_hash = rollup_context.rollup_script_hash.as_slice().iter();
+                let rollup_type_hash = rollup_context.rollup_script_hash().as_slice().iter();
                 rollup_type_hash
                     .chain(custodian_lock_args.as_slice().iter())
                     .cloned()
                     .collect()
             };
             let lock = Script::new_builder()
-                .code_hash(rollup_context.rollup_config.custodian_script_type_hash())
+                .code_hash(rollup_context.rollup_config().custodian_script_type_hash())
                 .hash_type(ScriptHashType::Type.into())
                 .args(lock_args.pack())
                 .build();
@@ -609,12 +609,12 @@ impl BlockProducer {
             // the lock should be correct unless the upstream ckb-indexer has bugs
             {
                 let lock = cell.cell.output.lock();
-                if lock.code_hash() != ctx.rollup_config.deposition_script_type_hash()
+                if lock.code_hash() != ctx.rollup_config().deposition_script_type_hash()
                     || lock.hash_type() != hash_type
                 {
                     log::error!(
                         "Invalid deposit lock, expect code_hash: {}, hash_type: Type, got: {}, {}",
-                        ctx.rollup_config.deposition_script_type_hash(),
+                        ctx.rollup_config().deposition_script_type_hash(),
                         lock.code_hash(),
                         lock.hash_type()
                     );
@@ -625,10 +625,10 @@ impl BlockProducer {
                     log::error!("Invalid deposit args, expect len: 32, got: {}", args.len());
                     continue;
                 }
-                if &args[..32] != ctx.rollup_script_hash.as_slice() {
+                if &args[..32] != ctx.rollup_script_hash().as_slice() {
                     log::error!(
                         "Invalid deposit args, expect rollup_script_hash: {}, got: {}",
-                        hex::encode(ctx.rollup_script_hash.as_slice()),
+                        hex::encode(ctx.rollup_script_hash().as_slice()),
                         hex::encode(&args[..32])
                     );
                     continue;
@@ -637,12 +637,12 @@ impl BlockProducer {
             // check sUDT
             // sUDT may be invalid, this may caused by malicious user
             if let Some(type_) = cell.cell.output.type_().to_opt() {
-                if type_.code_hash() != ctx.rollup_config.l1_sudt_script_type_hash()
+                if type_.code_hash() != ctx.rollup_config().l1_sudt_script_type_hash()
                     || type_.hash_type() != hash_type
                 {
                     log::debug!(
                         "Invalid deposit sUDT, expect code_hash: {}, hash_type: Type, got: {}, {}",
-                        ctx.rollup_config.l1_sudt_script_type_hash(),
+                        ctx.rollup_config().l1_sudt_script_type_hash(),
                         type_.code_hash(),
                         type_.hash_type()
                     );
@@ -659,7 +659,7 @@ impl BlockProducer {
                     continue;
                 }
                 if ctx
-                    .rollup_config
+                    .rollup_config()
                     .allowed_eoa_type_hashes()
                     .into_iter()
                     .all(|type_hash| script.code_hash() != type_hash)
@@ -678,10 +678,10 @@ impl BlockProducer {
                     );
                     continue;
                 }
-                if &args[..32] != ctx.rollup_script_hash.as_slice() {
+                if &args[..32] != ctx.rollup_script_hash().as_slice() {
                     log::debug!(
                         "Invalid deposit account args, expect rollup_script_hash: {}, got: {}",
-                        hex::encode(ctx.rollup_script_hash.as_slice()),
+                        hex::encode(ctx.rollup_script_hash().as_slice()),
                         hex::encode(&args[..32])
                     );
                     continue;
@@ -232,7 +232,8 @@ fn try_parse_deposition_request(
     cell_data: &Bytes,
     rollup_context: &RollupContext,
 ) -> Option<DepositionRequest> {
-    if cell_output.lock().code_hash() != rollup_context.rollup_config.deposition_script_type_hash()
+    if cell_output.lock().code_hash()
+        != rollup_context.rollup_config().deposition_script_type_hash()
         || cell_output.lock().hash_type() != ScriptHashType::Type.into()
     {
         return None;
@@ -241,7 +242,7 @@ fn try_parse_deposition_request(
     if args.len() < 32 {
         return None;
     }
-    let rollup_type_script_hash: [u8; 32] = rollup_context.rollup_script_hash.into();
+    let rollup_type_script_hash: [u8; 32] = (*rollup_context.rollup_script_hash()).into();
     if args.slice(0..32) != rollup_type_script_hash[..] {
         return None;
     }
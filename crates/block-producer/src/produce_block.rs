@@ -5,9 +5,10 @@
 use crate::withdrawal::AvailableCustodians;
 
 use anyhow::{anyhow, Result};
+use gw_chain::merkle_root::{canonicalize_kv_state, compute_withdrawal_requests_root};
 use gw_common::{
     h256_ext::H256Ext,
-    merkle_utils::{calculate_merkle_root, calculate_state_checkpoint},
+    merkle_utils::{calculate_merkle_root, calculate_state_checkpoint_list},
     smt::Blake2bHasher,
     state::State,
     H256,
@@ -21,8 +22,8 @@ use gw_store::{
 use gw_types::{
     core::Status,
     packed::{
-        AccountMerkleState, BlockInfo, BlockMerkleState, DepositionRequest, GlobalState, L2Block,
-        L2Transaction, RawL2Block, SubmitTransactions, SubmitWithdrawals, TxReceipt,
+        AccountMerkleState, BlockInfo, BlockMerkleState, DepositionRequest, GlobalState, KVPair,
+        L2Block, L2Transaction, RawL2Block, SubmitTransactions, SubmitWithdrawals, TxReceipt,
         WithdrawalRequest,
     },
     prelude::*,
@@ -191,14 +192,7 @@ pub fn produce_block(param: ProduceBlockParam<'_>) -> Result<ProduceBlockResult>
         let receipt = TxReceipt::new_builder()
             .tx_witness_hash(tx_witness_hash.pack())
             .post_state(tx_post_state)
-            .read_data_hashes(
-                run_result
-                    .read_data
-                    .iter()
-                    .map(|(hash, _)| *hash)
-                    .collect::<Vec<_>>()
-                    .pack(),
-            )
+            .read_data_hashes(run_result.read_data_hashes().pack())
             .logs(run_result.logs.pack())
             .build();
         used_transactions.push(tx);
@@ -209,7 +203,7 @@ pub fn produce_block(param: ProduceBlockParam<'_>) -> Result<ProduceBlockResult>
         .tracker_mut()
         .touched_keys()
         .expect("track touched keys")
-        .borrow()
+        .lock()
         .clone()
         .into_iter()
         .collect();
@@ -229,13 +223,17 @@ pub fn produce_block(param: ProduceBlockParam<'_>) -> Result<ProduceBlockResult>
         )
         .map_err(|err| anyhow!("merkle root error: {:?}", err))?;
         let tx_count = tx_receipts.len() as u32;
-        state_checkpoint_list.extend(tx_receipts.iter().map(|tx_receipt| {
-            let post_state = tx_receipt.post_state();
-            calculate_state_checkpoint(
-                &post_state.merkle_root().unpack(),
-                post_state.count().unpack(),
-            )
-        }));
+        let post_states: Vec<(H256, u32)> = tx_receipts
+            .iter()
+            .map(|tx_receipt| {
+                let post_state = tx_receipt.post_state();
+                (
+                    post_state.merkle_root().unpack(),
+                    post_state.count().unpack(),
+                )
+            })
+            .collect();
+        state_checkpoint_list.extend(calculate_state_checkpoint_list(&post_states));
         SubmitTransactions::new_builder()
             .tx_witness_root(tx_witness_root.pack())
             .tx_count(tx_count.pack())
@@ -243,13 +241,7 @@ pub fn produce_block(param: ProduceBlockParam<'_>) -> Result<ProduceBlockResult>
             .build()
     };
     let submit_withdrawals = {
-        let withdrawal_witness_root = calculate_merkle_root(
-            used_withdrawal_requests
-                .iter()
-                .map(|request| request.witness_hash().into())
-                .collect(),
-        )
-        .map_err(|err| anyhow!("merkle root error: {:?}", err))?;
+        let withdrawal_witness_root = compute_withdrawal_requests_root(&used_withdrawal_requests);
         let withdrawal_count = used_withdrawal_requests.len() as u32;
         SubmitWithdrawals::new_builder()
             .withdrawal_witness_root(withdrawal_witness_root.pack())
@@ -293,7 +285,13 @@ pub fn produce_block(param: ProduceBlockParam<'_>) -> Result<ProduceBlockResult>
                 .map_err(|err| anyhow!("can't fetch value error: {:?}", err))
         })
         .collect::<Result<_>>()?;
-    let packed_kv_state = kv_state.pack();
+    let mut kv_pairs: Vec<KVPair> = kv_state.pack().into_iter().collect();
+    canonicalize_kv_state(&mut kv_pairs);
+    let kv_state: Vec<(H256, H256)> = kv_pairs
+        .iter()
+        .map(|kv| (kv.k().unpack(), kv.v().unpack()))
+        .collect();
+    let packed_kv_state = kv_pairs.pack();
     let proof = if kv_state.is_empty() {
         // nothing need to prove
         Vec::new()
@@ -330,7 +328,7 @@ pub fn produce_block(param: ProduceBlockParam<'_>) -> Result<ProduceBlockResult>
             .build()
     };
     let last_finalized_block_number =
-        number.saturating_sub(rollup_context.rollup_config.finality_blocks().unpack());
+        number.saturating_sub(rollup_context.rollup_config().finality_blocks().unpack());
     let global_state = GlobalState::new_builder()
         .account(post_account)
         .block(post_block)
@@ -450,7 +450,7 @@ impl RPCClient {
 
         let rollup_type_hash: Bytes = self
             .rollup_context
-            .rollup_script_hash
+            .rollup_script_hash()
             .as_slice()
             .to_vec()
             .into();
@@ -459,7 +459,7 @@ impl RPCClient {
             .args(rollup_type_hash.pack())
             .code_hash(
                 self.rollup_context
-                    .rollup_config
+                    .rollup_config()
                     .deposition_script_type_hash(),
             )
             .hash_type(ScriptHashType::Type.into())
@@ -548,9 +548,9 @@ impl RPCClient {
         last_finalized_block_number: u64,
     ) -> Result<Option<CellInfo>> {
         let lock = Script::new_builder()
-            .code_hash(rollup_context.rollup_config.stake_script_type_hash())
+            .code_hash(rollup_context.rollup_config().stake_script_type_hash())
             .hash_type(ScriptHashType::Type.into())
-            .args(rollup_context.rollup_script_hash.as_slice().pack())
+            .args(rollup_context.rollup_script_hash().as_slice().pack())
             .build();
 
         let search_key = SearchKey {
@@ -644,9 +644,9 @@ impl RPCClient {
         };
 
         let custodian_lock = Script::new_builder()
-            .code_hash(rollup_context.rollup_config.custodian_script_type_hash())
+            .code_hash(rollup_context.rollup_config().custodian_script_type_hash())
             .hash_type(ScriptHashType::Type.into())
-            .args(rollup_context.rollup_script_hash.as_slice().pack())
+            .args(rollup_context.rollup_script_hash().as_slice().pack())
             .build();
 
         let search_key = SearchKey {
@@ -706,7 +706,7 @@ impl RPCClient {
 
                     // Invalid custodian type script
                     let l1_sudt_script_type_hash =
-                        rollup_context.rollup_config.l1_sudt_script_type_hash();
+                        rollup_context.rollup_config().l1_sudt_script_type_hash();
                     if sudt_type_script.code_hash() != l1_sudt_script_type_hash
                         || sudt_type_script.hash_type() != ScriptHashType::Type.into()
                     {
@@ -782,13 +782,13 @@ impl RPCClient {
         let rollup_context = &self.rollup_context;
 
         let custodian_lock = Script::new_builder()
-            .code_hash(rollup_context.rollup_config.custodian_script_type_hash())
+            .code_hash(rollup_context.rollup_config().custodian_script_type_hash())
             .hash_type(ScriptHashType::Type.into())
-            .args(rollup_context.rollup_script_hash.as_slice().pack())
+            .args(rollup_context.rollup_script_hash().as_slice().pack())
             .build();
 
         let l1_sudt_type = Script::new_builder()
-            .code_hash(rollup_context.rollup_config.l1_sudt_script_type_hash())
+            .code_hash(rollup_context.rollup_config().l1_sudt_script_type_hash())
             .hash_type(ScriptHashType::Type.into())
             .build();
 
@@ -856,9 +856,9 @@ impl RPCClient {
         let rollup_context = &self.rollup_context;
 
         let withdrawal_lock = Script::new_builder()
-            .code_hash(rollup_context.rollup_config.withdrawal_script_type_hash())
+            .code_hash(rollup_context.rollup_config().withdrawal_script_type_hash())
             .hash_type(ScriptHashType::Type.into())
-            .args(rollup_context.rollup_script_hash.as_slice().pack())
+            .args(rollup_context.rollup_script_hash().as_slice().pack())
             .build();
 
         let search_key = SearchKey {
@@ -130,12 +130,13 @@ pub fn run(config: Config) -> Result<()> {
         .clone()
         .ok_or_else(|| anyhow!("not set block producer"))?;
 
-    let rollup_context = RollupContext {
-        rollup_config: rollup_config.clone(),
-        rollup_script_hash: {
-            let rollup_script_hash: [u8; 32] = config.genesis.rollup_type_hash.clone().into();
-            rollup_script_hash.into()
-        },
+    let rollup_context = {
+        let rollup_script_hash: [u8; 32] = config.genesis.rollup_type_hash.clone().into();
+        let polyjuice_chain_id = config.chain.polyjuice_chain_id.ok_or_else(|| {
+            anyhow!("not set polyjuice_chain_id (the polyjuice creator account id) of the chain")
+        })?;
+        RollupContext::new(rollup_script_hash.into(), rollup_config.clone())?
+            .with_polyjuice_chain_id(Some(polyjuice_chain_id))
     };
     let rollup_type_script: Script = config.chain.rollup_type_script.clone().into();
     let rpc_client = {
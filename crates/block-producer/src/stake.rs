@@ -37,7 +37,7 @@ pub async fn generate(
             .stake_block_number(block.raw().number())
             .build();
 
-        let rollup_type_hash = rollup_context.rollup_script_hash.as_slice().iter();
+        let rollup_type_hash = rollup_context.rollup_script_hash().as_slice().iter();
         rollup_type_hash
             .chain(stake_lock_args.as_slice().iter())
             .cloned()
@@ -45,7 +45,7 @@ pub async fn generate(
     };
 
     let lock = Script::new_builder()
-        .code_hash(rollup_context.rollup_config.stake_script_type_hash())
+        .code_hash(rollup_context.rollup_config().stake_script_type_hash())
         .hash_type(ScriptHashType::Type.into())
         .args(lock_args.pack())
         .build();
@@ -88,7 +88,7 @@ pub async fn generate(
     // No unlocked stake, collect free ckb cells to generate one
     let stake_capacity = {
         let required_staking_capacity = rollup_context
-            .rollup_config
+            .rollup_config()
             .required_staking_capacity()
             .unpack();
 
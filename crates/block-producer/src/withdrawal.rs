@@ -396,11 +396,11 @@ pub async fn revert(
     // index corresponding custodian output.
     // NOTE: These locks must also be different from custodian change cells created by
     // withdrawal requests processing.
-    let rollup_type_hash = rollup_context.rollup_script_hash.as_slice().iter();
+    let rollup_type_hash = rollup_context.rollup_script_hash().as_slice().iter();
     for (idx, withdrawal) in reverted_withdrawal_cells.into_iter().enumerate() {
         let custodian_lock = {
             let deposition_lock_args = DepositionLockArgs::new_builder()
-                .owner_lock_hash(rollup_context.rollup_script_hash.pack())
+                .owner_lock_hash(rollup_context.rollup_script_hash().pack())
                 .cancel_timeout((idx as u64 + timestamp).pack())
                 .build();
 
@@ -415,7 +415,7 @@ pub async fn revert(
                 .collect();
 
             Script::new_builder()
-                .code_hash(rollup_context.rollup_config.custodian_script_type_hash())
+                .code_hash(rollup_context.rollup_config().custodian_script_type_hash())
                 .hash_type(ScriptHashType::Type.into())
                 .args(lock_args.pack())
                 .build()
@@ -521,7 +521,7 @@ fn build_withdrawal_lock(
             .payment_lock_hash(req.raw().payment_lock_hash())
             .build();
 
-        let rollup_type_hash = rollup_context.rollup_script_hash.as_slice().iter();
+        let rollup_type_hash = rollup_context.rollup_script_hash().as_slice().iter();
         rollup_type_hash
             .chain(withdrawal_lock_args.as_slice().iter())
             .cloned()
@@ -529,14 +529,14 @@ fn build_withdrawal_lock(
     };
 
     Script::new_builder()
-        .code_hash(rollup_context.rollup_config.withdrawal_script_type_hash())
+        .code_hash(rollup_context.rollup_config().withdrawal_script_type_hash())
         .hash_type(ScriptHashType::Type.into())
         .args(lock_args.pack())
         .build()
 }
 
 fn build_finalized_custodian_lock(rollup_context: &RollupContext) -> Script {
-    let rollup_type_hash = rollup_context.rollup_script_hash.as_slice().iter();
+    let rollup_type_hash = rollup_context.rollup_script_hash().as_slice().iter();
     let custodian_lock_args = CustodianLockArgs::default();
 
     let args: Bytes = rollup_type_hash
@@ -545,7 +545,7 @@ fn build_finalized_custodian_lock(rollup_context: &RollupContext) -> Script {
         .collect();
 
     Script::new_builder()
-        .code_hash(rollup_context.rollup_config.custodian_script_type_hash())
+        .code_hash(rollup_context.rollup_config().custodian_script_type_hash())
         .hash_type(ScriptHashType::Type.into())
         .args(args.pack())
         .build()
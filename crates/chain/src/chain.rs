@@ -137,7 +137,7 @@ impl Chain {
         // convert serde types to gw-types
         assert_eq!(
             rollup_config,
-            &generator.rollup_context().rollup_config,
+            generator.rollup_context().rollup_config(),
             "check generator rollup config"
         );
         let rollup_type_script_hash = rollup_type_script.hash();
@@ -326,7 +326,7 @@ impl Chain {
                     self.local_state.tip.hash(),
                     "reverted l2block must be current tip"
                 );
-                let rollup_config = &self.generator().rollup_context().rollup_config;
+                let rollup_config = self.generator().rollup_context().rollup_config();
                 db.detach_block(&l2block, rollup_config)?;
 
                 // check reverted state
@@ -507,7 +507,7 @@ impl Chain {
             result.receipts,
             deposition_requests,
         )?;
-        let rollup_config = &self.generator.rollup_context().rollup_config;
+        let rollup_config = self.generator.rollup_context().rollup_config();
         db.attach_block(l2block.clone(), rollup_config)?;
         tree.submit_tree()?;
         let post_merkle_root: H256 = l2block.raw().post_account().merkle_root().unpack();
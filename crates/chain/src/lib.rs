@@ -7,3 +7,5 @@
 //! * Submit new blocks to layer1(as an block_producer)
 
 pub mod chain;
+pub mod merkle_root;
+pub mod validation;
@@ -0,0 +1,139 @@
+//! Compute merkle roots committed in the layer2 block
+
+use gw_common::{merkle_utils::calculate_merkle_root, H256};
+use gw_types::{
+    packed::{KVPair, L2Transaction, WithdrawalRequest},
+    prelude::*,
+};
+
+/// Compute the `withdrawal_witness_root` of the block,
+/// leaves are witness hashes of the requests which are indexed by their position.
+/// Returns zero if there are no requests
+pub fn compute_withdrawal_requests_root(requests: &[WithdrawalRequest]) -> H256 {
+    let leaves = requests
+        .iter()
+        .map(|request| request.witness_hash().into())
+        .collect();
+    calculate_merkle_root(leaves).expect("compute withdrawal requests root")
+}
+
+/// Compute the `tx_witness_root` of the block,
+/// leaves are witness hashes of the transactions which are indexed by their position.
+/// Returns zero if there are no transactions
+pub fn compute_tx_witness_root(txs: &[L2Transaction]) -> H256 {
+    let leaves = txs.iter().map(|tx| tx.witness_hash().into()).collect();
+    calculate_merkle_root(leaves).expect("compute tx witness root")
+}
+
+/// Sort the `kv_state` of a block in the SMT key order (the `Ord` of `H256`) and remove
+/// duplicated keys, the last value of a key wins. So the same state is always serialized the
+/// same way, and in the same order as `canonical_kv_state` of the JSON RPC types
+pub fn canonicalize_kv_state(kv: &mut Vec<KVPair>) {
+    fn key_of(kv: &KVPair) -> H256 {
+        kv.k().unpack()
+    }
+    // reverse before the stable sort, so the last value of a key is kept by the dedup
+    kv.reverse();
+    kv.sort_by_key(key_of);
+    kv.dedup_by_key(|kv| key_of(kv));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gw_common::smt::{default_store::DefaultStore, Blake2bHasher, SMT};
+    use gw_types::packed::RawWithdrawalRequest;
+
+    fn build_request(nonce: u32) -> WithdrawalRequest {
+        let raw = RawWithdrawalRequest::new_builder()
+            .nonce(nonce.pack())
+            .build();
+        WithdrawalRequest::new_builder().raw(raw).build()
+    }
+
+    #[test]
+    fn test_compute_withdrawal_requests_root() {
+        assert_eq!(compute_withdrawal_requests_root(&[]), H256::zero());
+
+        let one = vec![build_request(0)];
+        let root = compute_withdrawal_requests_root(&one);
+        assert_ne!(root, H256::zero());
+        assert_eq!(root, compute_withdrawal_requests_root(&one));
+
+        let several: Vec<_> = (0..5).map(build_request).collect();
+        let root = compute_withdrawal_requests_root(&several);
+        assert_eq!(root, compute_withdrawal_requests_root(&several));
+        assert_ne!(root, compute_withdrawal_requests_root(&several[..4]));
+
+        // the root commits to the order of requests
+        let mut reversed = several.clone();
+        reversed.reverse();
+        assert_ne!(root, compute_withdrawal_requests_root(&reversed));
+    }
+
+    fn build_kv(k: u8, v: u8) -> KVPair {
+        let mut key = [0u8; 32];
+        key[31] = k;
+        KVPair::new_builder()
+            .k(key.pack())
+            .v([v; 32].pack())
+            .build()
+    }
+
+    #[test]
+    fn test_canonicalize_kv_state() {
+        let mut kv_state = vec![
+            build_kv(3, 1),
+            build_kv(1, 1),
+            build_kv(2, 1),
+            build_kv(1, 2),
+        ];
+        let mut shuffled = vec![
+            build_kv(2, 1),
+            build_kv(1, 1),
+            build_kv(3, 1),
+            build_kv(1, 2),
+        ];
+        canonicalize_kv_state(&mut kv_state);
+        canonicalize_kv_state(&mut shuffled);
+        let expected = vec![build_kv(1, 2), build_kv(2, 1), build_kv(3, 1)];
+        assert_eq!(kv_state, expected);
+        assert_eq!(shuffled, expected);
+
+        // the proof of the canonical kv state still verifies
+        let leaves: Vec<(H256, H256)> = kv_state
+            .iter()
+            .map(|kv| (kv.k().unpack(), kv.v().unpack()))
+            .collect();
+        let mut tree = SMT::<DefaultStore<H256>>::default();
+        for (k, v) in &leaves {
+            tree.update(*k, *v).unwrap();
+        }
+        tree.update(build_kv(4, 1).k().unpack(), [4u8; 32].into())
+            .unwrap();
+        let proof = tree
+            .merkle_proof(leaves.iter().map(|(k, _)| *k).collect())
+            .unwrap()
+            .compile(leaves.clone())
+            .unwrap();
+        assert!(proof.verify::<Blake2bHasher>(tree.root(), leaves).unwrap());
+    }
+
+    #[test]
+    fn test_canonicalize_kv_state_in_smt_order() {
+        // the keys differ in both the lowest and the highest byte,
+        // the highest byte is compared first
+        let build_kv = |first: u8, last: u8| {
+            let mut key = [0u8; 32];
+            key[0] = first;
+            key[31] = last;
+            KVPair::new_builder()
+                .k(key.pack())
+                .v([1u8; 32].pack())
+                .build()
+        };
+        let mut kv_state = vec![build_kv(1, 2), build_kv(2, 1)];
+        canonicalize_kv_state(&mut kv_state);
+        assert_eq!(kv_state, vec![build_kv(2, 1), build_kv(1, 2)]);
+    }
+}
@@ -0,0 +1,676 @@
+//! Validate the transition of rollup states
+
+use crate::merkle_root::compute_tx_witness_root;
+use gw_common::{
+    error::{Error, ValidationError},
+    h256_ext::H256Ext,
+    merkle_utils::calculate_state_checkpoint,
+    smt::{Blake2bHasher, CompiledMerkleProof},
+    state::State,
+    H256,
+};
+use gw_generator::{is_block_finalized, RollupContext};
+use gw_types::{
+    bytes::Bytes,
+    core::{ChallengeTargetType, Status},
+    packed::{CancelChallenge, ChallengeWitness, GlobalState, L2Block, RawL2Block, TxReceipt},
+    prelude::*,
+};
+use std::convert::TryFrom;
+
+/// Validate the `next` global state follows from the `prev` one
+///
+/// * account count and block count can't decrease
+/// * last finalized block number can't decrease
+/// * new blocks can only be submitted while the rollup is running,
+///   otherwise the status can switch between Running and Halting
+pub fn validate_global_state_transition(
+    prev: &GlobalState,
+    next: &GlobalState,
+) -> Result<(), ValidationError> {
+    let prev_account_count: u32 = prev.account().count().unpack();
+    let next_account_count: u32 = next.account().count().unpack();
+    if next_account_count < prev_account_count {
+        return Err(ValidationError::AccountCountRegression {
+            prev: prev_account_count,
+            next: next_account_count,
+        });
+    }
+
+    let prev_block_count: u64 = prev.block().count().unpack();
+    let next_block_count: u64 = next.block().count().unpack();
+    if next_block_count < prev_block_count {
+        return Err(ValidationError::BlockCountRegression {
+            prev: prev_block_count,
+            next: next_block_count,
+        });
+    }
+
+    let prev_finalized: u64 = prev.last_finalized_block_number().unpack();
+    let next_finalized: u64 = next.last_finalized_block_number().unpack();
+    if next_finalized < prev_finalized {
+        return Err(ValidationError::FinalizedBlockNumberRegression {
+            prev: prev_finalized,
+            next: next_finalized,
+        });
+    }
+
+    let invalid_status = || {
+        let prev: u8 = prev.status().into();
+        let next: u8 = next.status().into();
+        ValidationError::InvalidStatusTransition { prev, next }
+    };
+    let prev_status = Status::try_from(prev.status()).map_err(|_| invalid_status())?;
+    let next_status = Status::try_from(next.status()).map_err(|_| invalid_status())?;
+    let is_new_block_submitted = next_block_count > prev_block_count;
+    let is_running = prev_status == Status::Running && next_status == Status::Running;
+    if is_new_block_submitted && !is_running {
+        return Err(invalid_status());
+    }
+
+    Ok(())
+}
+
+/// Validate the challenged block is still in the challenge period
+pub fn validate_challengeable(
+    ctx: &RollupContext,
+    challenged_block_number: u64,
+    global_state: &GlobalState,
+) -> Result<(), ValidationError> {
+    let last_finalized_block_number: u64 = global_state.last_finalized_block_number().unpack();
+    let block_count: u64 = global_state.block().count().unpack();
+    let tip_block_number = block_count.saturating_sub(1);
+    if challenged_block_number <= last_finalized_block_number
+        || is_block_finalized(ctx, challenged_block_number, tip_block_number)
+    {
+        return Err(ValidationError::BlockAlreadyFinalized {
+            block_number: challenged_block_number,
+            last_finalized_block_number,
+        });
+    }
+    Ok(())
+}
+
+/// Validate the block timestamp doesn't go backward from its parent
+pub fn validate_block_timestamp(
+    parent: &RawL2Block,
+    block: &RawL2Block,
+) -> Result<(), ValidationError> {
+    let prev: u64 = parent.timestamp().unpack();
+    let next: u64 = block.timestamp().unpack();
+    if next < prev {
+        return Err(ValidationError::NonMonotonicTimestamp { prev, next });
+    }
+    Ok(())
+}
+
+/// Validate the `tx_witness_root` of the block commits to its transactions
+pub fn validate_tx_witness_root(block: &L2Block) -> Result<(), ValidationError> {
+    let expected: H256 = block.raw().submit_transactions().tx_witness_root().unpack();
+    let actual = compute_tx_witness_root(&block.transactions().into_iter().collect::<Vec<_>>());
+    if expected != actual {
+        return Err(ValidationError::TxWitnessRootMismatch { expected, actual });
+    }
+    Ok(())
+}
+
+/// Validate the account root of the state after applying the block
+/// equals the `post_account` root the block commits to
+pub fn verify_post_account_state<S: State>(
+    state: &S,
+    raw_block: &RawL2Block,
+) -> Result<(), ValidationError> {
+    let expected: H256 = raw_block.post_account().merkle_root().unpack();
+    let actual = state.calculate_root()?;
+    if expected != actual {
+        return Err(ValidationError::PostAccountRootMismatch { expected, actual });
+    }
+    Ok(())
+}
+
+/// The capacity a challenger must lock to start a challenge,
+/// it equals the `required_staking_capacity` a block producer stakes,
+/// so a challenge costs as much as the stake it can slash
+pub fn challenge_bond(ctx: &RollupContext) -> u64 {
+    ctx.rollup_config().required_staking_capacity().unpack()
+}
+
+/// Validate the challenger provides at least the challenge bond
+pub fn validate_challenge_bond(ctx: &RollupContext, provided: u64) -> Result<(), ValidationError> {
+    let required = challenge_bond(ctx);
+    if provided < required {
+        return Err(ValidationError::InsufficientChallengeBond { required, provided });
+    }
+    Ok(())
+}
+
+/// Verify the challenged block is committed under the block merkle root
+pub fn verify_block_proof(
+    block_merkle_root: &H256,
+    witness: &ChallengeWitness,
+) -> Result<bool, Error> {
+    let raw_block = witness.raw_l2block();
+    let block_proof: Bytes = witness.block_proof().unpack();
+    let leaves = vec![(raw_block.smt_key().into(), raw_block.hash().into())];
+    let valid = CompiledMerkleProof(block_proof.to_vec())
+        .verify::<Blake2bHasher>(block_merkle_root, leaves)?;
+    Ok(valid)
+}
+
+/// Verify the proofs of a cancel challenge are consistent with the challenged block
+///
+/// * the target is a tx execution target in the block of the witness
+/// * `tx_proof` proves `l2tx` is the tx at the target index under the `tx_witness_root` of the block
+/// * `kv_state_proof` proves `kv_state` against the `prev_account` root of the block
+pub fn verify_cancel_challenge(
+    _ctx: &RollupContext,
+    cancel: &CancelChallenge,
+) -> Result<(), ValidationError> {
+    let target = cancel.target();
+    let witness = cancel.witness();
+    let raw_block = witness.raw_l2block();
+    let target_type: u8 = target.target_type().into();
+    let target_index: u32 = target.target_index().unpack();
+    let submit_txs = raw_block.submit_transactions();
+    let tx_count: u32 = submit_txs.tx_count().unpack();
+    if ChallengeTargetType::try_from(target_type) != Ok(ChallengeTargetType::TxExecution)
+        || target_index >= tx_count
+    {
+        return Err(ValidationError::InvalidChallengeTarget {
+            target_type,
+            target_index,
+        });
+    }
+    let block_hash: H256 = raw_block.hash().into();
+    let target_block_hash: H256 = target.block_hash().unpack();
+    if block_hash != target_block_hash {
+        return Err(ValidationError::ChallengeBlockMismatch {
+            expected: target_block_hash,
+            actual: block_hash,
+        });
+    }
+
+    let tx_witness_root: H256 = submit_txs.tx_witness_root().unpack();
+    let leaves = vec![(
+        H256::from_u32(target_index),
+        witness.l2tx().witness_hash().into(),
+    )];
+    let tx_proof: Bytes = witness.tx_proof().unpack();
+    let tx_included = matches!(
+        CompiledMerkleProof(tx_proof.to_vec()).verify::<Blake2bHasher>(&tx_witness_root, leaves),
+        Ok(true)
+    );
+    if !tx_included {
+        return Err(ValidationError::InvalidTxProof);
+    }
+
+    let kv_state: Vec<(H256, H256)> = witness
+        .context()
+        .kv_state()
+        .into_iter()
+        .map(|kv| kv.unpack())
+        .collect();
+    if kv_state.is_empty() {
+        return Ok(());
+    }
+    let prev_account_root: H256 = raw_block.prev_account().merkle_root().unpack();
+    let kv_state_proof: Bytes = witness.kv_state_proof().unpack();
+    let valid = matches!(
+        CompiledMerkleProof(kv_state_proof.to_vec())
+            .verify::<Blake2bHasher>(&prev_account_root, kv_state),
+        Ok(true)
+    );
+    if !valid {
+        return Err(ValidationError::InvalidKVStateProof);
+    }
+    Ok(())
+}
+
+/// Verify the post state of a receipt matches the state, e.g. after executing the tx of a peer's receipt.
+/// Both are compared by the state checkpoint `blake2b(account_root | account_count)`
+pub fn verify_receipt_against_state<S: State>(
+    state: &S,
+    receipt: &TxReceipt,
+) -> Result<bool, Error> {
+    let post_state = receipt.post_state();
+    let expected = calculate_state_checkpoint(
+        &post_state.merkle_root().unpack(),
+        post_state.count().unpack(),
+    );
+    Ok(state.calculate_state_checkpoint()? == expected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gw_common::smt::{default_store::DefaultStore, SMT};
+    use gw_generator::dummy_state::DummyState;
+    use gw_types::packed::{
+        AccountMerkleState, BlockMerkleState, ChallengeTarget, L2Transaction, RawL2Transaction,
+        RollupConfig, SubmitTransactions, VerifyTransactionContext, VerifyTransactionWitness,
+    };
+
+    fn build_global_state(
+        account_count: u32,
+        block_count: u64,
+        last_finalized_block_number: u64,
+        status: u8,
+    ) -> GlobalState {
+        GlobalState::new_builder()
+            .account(
+                AccountMerkleState::new_builder()
+                    .count(account_count.pack())
+                    .build(),
+            )
+            .block(
+                BlockMerkleState::new_builder()
+                    .count(block_count.pack())
+                    .build(),
+            )
+            .last_finalized_block_number(last_finalized_block_number.pack())
+            .status(status.into())
+            .build()
+    }
+
+    #[test]
+    fn test_valid_advance() {
+        let running = Status::Running as u8;
+        let prev = build_global_state(3, 10, 5, running);
+        let next = build_global_state(4, 11, 6, running);
+        assert_eq!(validate_global_state_transition(&prev, &next), Ok(()));
+
+        // halt and resume the rollup without submitting blocks
+        let halting = Status::Halting as u8;
+        let halted = build_global_state(4, 11, 6, halting);
+        assert_eq!(validate_global_state_transition(&next, &halted), Ok(()));
+        assert_eq!(validate_global_state_transition(&halted, &next), Ok(()));
+    }
+
+    #[test]
+    fn test_regressing_finalized_block_number() {
+        let running = Status::Running as u8;
+        let prev = build_global_state(3, 10, 5, running);
+        let next = build_global_state(3, 11, 4, running);
+        assert_eq!(
+            validate_global_state_transition(&prev, &next),
+            Err(ValidationError::FinalizedBlockNumberRegression { prev: 5, next: 4 })
+        );
+    }
+
+    #[test]
+    fn test_illegal_status_change() {
+        let running = Status::Running as u8;
+        let halting = Status::Halting as u8;
+
+        // submit a block while the rollup is halting
+        let prev = build_global_state(3, 10, 5, halting);
+        let next = build_global_state(3, 11, 5, running);
+        assert_eq!(
+            validate_global_state_transition(&prev, &next),
+            Err(ValidationError::InvalidStatusTransition {
+                prev: halting,
+                next: running
+            })
+        );
+
+        // unknown status
+        let prev = build_global_state(3, 10, 5, running);
+        let next = build_global_state(3, 10, 5, 2);
+        assert_eq!(
+            validate_global_state_transition(&prev, &next),
+            Err(ValidationError::InvalidStatusTransition {
+                prev: running,
+                next: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate_challengeable() {
+        let ctx = RollupContext::new_unchecked(
+            Default::default(),
+            RollupConfig::new_builder()
+                .finality_blocks(10u64.pack())
+                .build(),
+        );
+        // tip block is 100
+        let global_state = build_global_state(3, 101, 90, Status::Running as u8);
+        assert_eq!(validate_challengeable(&ctx, 91, &global_state), Ok(()));
+        assert_eq!(validate_challengeable(&ctx, 100, &global_state), Ok(()));
+        assert_eq!(
+            validate_challengeable(&ctx, 90, &global_state),
+            Err(ValidationError::BlockAlreadyFinalized {
+                block_number: 90,
+                last_finalized_block_number: 90
+            })
+        );
+        assert!(validate_challengeable(&ctx, 0, &global_state).is_err());
+    }
+
+    #[test]
+    fn test_validate_block_timestamp() {
+        let build_block = |timestamp: u64| {
+            RawL2Block::new_builder()
+                .timestamp(timestamp.pack())
+                .build()
+        };
+        let parent = build_block(100);
+        assert_eq!(validate_block_timestamp(&parent, &build_block(101)), Ok(()));
+        assert_eq!(validate_block_timestamp(&parent, &build_block(100)), Ok(()));
+        assert_eq!(
+            validate_block_timestamp(&parent, &build_block(99)),
+            Err(ValidationError::NonMonotonicTimestamp {
+                prev: 100,
+                next: 99
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate_tx_witness_root() {
+        let txs: Vec<L2Transaction> = (0u32..3)
+            .map(|nonce| {
+                let raw = RawL2Transaction::new_builder().nonce(nonce.pack()).build();
+                L2Transaction::new_builder().raw(raw).build()
+            })
+            .collect();
+        let build_block = |tx_witness_root: H256| {
+            let submit_txs = SubmitTransactions::new_builder()
+                .tx_witness_root(tx_witness_root.pack())
+                .tx_count((txs.len() as u32).pack())
+                .build();
+            let raw = RawL2Block::new_builder()
+                .submit_transactions(submit_txs)
+                .build();
+            L2Block::new_builder()
+                .raw(raw)
+                .transactions(txs.clone().pack())
+                .build()
+        };
+
+        let root = compute_tx_witness_root(&txs);
+        assert_eq!(validate_tx_witness_root(&build_block(root)), Ok(()));
+        assert_eq!(
+            validate_tx_witness_root(&build_block(H256::zero())),
+            Err(ValidationError::TxWitnessRootMismatch {
+                expected: H256::zero(),
+                actual: root
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate_challenge_bond() {
+        let ctx = RollupContext::new_unchecked(
+            Default::default(),
+            RollupConfig::new_builder()
+                .required_staking_capacity(500_00000000u64.pack())
+                .build(),
+        );
+        assert_eq!(challenge_bond(&ctx), 500_00000000);
+        assert_eq!(validate_challenge_bond(&ctx, 500_00000000), Ok(()));
+        assert_eq!(validate_challenge_bond(&ctx, 600_00000000), Ok(()));
+        assert_eq!(
+            validate_challenge_bond(&ctx, 500_00000000 - 1),
+            Err(ValidationError::InsufficientChallengeBond {
+                required: 500_00000000,
+                provided: 500_00000000 - 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_verify_receipt_against_state() {
+        let mut state = DummyState::default();
+        state.create_account([1u8; 32].into()).unwrap();
+        state.create_account([2u8; 32].into()).unwrap();
+        let build_receipt = |root: H256, count: u32| {
+            let post_state = AccountMerkleState::new_builder()
+                .merkle_root(root.pack())
+                .count(count.pack())
+                .build();
+            TxReceipt::new_builder().post_state(post_state).build()
+        };
+        let root = state.calculate_root().unwrap();
+
+        let receipt = build_receipt(root, 2);
+        assert_eq!(verify_receipt_against_state(&state, &receipt), Ok(true));
+        let receipt = build_receipt(root, 3);
+        assert_eq!(verify_receipt_against_state(&state, &receipt), Ok(false));
+        let receipt = build_receipt([42u8; 32].into(), 2);
+        assert_eq!(verify_receipt_against_state(&state, &receipt), Ok(false));
+    }
+
+    #[test]
+    fn test_verify_post_account_state() {
+        let mut state = DummyState::default();
+        state.create_account([1u8; 32].into()).unwrap();
+        let build_raw_block = |root: H256| {
+            let post_account = AccountMerkleState::new_builder()
+                .merkle_root(root.pack())
+                .count(1u32.pack())
+                .build();
+            RawL2Block::new_builder().post_account(post_account).build()
+        };
+        let root = state.calculate_root().unwrap();
+        assert_eq!(
+            verify_post_account_state(&state, &build_raw_block(root)),
+            Ok(())
+        );
+
+        // the state diverges from the block
+        state.create_account([2u8; 32].into()).unwrap();
+        let diverged_root = state.calculate_root().unwrap();
+        assert_eq!(
+            verify_post_account_state(&state, &build_raw_block(root)),
+            Err(ValidationError::PostAccountRootMismatch {
+                expected: root,
+                actual: diverged_root
+            })
+        );
+    }
+
+    #[test]
+    fn test_verify_block_proof() {
+        let blocks: Vec<RawL2Block> = (0u64..3)
+            .map(|number| {
+                RawL2Block::new_builder()
+                    .number(number.pack())
+                    .parent_block_hash([number as u8; 32].pack())
+                    .build()
+            })
+            .collect();
+        let mut tree = SMT::<DefaultStore<H256>>::default();
+        for block in &blocks {
+            tree.update(block.smt_key().into(), block.hash().into())
+                .unwrap();
+        }
+        let root = *tree.root();
+        let block_proof = tree
+            .merkle_proof(vec![blocks[1].smt_key().into()])
+            .unwrap()
+            .compile(vec![(blocks[1].smt_key().into(), blocks[1].hash().into())])
+            .unwrap();
+        let build_witness = |raw_block: &RawL2Block| {
+            ChallengeWitness::new_builder()
+                .raw_l2block(raw_block.to_owned())
+                .block_proof(block_proof.0.pack())
+                .build()
+        };
+
+        let witness = build_witness(&blocks[1]);
+        assert_eq!(verify_block_proof(&root, &witness), Ok(true));
+        assert!(!verify_block_proof(&H256::zero(), &witness).unwrap_or(false));
+        // proof of the wrong block
+        let witness = build_witness(&blocks[2]);
+        assert!(!verify_block_proof(&root, &witness).unwrap_or(false));
+    }
+
+    #[test]
+    fn test_verify_cancel_challenge() {
+        let txs: Vec<L2Transaction> = (0u32..3)
+            .map(|nonce| {
+                L2Transaction::new_builder()
+                    .raw(RawL2Transaction::new_builder().nonce(nonce.pack()).build())
+                    .build()
+            })
+            .collect();
+        let mut tx_tree = SMT::<DefaultStore<H256>>::default();
+        for (index, tx) in txs.iter().enumerate() {
+            tx_tree
+                .update(H256::from_u32(index as u32), tx.witness_hash().into())
+                .unwrap();
+        }
+        let tx_proof = |index: u32| {
+            let key = H256::from_u32(index);
+            tx_tree
+                .merkle_proof(vec![key])
+                .unwrap()
+                .compile(vec![(key, txs[index as usize].witness_hash().into())])
+                .unwrap()
+                .0
+        };
+
+        let mut account_tree = SMT::<DefaultStore<H256>>::default();
+        for i in 1u8..=4 {
+            account_tree.update([i; 32].into(), [i; 32].into()).unwrap();
+        }
+        let kv_state: Vec<(H256, H256)> = vec![([1u8; 32].into(), [1u8; 32].into())];
+        let kv_state_proof = account_tree
+            .merkle_proof(vec![kv_state[0].0])
+            .unwrap()
+            .compile(kv_state.clone())
+            .unwrap()
+            .0;
+
+        let raw_block = RawL2Block::new_builder()
+            .prev_account(
+                AccountMerkleState::new_builder()
+                    .merkle_root(account_tree.root().pack())
+                    .count(4u32.pack())
+                    .build(),
+            )
+            .submit_transactions(
+                SubmitTransactions::new_builder()
+                    .tx_witness_root(tx_tree.root().pack())
+                    .tx_count(3u32.pack())
+                    .build(),
+            )
+            .build();
+        let witness = VerifyTransactionWitness::new_builder()
+            .l2tx(txs[1].clone())
+            .raw_l2block(raw_block.clone())
+            .tx_proof(tx_proof(1).pack())
+            .kv_state_proof(kv_state_proof.pack())
+            .context(
+                VerifyTransactionContext::new_builder()
+                    .kv_state(kv_state.pack())
+                    .build(),
+            )
+            .build();
+        let build_cancel = |witness: VerifyTransactionWitness, target_index: u32| {
+            let target = ChallengeTarget::new_builder()
+                .block_hash(witness.raw_l2block().hash().pack())
+                .target_index(target_index.pack())
+                .target_type(ChallengeTargetType::TxExecution.into())
+                .build();
+            CancelChallenge::new_builder()
+                .target(target)
+                .witness(witness)
+                .build()
+        };
+        let ctx = RollupContext::new_unchecked(H256::zero(), RollupConfig::default());
+        let cancel = build_cancel(witness.clone(), 1);
+        assert_eq!(verify_cancel_challenge(&ctx, &cancel), Ok(()));
+
+        // the tx is only verified at the target index
+        assert_eq!(
+            verify_cancel_challenge(&ctx, &build_cancel(witness.clone(), 0)),
+            Err(ValidationError::InvalidTxProof)
+        );
+        assert_eq!(
+            verify_cancel_challenge(&ctx, &build_cancel(witness.clone(), 3)),
+            Err(ValidationError::InvalidChallengeTarget {
+                target_type: 0,
+                target_index: 3,
+            })
+        );
+        // tampered target
+        let target = cancel
+            .target()
+            .as_builder()
+            .target_type(ChallengeTargetType::Withdrawal.into())
+            .build();
+        let tampered = cancel.clone().as_builder().target(target).build();
+        assert_eq!(
+            verify_cancel_challenge(&ctx, &tampered),
+            Err(ValidationError::InvalidChallengeTarget {
+                target_type: 2,
+                target_index: 1,
+            })
+        );
+        let target = cancel
+            .target()
+            .as_builder()
+            .block_hash([1u8; 32].pack())
+            .build();
+        let tampered = cancel.as_builder().target(target).build();
+        assert_eq!(
+            verify_cancel_challenge(&ctx, &tampered),
+            Err(ValidationError::ChallengeBlockMismatch {
+                expected: [1u8; 32].into(),
+                actual: raw_block.hash().into(),
+            })
+        );
+        // tampered l2tx
+        let tampered = witness.clone().as_builder().l2tx(txs[2].clone()).build();
+        assert_eq!(
+            verify_cancel_challenge(&ctx, &build_cancel(tampered, 1)),
+            Err(ValidationError::InvalidTxProof)
+        );
+        // tampered tx_proof
+        let tampered = witness
+            .clone()
+            .as_builder()
+            .tx_proof(tx_proof(0).pack())
+            .build();
+        assert_eq!(
+            verify_cancel_challenge(&ctx, &build_cancel(tampered, 1)),
+            Err(ValidationError::InvalidTxProof)
+        );
+        // tampered raw_l2block
+        let tampered_block = raw_block
+            .as_builder()
+            .prev_account(AccountMerkleState::default())
+            .build();
+        let tampered = witness
+            .clone()
+            .as_builder()
+            .raw_l2block(tampered_block)
+            .build();
+        assert_eq!(
+            verify_cancel_challenge(&ctx, &build_cancel(tampered, 1)),
+            Err(ValidationError::InvalidKVStateProof)
+        );
+        // tampered kv_state
+        let context = witness
+            .context()
+            .as_builder()
+            .kv_state(vec![(H256::from([1u8; 32]), H256::from([2u8; 32]))].pack())
+            .build();
+        let tampered = witness.clone().as_builder().context(context).build();
+        assert_eq!(
+            verify_cancel_challenge(&ctx, &build_cancel(tampered, 1)),
+            Err(ValidationError::InvalidKVStateProof)
+        );
+        // tampered kv_state_proof
+        let tampered = witness
+            .as_builder()
+            .kv_state_proof(Vec::<u8>::new().pack())
+            .build();
+        assert_eq!(
+            verify_cancel_challenge(&ctx, &build_cancel(tampered, 1)),
+            Err(ValidationError::InvalidKVStateProof)
+        );
+    }
+}
@@ -0,0 +1,83 @@
+//! Verification core of the secp256k1 account locks
+//!
+//! The signature recovery lives in the off-chain generator,
+//! this module only contains the `no_std` parts which can be shared with on-chain validators.
+
+use crate::{blake2b::new_blake2b, H256};
+
+/// Lock args: rollup_type_hash(32 bytes) | pubkey_hash(20 bytes)
+pub const SECP256K1_LOCK_ARGS_LEN: usize = 52;
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+        use thiserror::Error;
+        #[derive(Error, Debug, Eq, PartialEq, Clone)]
+        pub enum LockError {
+            #[error("Invalid lock args length expected {expected} actual {actual}")]
+            InvalidLockArgs { expected: usize, actual: usize },
+        }
+    } else {
+        #[derive(Debug, Eq, PartialEq, Clone)]
+        pub enum LockError {
+            InvalidLockArgs { expected: usize, actual: usize },
+        }
+    }
+}
+
+/// Return the pubkey hash in the lock args
+pub fn extract_pubkey_hash(lock_args: &[u8]) -> Result<[u8; 20], LockError> {
+    if lock_args.len() != SECP256K1_LOCK_ARGS_LEN {
+        return Err(LockError::InvalidLockArgs {
+            expected: SECP256K1_LOCK_ARGS_LEN,
+            actual: lock_args.len(),
+        });
+    }
+    let mut pubkey_hash = [0u8; 20];
+    pubkey_hash.copy_from_slice(&lock_args[32..SECP256K1_LOCK_ARGS_LEN]);
+    Ok(pubkey_hash)
+}
+
+/// Return true if the pubkey hash recovered from the signature matches the lock args
+pub fn verify_pubkey_hash(lock_args: &[u8], pubkey_hash: &[u8; 20]) -> Result<bool, LockError> {
+    let expected_pubkey_hash = extract_pubkey_hash(lock_args)?;
+    Ok(&expected_pubkey_hash == pubkey_hash)
+}
+
+/// Bind the signing message to a chain: `blake2b(chain_id(u32 LE) | message)`
+pub fn calc_chain_id_tagged_message(chain_id: u32, message: &H256) -> H256 {
+    let mut buf = [0u8; 32];
+    let mut hasher = new_blake2b();
+    hasher.update(&chain_id.to_le_bytes());
+    hasher.update(message.as_slice());
+    hasher.finalize(&mut buf);
+    buf.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_pubkey_hash() {
+        let mut lock_args = [0u8; SECP256K1_LOCK_ARGS_LEN];
+        lock_args[32..].copy_from_slice(&[7u8; 20]);
+        assert_eq!(extract_pubkey_hash(&lock_args), Ok([7u8; 20]));
+        assert_eq!(verify_pubkey_hash(&lock_args, &[7u8; 20]), Ok(true));
+        assert_eq!(verify_pubkey_hash(&lock_args, &[8u8; 20]), Ok(false));
+        assert_eq!(
+            verify_pubkey_hash(&lock_args[..20], &[7u8; 20]),
+            Err(LockError::InvalidLockArgs {
+                expected: 52,
+                actual: 20
+            })
+        );
+    }
+
+    #[test]
+    fn test_chain_id_tagged_message() {
+        let message = H256::from([1u8; 32]);
+        let tagged = calc_chain_id_tagged_message(42, &message);
+        assert_ne!(tagged, message);
+        assert_ne!(tagged, calc_chain_id_tagged_message(43, &message));
+    }
+}
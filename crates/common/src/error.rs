@@ -1,4 +1,5 @@
 use crate::smt::Error as SMTError;
+use crate::H256;
 
 cfg_if::cfg_if! {
     if #[cfg(feature = "std")] {
@@ -15,6 +16,14 @@ cfg_if::cfg_if! {
             MissingKey,
             #[error("Store error")]
             Store,
+            #[error("No account to remove")]
+            NoAccountToRemove,
+            #[error("Account already exists")]
+            AccountAlreadyExists,
+            #[error("Key is out of the overlay")]
+            KeyOutOfOverlay,
+            #[error("Unsupported by the overlay")]
+            UnsupportedByOverlay,
         }
     } else {
         #[derive(Debug, Eq, PartialEq, Clone)]
@@ -24,6 +33,10 @@ cfg_if::cfg_if! {
             MerkleProof,
             MissingKey,
             Store,
+            NoAccountToRemove,
+            AccountAlreadyExists,
+            KeyOutOfOverlay,
+            UnsupportedByOverlay,
         }
     }
 }
@@ -33,3 +46,171 @@ impl From<SMTError> for Error {
         Error::SMT(err)
     }
 }
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+        #[derive(Error, Debug, Eq, PartialEq, Clone)]
+        pub enum ValidationError {
+            #[error("Account count regression, prev: {prev} next: {next}")]
+            AccountCountRegression { prev: u32, next: u32 },
+            #[error("Block count regression, prev: {prev} next: {next}")]
+            BlockCountRegression { prev: u64, next: u64 },
+            #[error("Last finalized block number regression, prev: {prev} next: {next}")]
+            FinalizedBlockNumberRegression { prev: u64, next: u64 },
+            #[error("Invalid status transition, prev: {prev} next: {next}")]
+            InvalidStatusTransition { prev: u8, next: u8 },
+            #[error("Block {block_number} is already finalized, last finalized block: {last_finalized_block_number}")]
+            BlockAlreadyFinalized {
+                block_number: u64,
+                last_finalized_block_number: u64,
+            },
+            #[error("Total capacity overflow")]
+            CapacityOverflow,
+            #[error("Non monotonic timestamp, prev: {prev} next: {next}")]
+            NonMonotonicTimestamp { prev: u64, next: u64 },
+            #[error("Tx witness root mismatch, expected: {expected:?} actual: {actual:?}")]
+            TxWitnessRootMismatch { expected: H256, actual: H256 },
+            #[error("Insufficient challenge bond, required: {required} provided: {provided}")]
+            InsufficientChallengeBond { required: u64, provided: u64 },
+            #[error("Chain halted, status: {status}")]
+            ChainHalted { status: u8 },
+            #[error("Post account root mismatch, expected: {expected:?} actual: {actual:?}")]
+            PostAccountRootMismatch { expected: H256, actual: H256 },
+            #[error("State error {0}")]
+            State(Error),
+            #[error("Payment lock {lock_hash:?} doesn't exist")]
+            UnknownPaymentLock { lock_hash: H256 },
+            #[error("Invalid tx proof")]
+            InvalidTxProof,
+            #[error("Invalid kv state proof")]
+            InvalidKVStateProof,
+            #[error("Invalid challenge target, type: {target_type} index: {target_index}")]
+            InvalidChallengeTarget { target_type: u8, target_index: u32 },
+            #[error("Challenge block mismatch, expected: {expected:?} actual: {actual:?}")]
+            ChallengeBlockMismatch { expected: H256, actual: H256 },
+        }
+    } else {
+        #[derive(Debug, Eq, PartialEq, Clone)]
+        pub enum ValidationError {
+            AccountCountRegression { prev: u32, next: u32 },
+            BlockCountRegression { prev: u64, next: u64 },
+            FinalizedBlockNumberRegression { prev: u64, next: u64 },
+            InvalidStatusTransition { prev: u8, next: u8 },
+            BlockAlreadyFinalized {
+                block_number: u64,
+                last_finalized_block_number: u64,
+            },
+            CapacityOverflow,
+            NonMonotonicTimestamp { prev: u64, next: u64 },
+            TxWitnessRootMismatch { expected: H256, actual: H256 },
+            InsufficientChallengeBond { required: u64, provided: u64 },
+            ChainHalted { status: u8 },
+            PostAccountRootMismatch { expected: H256, actual: H256 },
+            State(Error),
+            UnknownPaymentLock { lock_hash: H256 },
+            InvalidTxProof,
+            InvalidKVStateProof,
+            InvalidChallengeTarget { target_type: u8, target_index: u32 },
+            ChallengeBlockMismatch { expected: H256, actual: H256 },
+        }
+    }
+}
+
+impl From<Error> for ValidationError {
+    fn from(err: Error) -> Self {
+        ValidationError::State(err)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validation_error_display() {
+        let cases = vec![
+            (
+                ValidationError::AccountCountRegression { prev: 3, next: 2 },
+                "Account count regression, prev: 3 next: 2",
+            ),
+            (
+                ValidationError::BlockCountRegression { prev: 10, next: 9 },
+                "Block count regression, prev: 10 next: 9",
+            ),
+            (
+                ValidationError::FinalizedBlockNumberRegression { prev: 5, next: 4 },
+                "Last finalized block number regression, prev: 5 next: 4",
+            ),
+            (
+                ValidationError::InvalidStatusTransition { prev: 1, next: 0 },
+                "Invalid status transition, prev: 1 next: 0",
+            ),
+            (
+                ValidationError::BlockAlreadyFinalized {
+                    block_number: 90,
+                    last_finalized_block_number: 95,
+                },
+                "Block 90 is already finalized, last finalized block: 95",
+            ),
+            (ValidationError::CapacityOverflow, "Total capacity overflow"),
+            (
+                ValidationError::NonMonotonicTimestamp {
+                    prev: 100,
+                    next: 99,
+                },
+                "Non monotonic timestamp, prev: 100 next: 99",
+            ),
+            (
+                ValidationError::InsufficientChallengeBond {
+                    required: 500,
+                    provided: 499,
+                },
+                "Insufficient challenge bond, required: 500 provided: 499",
+            ),
+            (
+                ValidationError::ChainHalted { status: 1 },
+                "Chain halted, status: 1",
+            ),
+            (
+                ValidationError::State(Error::MissingKey),
+                "State error Missing key error",
+            ),
+            (ValidationError::InvalidTxProof, "Invalid tx proof"),
+            (
+                ValidationError::InvalidKVStateProof,
+                "Invalid kv state proof",
+            ),
+            (
+                ValidationError::InvalidChallengeTarget {
+                    target_type: 1,
+                    target_index: 3,
+                },
+                "Invalid challenge target, type: 1 index: 3",
+            ),
+        ];
+        for (err, expected) in cases {
+            assert_eq!(err.to_string(), expected);
+        }
+
+        let err = ValidationError::TxWitnessRootMismatch {
+            expected: H256::zero(),
+            actual: [1u8; 32].into(),
+        };
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Tx witness root mismatch, expected: {:?} actual: {:?}",
+                H256::zero(),
+                H256::from([1u8; 32])
+            )
+        );
+
+        let err = ValidationError::UnknownPaymentLock {
+            lock_hash: [1u8; 32].into(),
+        };
+        assert_eq!(
+            err.to_string(),
+            format!("Payment lock {:?} doesn't exist", H256::from([1u8; 32]))
+        );
+    }
+}
@@ -4,27 +4,36 @@ pub use sparse_merkle_tree::H256;
 
 pub trait H256Ext {
     fn one() -> H256;
+    fn from_array(arr: [u8; 32]) -> H256;
+    fn to_array(&self) -> [u8; 32];
     fn from_u32(n: u32) -> H256;
     fn to_u32(&self) -> u32;
     fn from_u64(n: u64) -> H256;
     fn to_u64(&self) -> u64;
     fn from_u128(n: u128) -> H256;
     fn to_u128(&self) -> u128;
+    /// The number of bits shared by both keys, counted from bit 255 downward
+    /// as the SMT walks from the root, 256 for identical keys.
+    /// Bits are ordered as the inherent `H256::get_bit`: bit `i` is bit `i % 8`
+    /// (from the least significant) of byte `i / 8`
+    fn common_prefix_len(&self, other: &H256) -> u16;
 }
 
 impl H256Ext for H256 {
     fn one() -> H256 {
         H256::from_u32(1)
     }
+    fn from_array(arr: [u8; 32]) -> H256 {
+        arr.into()
+    }
+    fn to_array(&self) -> [u8; 32] {
+        (*self).into()
+    }
     fn from_u32(n: u32) -> H256 {
-        let mut buf = [0u8; 32];
-        buf[..4].copy_from_slice(&n.to_le_bytes());
-        buf.into()
+        h256_from_u32_le(n)
     }
     fn to_u32(&self) -> u32 {
-        let mut n_bytes = [0u8; 4];
-        n_bytes.copy_from_slice(&self.as_slice()[..4]);
-        u32::from_le_bytes(n_bytes)
+        u32_from_h256_le(self)
     }
     fn from_u64(n: u64) -> H256 {
         let mut buf = [0u8; 32];
@@ -46,4 +55,110 @@ impl H256Ext for H256 {
         n_bytes.copy_from_slice(&self.as_slice()[..16]);
         u128::from_le_bytes(n_bytes)
     }
+    fn common_prefix_len(&self, other: &H256) -> u16 {
+        // `fork_height` is the highest different bit, it is also 0 for identical keys
+        if self == other {
+            return 256;
+        }
+        255 - u16::from(self.fork_height(other))
+    }
+}
+
+/// Put `n` in the first 4 bytes of H256 as little-endian,
+/// this is the layout of integer values in the state tree
+pub fn h256_from_u32_le(n: u32) -> H256 {
+    let mut buf = [0u8; 32];
+    buf[..4].copy_from_slice(&n.to_le_bytes());
+    buf.into()
+}
+
+/// Read a little-endian u32 from the first 4 bytes of H256
+pub fn u32_from_h256_le(h: &H256) -> u32 {
+    let mut n_bytes = [0u8; 4];
+    n_bytes.copy_from_slice(&h.as_slice()[..4]);
+    u32::from_le_bytes(n_bytes)
+}
+
+/// Put `n` in the last 4 bytes of H256 as big-endian,
+/// i.e. H256 is treated as a big-endian 256 bits integer
+pub fn h256_from_u32_be(n: u32) -> H256 {
+    let mut buf = [0u8; 32];
+    buf[28..].copy_from_slice(&n.to_be_bytes());
+    buf.into()
+}
+
+/// Read a big-endian u32 from the last 4 bytes of H256
+pub fn u32_from_h256_be(h: &H256) -> u32 {
+    let mut n_bytes = [0u8; 4];
+    n_bytes.copy_from_slice(&h.as_slice()[28..]);
+    u32::from_be_bytes(n_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_u32_round_trip() {
+        for n in [0u32, 1, 0x1234_5678, u32::MAX].iter() {
+            assert_eq!(u32_from_h256_le(&h256_from_u32_le(*n)), *n);
+            assert_eq!(u32_from_h256_be(&h256_from_u32_be(*n)), *n);
+            assert_eq!(H256::from_u32(*n), h256_from_u32_le(*n));
+        }
+    }
+
+    #[test]
+    fn test_le_and_be_are_different() {
+        let n = 0x1234_5678u32;
+        let le = h256_from_u32_le(n);
+        let be = h256_from_u32_be(n);
+        assert_ne!(le, be);
+        assert_eq!(&le.as_slice()[..4], &[0x78, 0x56, 0x34, 0x12]);
+        assert_eq!(&be.as_slice()[28..], &[0x12, 0x34, 0x56, 0x78]);
+    }
+
+    #[test]
+    fn test_array_conversion() {
+        let mut arr = [0u8; 32];
+        for (i, b) in arr.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        let h = H256::from_array(arr);
+        assert_eq!(h, H256::from(arr));
+        assert_eq!(h.as_slice(), &arr[..]);
+        assert_eq!(h.to_array(), Into::<[u8; 32]>::into(h));
+        assert_eq!(h.to_array(), arr);
+    }
+
+    #[test]
+    fn test_get_bit_order() {
+        // byte 0 is 0b0000_0101, byte 31 is 0b1000_0000
+        let mut arr = [0u8; 32];
+        arr[0] = 0b0000_0101;
+        arr[31] = 0b1000_0000;
+        let key = H256::from(arr);
+        let set_bits: Vec<u8> = (0..=255u8).filter(|i| key.get_bit(*i)).collect();
+        assert_eq!(set_bits, vec![0, 2, 255]);
+    }
+
+    #[test]
+    fn test_common_prefix_len() {
+        let key = H256::from([0xffu8; 32]);
+        assert_eq!(key.common_prefix_len(&key), 256);
+        assert_eq!(key.common_prefix_len(&H256::zero()), 0);
+
+        // keys differ only at bit 0 aren't taken as identical
+        let mut arr = [0xffu8; 32];
+        arr[0] = 0b1111_1110;
+        assert_eq!(key.common_prefix_len(&H256::from(arr)), 255);
+
+        let mut arr = [0xffu8; 32];
+        // clear bit 250
+        arr[31] = 0b1111_1011;
+        let other = H256::from(arr);
+        assert_eq!(key.common_prefix_len(&other), 5);
+        assert_eq!(other.common_prefix_len(&key), 5);
+        // the SMT forks the two keys at height 250
+        assert_eq!(key.fork_height(&other), 250);
+    }
 }
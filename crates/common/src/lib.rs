@@ -1,5 +1,6 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+pub mod account_lock;
 pub mod builtins;
 pub mod error;
 pub mod h256_ext;
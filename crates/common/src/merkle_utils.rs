@@ -15,6 +15,15 @@ pub fn calculate_state_checkpoint(root: &H256, count: u32) -> H256 {
     hash.into()
 }
 
+/// Calculate the state checkpoint of each `(account root, account count)` pair,
+/// e.g. the post states of the transactions in a block
+pub fn calculate_state_checkpoint_list(states: &[(H256, u32)]) -> Vec<H256> {
+    states
+        .iter()
+        .map(|(root, count)| calculate_state_checkpoint(root, *count))
+        .collect()
+}
+
 /// Compute merkle root from vectors
 pub fn calculate_merkle_root(leaves: Vec<H256>) -> Result<H256, Error> {
     if leaves.is_empty() {
@@ -26,3 +35,24 @@ pub fn calculate_merkle_root(leaves: Vec<H256>) -> Result<H256, Error> {
     }
     Ok(*tree.root())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calculate_state_checkpoint_list() {
+        let states: Vec<(H256, u32)> = (0u8..3).map(|i| ([i; 32].into(), i as u32 + 1)).collect();
+        let list = calculate_state_checkpoint_list(&states);
+        assert_eq!(list.len(), states.len());
+        for ((root, count), checkpoint) in states.iter().zip(list.iter()) {
+            let mut expected = [0u8; 32];
+            let mut hasher = new_blake2b();
+            hasher.update(root.as_slice());
+            hasher.update(&count.to_le_bytes());
+            hasher.finalize(&mut expected);
+            assert_eq!(checkpoint, &H256::from(expected));
+        }
+        assert!(calculate_state_checkpoint_list(&[]).is_empty());
+    }
+}
@@ -16,8 +16,10 @@
 //
 // Thus, the first 5 bytes keeps uniqueness for different type of keys.
 
+use crate::builtins::RESERVED_ACCOUNT_ID;
 use crate::error::Error;
 use crate::h256_ext::{H256Ext, H256};
+use crate::vec::Vec;
 use crate::{blake2b::new_blake2b, merkle_utils::calculate_state_checkpoint};
 use core::mem::size_of;
 
@@ -44,7 +46,32 @@ pub fn build_account_key(id: u32, key: &[u8]) -> H256 {
     raw_key.into()
 }
 
-pub fn build_account_field_key(id: u32, type_: u8) -> H256 {
+/// Fields of an account in the state tree
+///
+/// There is no `CodeHash` field: the code hash of an account is the `code_hash` of its script,
+/// the tree only stores the script hash, and the script itself is stored by `CodeStore`.
+/// To prove the code hash, prove the `ScriptHash` field and provide the script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountField {
+    Nonce,
+    ScriptHash,
+    /// Balance of the account in the sUDT account of the id
+    Sudt(u32),
+}
+
+/* Generate the SMT key of an account field
+ *
+ * Nonce & ScriptHash: id(4 bytes LE) | type(1 byte) | zeros(27 bytes)
+ * Sudt: the account kv `build_account_key(sudt_id, id(u32 LE as H256))` of the sUDT account
+ */
+pub fn build_account_field_key(id: u32, field: AccountField) -> H256 {
+    let type_ = match field {
+        AccountField::Nonce => GW_ACCOUNT_NONCE,
+        AccountField::ScriptHash => GW_ACCOUNT_SCRIPT_HASH,
+        AccountField::Sudt(sudt_id) => {
+            return build_account_key(sudt_id, H256::from_u32(id).as_slice());
+        }
+    };
     let mut key: [u8; 32] = H256::zero().into();
     key[..size_of::<u32>()].copy_from_slice(&id.to_le_bytes());
     key[size_of::<u32>()] = type_;
@@ -79,10 +106,25 @@ pub trait State {
     // KV interface
     fn get_raw(&self, key: &H256) -> Result<H256, Error>;
     fn update_raw(&mut self, key: H256, value: H256) -> Result<(), Error>;
+    /// Update raw keys in a batch, a SMT backed implementation updates the tree in one pass
+    fn update_raws(&mut self, kvs: Vec<(H256, H256)>) -> Result<(), Error> {
+        for (key, value) in kvs {
+            self.update_raw(key, value)?;
+        }
+        Ok(())
+    }
     fn get_account_count(&self) -> Result<u32, Error>;
     fn set_account_count(&mut self, count: u32) -> Result<(), Error>;
     fn calculate_root(&self) -> Result<H256, Error>;
 
+    /// Return the account merkle root and account count as a pair,
+    /// implementations should read both from the same snapshot
+    fn account_merkle_state(&self) -> Result<(H256, u32), Error> {
+        let root = self.calculate_root()?;
+        let count = self.get_account_count()?;
+        Ok((root, count))
+    }
+
     // implementations
     fn get_value(&self, id: u32, key: &H256) -> Result<H256, Error> {
         let raw_key = build_account_key(id, key.as_slice());
@@ -100,7 +142,7 @@ pub trait State {
         self.set_nonce(id, 0)?;
         // script hash
         self.update_raw(
-            build_account_field_key(id, GW_ACCOUNT_SCRIPT_HASH),
+            build_account_field_key(id, AccountField::ScriptHash),
             script_hash,
         )?;
         // script hash to id
@@ -113,19 +155,64 @@ pub trait State {
         Ok(id)
     }
 
+    /// Create the reserved account (id 0) of a fresh state,
+    /// returns an error if any account already exists
+    fn init_reserved_account(&mut self, meta_script_hash: H256) -> Result<(), Error> {
+        if self.get_account_count()? != 0 {
+            return Err(Error::AccountAlreadyExists);
+        }
+        let id = self.create_account(meta_script_hash)?;
+        debug_assert_eq!(id, RESERVED_ACCOUNT_ID);
+        Ok(())
+    }
+
+    /// Remove the last created account, used to revert an account creation
+    fn remove_last_account(&mut self) -> Result<(), Error> {
+        let count = self.get_account_count()?;
+        let id = count.checked_sub(1).ok_or(Error::NoAccountToRemove)?;
+        let script_hash = self.get_script_hash(id)?;
+        // nonce
+        self.update_raw(
+            build_account_field_key(id, AccountField::Nonce),
+            H256::zero(),
+        )?;
+        // script hash
+        self.update_raw(
+            build_account_field_key(id, AccountField::ScriptHash),
+            H256::zero(),
+        )?;
+        // script hash to id
+        self.update_raw(
+            build_script_hash_to_account_id_key(&script_hash.as_slice()),
+            H256::zero(),
+        )?;
+        // update account count
+        self.set_account_count(id)?;
+        Ok(())
+    }
+
+    /// Return true if the account is created, which requires both `id < account_count`
+    /// and a non-zero script hash, in case the account ids are not contiguous
+    fn is_account_created(&self, id: u32) -> Result<bool, Error> {
+        if id >= self.get_account_count()? {
+            return Ok(false);
+        }
+        Ok(!self.get_script_hash(id)?.is_zero())
+    }
+
     fn get_script_hash(&self, id: u32) -> Result<H256, Error> {
-        let value = self.get_raw(&build_account_field_key(id, GW_ACCOUNT_SCRIPT_HASH))?;
+        let value = self.get_raw(&build_account_field_key(id, AccountField::ScriptHash))?;
         Ok(value)
     }
 
     fn get_nonce(&self, id: u32) -> Result<u32, Error> {
-        let value = self.get_raw(&build_account_field_key(id, GW_ACCOUNT_NONCE))?;
+        let value = self.get_raw(&build_account_field_key(id, AccountField::Nonce))?;
         Ok(value.to_u32())
     }
 
     fn set_nonce(&mut self, id: u32, nonce: u32) -> Result<(), Error> {
         self.update_raw(
-            build_account_field_key(id, GW_ACCOUNT_NONCE),
+            build_account_field_key(id, AccountField::Nonce),
             H256::from_u32(nonce),
         )?;
         Ok(())
@@ -142,7 +229,7 @@ pub trait State {
 
     fn get_sudt_balance(&self, sudt_id: u32, id: u32) -> Result<u128, Error> {
         // get balance
-        let balance = self.get_value(sudt_id, &H256::from_u32(id))?;
+        let balance = self.get_raw(&build_account_field_key(id, AccountField::Sudt(sudt_id)))?;
         Ok(balance.to_u128())
     }
 
@@ -160,7 +247,7 @@ pub trait State {
 
     /// Mint SUDT token on layer2
     fn mint_sudt(&mut self, sudt_id: u32, id: u32, amount: u128) -> Result<(), Error> {
-        let raw_key = build_account_key(sudt_id, &H256::from_u32(id).as_slice());
+        let raw_key = build_account_field_key(id, AccountField::Sudt(sudt_id));
         // calculate balance
         let mut balance = self.get_raw(&raw_key)?.to_u128();
         balance = balance.checked_add(amount).ok_or(Error::AmountOverflow)?;
@@ -170,7 +257,7 @@ pub trait State {
 
     /// burn SUDT
     fn burn_sudt(&mut self, sudt_id: u32, id: u32, amount: u128) -> Result<(), Error> {
-        let raw_key = build_account_key(sudt_id, &H256::from_u32(id).as_slice());
+        let raw_key = build_account_field_key(id, AccountField::Sudt(sudt_id));
         // calculate balance
         let mut balance = self.get_raw(&raw_key)?.to_u128();
         balance = balance.checked_sub(amount).ok_or(Error::AmountOverflow)?;
@@ -180,8 +267,41 @@ pub trait State {
 
     /// calculate state checkpoint
     fn calculate_state_checkpoint(&self) -> Result<H256, Error> {
-        let account_root = self.calculate_root()?;
-        let account_count = self.get_account_count()?;
+        let (account_root, account_count) = self.account_merkle_state()?;
         Ok(calculate_state_checkpoint(&account_root, account_count))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_account_field_key_layout() {
+        let id = 0x0102_0304u32;
+        let mut expected = [0u8; 32];
+        expected[..5].copy_from_slice(&[4, 3, 2, 1, 1]);
+        assert_eq!(
+            build_account_field_key(id, AccountField::Nonce),
+            expected.into()
+        );
+        expected[4] = 2;
+        assert_eq!(
+            build_account_field_key(id, AccountField::ScriptHash),
+            expected.into()
+        );
+
+        let sudt_id = 5u32;
+        let mut expected = [0u8; 32];
+        let mut hasher = new_blake2b();
+        hasher.update(&[5, 0, 0, 0]);
+        hasher.update(&[GW_ACCOUNT_KV]);
+        hasher.update(&[4, 3, 2, 1]);
+        hasher.update(&[0u8; 28]);
+        hasher.finalize(&mut expected);
+        assert_eq!(
+            build_account_field_key(id, AccountField::Sudt(sudt_id)),
+            expected.into()
+        );
+    }
+}
@@ -34,6 +34,9 @@ pub struct RPCClientConfig {
 pub struct ChainConfig {
     pub genesis_committed_info: L2BlockCommittedInfo,
     pub rollup_type_script: Script,
+    /// Id of the polyjuice creator account, the lower half of the EVM chain id.
+    /// Required by the block producer to check the chain id of polyjuice transactions
+    pub polyjuice_chain_id: Option<u32>,
 }
 
 /// Genesis config
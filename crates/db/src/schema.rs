@@ -3,7 +3,7 @@
 /// Column families alias type
 pub type Col = u8;
 /// Total column number
-pub const COLUMNS: u32 = 19;
+pub const COLUMNS: u32 = 20;
 /// Column store meta data
 pub const COLUMN_META: Col = 0;
 /// Column store chain index
@@ -42,6 +42,8 @@ pub const COLUMN_BLOCK_DEPOSITION_REQUESTS: Col = 16;
 pub const COLUMN_CUSTODIAN_ASSETS: Col = 17;
 /// Column block state record
 pub const COLUMN_BLOCK_STATE_RECORD: Col = 18;
+/// Column sender transactions index
+pub const COLUMN_SENDER_TRANSACTION: Col = 19;
 
 /// chain id
 pub const META_CHAIN_ID_KEY: &[u8] = b"CHAIN_ID";
@@ -0,0 +1,86 @@
+//! Account cache
+//!
+//! Memoizes id -> script hash and id -> script lookups during the execution of one block,
+//! to avoid reading the same accounts from the SMT repeatedly.
+//!
+//! Invalidation rule: the script hash of an account only changes when the account is
+//! created or removed, which writes the `AccountField::ScriptHash` key of the id.
+//! Call `invalidate_by_run_result` after applying the run result of each transaction,
+//! call `invalidate` after mutating an account directly (e.g. `State::remove_last_account`),
+//! and drop the cache when the block is done.
+
+use gw_common::{
+    error::Error,
+    state::{build_account_field_key, AccountField, State},
+    H256,
+};
+use gw_traits::CodeStore;
+use gw_types::{offchain::RunResult, packed::Script};
+use std::{cell::RefCell, collections::HashMap};
+
+#[derive(Default)]
+pub struct AccountCache {
+    script_hashes: RefCell<HashMap<u32, H256>>,
+    scripts: RefCell<HashMap<u32, Script>>,
+}
+
+impl AccountCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the script hash of the account, read from the state on a cache miss.
+    /// A zero hash (account not exists) is never cached
+    pub fn cached_script_hash<S: State>(&self, state: &S, id: u32) -> Result<H256, Error> {
+        if let Some(script_hash) = self.script_hashes.borrow().get(&id) {
+            return Ok(*script_hash);
+        }
+        let script_hash = state.get_script_hash(id)?;
+        if !script_hash.is_zero() {
+            self.script_hashes.borrow_mut().insert(id, script_hash);
+        }
+        Ok(script_hash)
+    }
+
+    /// Return the script of the account, `None` if the account or the script doesn't exist
+    pub fn cached_script<S: State + CodeStore>(
+        &self,
+        state: &S,
+        id: u32,
+    ) -> Result<Option<Script>, Error> {
+        if let Some(script) = self.scripts.borrow().get(&id) {
+            return Ok(Some(script.clone()));
+        }
+        let script_hash = self.cached_script_hash(state, id)?;
+        if script_hash.is_zero() {
+            return Ok(None);
+        }
+        let script = state.get_script(&script_hash);
+        if let Some(script) = script.as_ref() {
+            self.scripts.borrow_mut().insert(id, script.clone());
+        }
+        Ok(script)
+    }
+
+    /// Remove the cached entries of the account
+    pub fn invalidate(&self, id: u32) {
+        self.script_hashes.borrow_mut().remove(&id);
+        self.scripts.borrow_mut().remove(&id);
+    }
+
+    /// Remove the cached entries of accounts whose script hash is written by the run result
+    pub fn invalidate_by_run_result(&self, run_result: &RunResult) {
+        let ids: Vec<u32> = self.script_hashes.borrow().keys().copied().collect();
+        for id in ids {
+            let key = build_account_field_key(id, AccountField::ScriptHash);
+            if run_result.write_values.contains_key(&key) {
+                self.invalidate(id);
+            }
+        }
+    }
+
+    pub fn clear(&self) {
+        self.script_hashes.borrow_mut().clear();
+        self.scripts.borrow_mut().clear();
+    }
+}
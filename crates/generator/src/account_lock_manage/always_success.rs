@@ -25,6 +25,15 @@ impl LockAlgorithm for AlwaysSuccess {
         Ok(true)
     }
 
+    fn verify_message_digest(
+        &self,
+        _lock_args: Bytes,
+        _signature: Signature,
+        _digest: H256,
+    ) -> Result<bool, LockAlgorithmError> {
+        Ok(true)
+    }
+
     fn verify_tx(
         &self,
         _ctx: &RollupContext,
@@ -1,18 +1,72 @@
 use std::collections::HashMap;
 
-use gw_common::H256;
+use gw_common::{state::State, H256};
+use gw_traits::CodeStore;
 use gw_types::{
     bytes::Bytes,
-    packed::{L2Transaction, Script, Signature},
+    packed::{L2Transaction, RawL2Transaction, RawWithdrawalRequest, Script, Signature},
+    prelude::*,
 };
 
 #[cfg(debug_assertions)]
 pub mod always_success;
 pub mod secp256k1;
 
-use crate::{error::LockAlgorithmError, RollupContext};
+use crate::{
+    error::{LockAlgorithmError, TransactionError},
+    RollupContext,
+};
+
+/// The message a wallet signs for a withdrawal request,
+/// a lock algorithm may apply its own prefix (e.g. Ethereum personal sign) before verifying
+pub fn withdrawal_signing_message(rollup_type_hash: &H256, req: &RawWithdrawalRequest) -> H256 {
+    req.calc_message(rollup_type_hash)
+}
+
+/// Resolve the sender and receiver scripts of the tx from the state,
+/// then verify the tx by `AccountLockManage::verify_tx`
+pub fn verify_l2_transaction<S: State + CodeStore>(
+    manage: &AccountLockManage,
+    ctx: &RollupContext,
+    state: &S,
+    tx: &L2Transaction,
+) -> Result<bool, LockAlgorithmError> {
+    let get_script = |account_id: u32| -> Result<Script, LockAlgorithmError> {
+        let script_hash = state.get_script_hash(account_id)?;
+        if script_hash.is_zero() {
+            return Err(LockAlgorithmError::ScriptNotFound { account_id });
+        }
+        state
+            .get_script(&script_hash)
+            .ok_or(LockAlgorithmError::ScriptNotFound { account_id })
+    };
+    let raw_tx = tx.raw();
+    let sender_script = get_script(raw_tx.from_id().unpack())?;
+    let receiver_script = get_script(raw_tx.to_id().unpack())?;
+    manage.verify_tx(ctx, sender_script, receiver_script, tx.clone())
+}
+
+/// Check the tx nonce equals the current nonce of the sender,
+/// a signature doesn't prevent replaying, call this alongside the signature verification
+pub fn verify_tx_nonce<S: State>(state: &S, tx: &RawL2Transaction) -> Result<(), TransactionError> {
+    let account_nonce = state.get_nonce(tx.from_id().unpack())?;
+    let nonce: u32 = tx.nonce().unpack();
+    if nonce != account_nonce {
+        return Err(TransactionError::NonceMismatch {
+            expected: account_nonce,
+            got: nonce,
+        });
+    }
+    Ok(())
+}
 
 pub trait LockAlgorithm {
+    /// Return false if the lock can't be used to sign withdrawal requests,
+    /// `verify_withdrawal_signature` is never called for such locks
+    fn supports_withdrawal(&self) -> bool {
+        true
+    }
+
     fn verify_withdrawal_signature(
         &self,
         lock_args: Bytes,
@@ -20,6 +74,15 @@ pub trait LockAlgorithm {
         message: H256,
     ) -> Result<bool, LockAlgorithmError>;
 
+    /// Verify the signature of a digest which is already prefixed and hashed by the signer,
+    /// the signing scheme of the algorithm isn't applied again
+    fn verify_message_digest(
+        &self,
+        lock_args: Bytes,
+        signature: Signature,
+        digest: H256,
+    ) -> Result<bool, LockAlgorithmError>;
+
     fn verify_tx(
         &self,
         ctx: &RollupContext,
@@ -57,4 +120,31 @@ impl AccountLockManage {
     ) -> Option<&Box<dyn LockAlgorithm + Send + Sync>> {
         self.locks.get(code_hash)
     }
+
+    /// Verify the tx by the lock algorithm registered for the lock code hash of the sender script,
+    /// a lock code hash not allowed by the rollup config is rejected before the verification
+    pub fn verify_tx(
+        &self,
+        ctx: &RollupContext,
+        sender_script: Script,
+        receiver_script: Script,
+        tx: L2Transaction,
+    ) -> Result<bool, LockAlgorithmError> {
+        let code_hash: H256 = sender_script.code_hash().unpack();
+        if !self.is_allowed_lock(ctx, &code_hash) {
+            return Err(LockAlgorithmError::DisallowedLock);
+        }
+        let lock_algo = self
+            .get_lock_algorithm(&code_hash)
+            .ok_or(LockAlgorithmError::UnknownLockCodeHash { code_hash })?;
+        lock_algo.verify_tx(ctx, sender_script, receiver_script, tx)
+    }
+
+    /// Return true if the lock code hash is in the allowed EoA type hashes of the rollup config
+    pub fn is_allowed_lock(&self, ctx: &RollupContext, code_hash: &H256) -> bool {
+        ctx.rollup_config()
+            .allowed_eoa_type_hashes()
+            .into_iter()
+            .any(|type_hash| type_hash.as_slice() == code_hash.as_slice())
+    }
 }
@@ -1,14 +1,17 @@
 use super::LockAlgorithm;
 use crate::{error::LockAlgorithmError, RollupContext};
+use gw_common::account_lock::{calc_chain_id_tagged_message, SECP256K1_LOCK_ARGS_LEN};
 use gw_common::blake2b::new_blake2b;
 use gw_common::H256;
 use gw_types::prelude::*;
 use gw_types::{
     bytes::Bytes,
+    core::ScriptHashType,
     packed::{L2Transaction, RawL2Transaction, Script, Signature},
 };
 use lazy_static::lazy_static;
 use secp256k1::recovery::{RecoverableSignature, RecoveryId};
+use sha2::Sha256;
 use sha3::{Digest, Keccak256};
 
 lazy_static! {
@@ -30,12 +33,7 @@ impl LockAlgorithm for Secp256k1 {
         receiver_script: Script,
         tx: L2Transaction,
     ) -> Result<bool, LockAlgorithmError> {
-        let message = calc_godwoken_signing_message(
-            &ctx.rollup_script_hash,
-            &sender_script,
-            &receiver_script,
-            &tx,
-        );
+        let message = calc_godwoken_signing_message(ctx, &sender_script, &receiver_script, &tx);
 
         self.verify_withdrawal_signature(sender_script.args().unpack(), tx.signature(), message)
     }
@@ -46,20 +44,28 @@ impl LockAlgorithm for Secp256k1 {
         signature: Signature,
         message: H256,
     ) -> Result<bool, LockAlgorithmError> {
-        if lock_args.len() != 52 {
-            return Err(LockAlgorithmError::InvalidLockArgs);
-        }
-        let mut expected_pubkey_hash = [0u8; 20];
-        expected_pubkey_hash.copy_from_slice(&lock_args[32..52]);
+        let signing_message = wrap_signing_message(SigningScheme::CkbBlake2b, message);
+        self.verify_message_digest(lock_args, signature, signing_message)
+    }
+
+    fn verify_message_digest(
+        &self,
+        lock_args: Bytes,
+        signature: Signature,
+        digest: H256,
+    ) -> Result<bool, LockAlgorithmError> {
+        let expected_pubkey_hash = decode_pubkey_hash(&lock_args)?;
         let signature: RecoverableSignature = {
             let signature: [u8; 65] = signature.unpack();
-            let recid = RecoveryId::from_i32(signature[64] as i32)
-                .map_err(|_| LockAlgorithmError::InvalidSignature)?;
+            if is_zero_signature(&signature) {
+                return Err(LockAlgorithmError::MissingSignature);
+            }
+            let recid = parse_recovery_id(signature[64])?;
             let data = &signature[..64];
             RecoverableSignature::from_compact(data, recid)
                 .map_err(|_| LockAlgorithmError::InvalidSignature)?
         };
-        let msg = secp256k1::Message::from_slice(message.as_slice())
+        let msg = secp256k1::Message::from_slice(digest.as_slice())
             .map_err(|_| LockAlgorithmError::InvalidSignature)?;
         let pubkey = SECP256K1
             .recover(&msg, &signature)
@@ -83,40 +89,74 @@ impl LockAlgorithm for Secp256k1 {
 #[derive(Debug, Default)]
 pub struct Secp256k1Eth;
 
+/// Describes the path taken by `Secp256k1Eth::verify_tx_explain`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyTxExplanation {
+    pub algorithm: &'static str,
+    pub receiver_code_hash: H256,
+    /// True if the tx is signed as an Ethereum transaction assembled from the polyjuice args,
+    /// otherwise the godwoken signing message with personal sign is used
+    pub polyjuice: bool,
+    pub recovered_address: [u8; 20],
+}
+
 impl Secp256k1Eth {
+    /// Recover the Ethereum address of the sender of a L2 transaction,
+    /// accepts both the packed and the JSON transaction
+    pub fn recover_eth_sender<T: Into<L2Transaction>>(
+        &self,
+        ctx: &RollupContext,
+        sender_script: &Script,
+        receiver_script: &Script,
+        tx: T,
+    ) -> Result<[u8; 20], LockAlgorithmError> {
+        let tx: L2Transaction = tx.into();
+        let message = self.calc_signing_message(ctx, sender_script, receiver_script, &tx)?;
+        recover_eth_address(tx.signature(), message)
+    }
+
+    /// Same as `verify_tx`, also returns an explanation of the path taken,
+    /// used to debug rejected transactions
+    pub fn verify_tx_explain(
+        &self,
+        ctx: &RollupContext,
+        sender_script: &Script,
+        receiver_script: &Script,
+        tx: &L2Transaction,
+    ) -> Result<(bool, VerifyTxExplanation), LockAlgorithmError> {
+        let (message, polyjuice) =
+            calc_eth_signing_message_with_path(ctx, sender_script, receiver_script, tx, false)?;
+        let expected_pubkey_hash = decode_pubkey_hash(&sender_script.args().unpack())?;
+        let recovered_address = recover_eth_address(tx.signature(), message)?;
+        let explanation = VerifyTxExplanation {
+            algorithm: "secp256k1_eth",
+            receiver_code_hash: receiver_script.code_hash().unpack(),
+            polyjuice,
+            recovered_address,
+        };
+        Ok((recovered_address == expected_pubkey_hash, explanation))
+    }
+
+    fn calc_signing_message(
+        &self,
+        ctx: &RollupContext,
+        sender_script: &Script,
+        receiver_script: &Script,
+        tx: &L2Transaction,
+    ) -> Result<H256, LockAlgorithmError> {
+        let (message, _polyjuice) =
+            calc_eth_signing_message_with_path(ctx, sender_script, receiver_script, tx, false)?;
+        Ok(message)
+    }
+
     fn verify_alone(
         &self,
         lock_args: Bytes,
         signature: Signature,
         message: H256,
     ) -> Result<bool, LockAlgorithmError> {
-        if lock_args.len() != 52 {
-            return Err(LockAlgorithmError::InvalidLockArgs);
-        }
-
-        let mut expected_pubkey_hash = [0u8; 20];
-        expected_pubkey_hash.copy_from_slice(&lock_args[32..52]);
-        let signature: RecoverableSignature = {
-            let signature: [u8; 65] = signature.unpack();
-            let recid = RecoveryId::from_i32(signature[64] as i32)
-                .map_err(|_| LockAlgorithmError::InvalidSignature)?;
-            let data = &signature[..64];
-            RecoverableSignature::from_compact(data, recid)
-                .map_err(|_| LockAlgorithmError::InvalidSignature)?
-        };
-        let msg = secp256k1::Message::from_slice(message.as_slice())
-            .map_err(|_| LockAlgorithmError::InvalidSignature)?;
-        let pubkey = SECP256K1
-            .recover(&msg, &signature)
-            .map_err(|_| LockAlgorithmError::InvalidSignature)?;
-        let pubkey_hash = {
-            let mut hasher = Keccak256::new();
-            hasher.update(&pubkey.serialize_uncompressed()[1..]);
-            let buf = hasher.finalize();
-            let mut pubkey_hash = [0u8; 20];
-            pubkey_hash.copy_from_slice(&buf[12..]);
-            pubkey_hash
-        };
+        let expected_pubkey_hash = decode_pubkey_hash(&lock_args)?;
+        let pubkey_hash = recover_eth_address(signature, message)?;
         if pubkey_hash != expected_pubkey_hash {
             return Ok(false);
         }
@@ -136,31 +176,17 @@ impl LockAlgorithm for Secp256k1Eth {
         receiver_script: Script,
         tx: L2Transaction,
     ) -> Result<bool, LockAlgorithmError> {
-        if let Some(rlp_data) = try_assemble_polyjuice_args(
-            ctx.rollup_config.compatible_chain_id().unpack(),
-            tx.raw(),
-            receiver_script.clone(),
-        ) {
-            let mut hasher = Keccak256::new();
-            hasher.update(&rlp_data);
-            let buf = hasher.finalize();
-            let mut signing_message = [0u8; 32];
-            signing_message.copy_from_slice(&buf[..]);
-            let signing_message = H256::from(signing_message);
-            return self.verify_alone(
-                sender_script.args().unpack(),
-                tx.signature(),
-                signing_message,
-            );
-        }
-
-        let message = calc_godwoken_signing_message(
-            &ctx.rollup_script_hash,
-            &sender_script,
-            &receiver_script,
-            &tx,
+        // log macros are compiled out if the static max level of the `log` crate is higher
+        let (valid, explanation) =
+            self.verify_tx_explain(ctx, &sender_script, &receiver_script, &tx)?;
+        log::debug!(
+            "secp256k1_eth verify_tx: receiver code hash {:?}, polyjuice: {}, recovered address: {}, valid: {}",
+            explanation.receiver_code_hash,
+            explanation.polyjuice,
+            hex_address(&explanation.recovered_address),
+            valid
         );
-        self.verify_withdrawal_signature(sender_script.args().unpack(), tx.signature(), message)
+        Ok(valid)
     }
 
     // NOTE: verify_tx in this module is using standard Ethereum transaction
@@ -175,18 +201,264 @@ impl LockAlgorithm for Secp256k1Eth {
         signature: Signature,
         message: H256,
     ) -> Result<bool, LockAlgorithmError> {
+        let signing_message = wrap_signing_message(SigningScheme::EthPersonal, message);
+        self.verify_alone(lock_args, signature, signing_message)
+    }
+
+    fn verify_message_digest(
+        &self,
+        lock_args: Bytes,
+        signature: Signature,
+        digest: H256,
+    ) -> Result<bool, LockAlgorithmError> {
+        self.verify_alone(lock_args, signature, digest)
+    }
+}
+
+/// Returns the signing message of a `Secp256k1Eth` tx and whether the polyjuice path is taken,
+/// `force_polyjuice` skips the polyjuice magic check of the args
+fn calc_eth_signing_message_with_path(
+    ctx: &RollupContext,
+    sender_script: &Script,
+    receiver_script: &Script,
+    tx: &L2Transaction,
+    force_polyjuice: bool,
+) -> Result<(H256, bool), LockAlgorithmError> {
+    if let Some(rlp_data) =
+        try_assemble_polyjuice_args(ctx, tx.raw(), receiver_script.clone(), !force_polyjuice)?
+    {
+        log::trace!("secp256k1_eth: polyjuice args assembled");
         let mut hasher = Keccak256::new();
-        hasher.update("\x19Ethereum Signed Message:\n32");
-        hasher.update(message.as_slice());
+        hasher.update(&rlp_data);
         let buf = hasher.finalize();
         let mut signing_message = [0u8; 32];
         signing_message.copy_from_slice(&buf[..]);
-        let signing_message = H256::from(signing_message);
+        return Ok((H256::from(signing_message), true));
+    }
 
-        self.verify_alone(lock_args, signature, signing_message)
+    log::trace!("secp256k1_eth: not a polyjuice call, use godwoken signing message");
+    let message = calc_godwoken_signing_message(ctx, sender_script, receiver_script, tx);
+    Ok((
+        wrap_signing_message(SigningScheme::EthPersonal, message),
+        false,
+    ))
+}
+
+/// Same as `Secp256k1Eth` except a tx sent to an account of the polyjuice generator
+/// (the receiver code hash is `polyjuice_code_hash`) is always verified as a polyjuice call,
+/// the Ethereum transaction is assembled even if the args don't start with the polyjuice magic.
+///
+/// It is for contract tooling relaying EVM transactions without the magic,
+/// txs to other receivers are verified the same as `Secp256k1Eth`.
+#[derive(Debug)]
+pub struct Secp256k1EthForcedPolyjuice {
+    polyjuice_code_hash: H256,
+}
+
+impl Secp256k1EthForcedPolyjuice {
+    pub fn new(polyjuice_code_hash: H256) -> Self {
+        Secp256k1EthForcedPolyjuice {
+            polyjuice_code_hash,
+        }
     }
 }
 
+impl LockAlgorithm for Secp256k1EthForcedPolyjuice {
+    fn verify_tx(
+        &self,
+        ctx: &RollupContext,
+        sender_script: Script,
+        receiver_script: Script,
+        tx: L2Transaction,
+    ) -> Result<bool, LockAlgorithmError> {
+        let receiver_code_hash: H256 = receiver_script.code_hash().unpack();
+        let force_polyjuice = receiver_code_hash == self.polyjuice_code_hash;
+        let (message, polyjuice) = calc_eth_signing_message_with_path(
+            ctx,
+            &sender_script,
+            &receiver_script,
+            &tx,
+            force_polyjuice,
+        )?;
+        log::debug!(
+            "secp256k1_eth verify_tx: receiver code hash {:?}, forced polyjuice: {}, polyjuice: {}",
+            receiver_code_hash,
+            force_polyjuice,
+            polyjuice
+        );
+        Secp256k1Eth.verify_alone(sender_script.args().unpack(), tx.signature(), message)
+    }
+
+    fn verify_withdrawal_signature(
+        &self,
+        lock_args: Bytes,
+        signature: Signature,
+        message: H256,
+    ) -> Result<bool, LockAlgorithmError> {
+        Secp256k1Eth.verify_withdrawal_signature(lock_args, signature, message)
+    }
+
+    fn verify_message_digest(
+        &self,
+        lock_args: Bytes,
+        signature: Signature,
+        digest: H256,
+    ) -> Result<bool, LockAlgorithmError> {
+        Secp256k1Eth.verify_alone(lock_args, signature, digest)
+    }
+}
+
+/// Same as `Secp256k1Eth` except the withdrawal signature is verified against
+/// the raw 32 bytes message without the personal sign prefix (like `Secp256k1`).
+///
+/// `Secp256k1Eth` expects withdrawals signed by wallets via personal sign,
+/// this one is for relayers which pre-hash and sign withdrawals on the server side,
+/// a signature is only accepted by one of the two.
+#[derive(Debug, Default)]
+pub struct Secp256k1EthRaw;
+
+impl LockAlgorithm for Secp256k1EthRaw {
+    fn verify_tx(
+        &self,
+        ctx: &RollupContext,
+        sender_script: Script,
+        receiver_script: Script,
+        tx: L2Transaction,
+    ) -> Result<bool, LockAlgorithmError> {
+        Secp256k1Eth.verify_tx(ctx, sender_script, receiver_script, tx)
+    }
+
+    fn verify_withdrawal_signature(
+        &self,
+        lock_args: Bytes,
+        signature: Signature,
+        message: H256,
+    ) -> Result<bool, LockAlgorithmError> {
+        Secp256k1Eth.verify_alone(lock_args, signature, message)
+    }
+
+    fn verify_message_digest(
+        &self,
+        lock_args: Bytes,
+        signature: Signature,
+        digest: H256,
+    ) -> Result<bool, LockAlgorithmError> {
+        Secp256k1Eth.verify_alone(lock_args, signature, digest)
+    }
+}
+
+/// Parse the recovery id of a compact recoverable signature, which must be in 0..=3
+fn parse_recovery_id(v: u8) -> Result<RecoveryId, LockAlgorithmError> {
+    if v > 3 {
+        return Err(LockAlgorithmError::InvalidRecoveryId(v));
+    }
+    RecoveryId::from_i32(v as i32).map_err(|_| LockAlgorithmError::InvalidRecoveryId(v))
+}
+
+/// An all-zero signature is the default value of an unsigned request,
+/// reject it before recovery to report a clearer error
+fn is_zero_signature(signature: &[u8; 65]) -> bool {
+    signature.iter().all(|b| *b == 0)
+}
+
+fn hex_address(address: &[u8; 20]) -> String {
+    address.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Normalize the `v` byte of a signature to a recovery id, accepts the raw
+/// recovery id (0..=3), the Ethereum form (27..=30) and the compressed key
+/// form (31..=34) produced by some Tron libraries
+fn normalize_recovery_id(v: u8) -> Result<RecoveryId, LockAlgorithmError> {
+    match v {
+        0..=3 => parse_recovery_id(v),
+        27..=30 => parse_recovery_id(v - 27),
+        31..=34 => parse_recovery_id(v - 31),
+        _ => Err(LockAlgorithmError::InvalidRecoveryId(v)),
+    }
+}
+
+/// How a wallet wraps the 32 bytes message before signing it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigningScheme {
+    /// The message (already a blake2b digest) is signed as is
+    CkbBlake2b,
+    /// Ethereum personal sign: keccak256("\x19Ethereum Signed Message:\n32" | message)
+    EthPersonal,
+    /// Ethereum typed data sign (EIP-712): keccak256("\x19\x01" | domain_separator | message),
+    /// the message is the `hashStruct` of the typed data
+    EthTyped { domain_separator: H256 },
+    /// Tron personal sign: keccak256("\x19TRON Signed Message:\n32" | message)
+    TronPersonal,
+    /// Bitcoin message sign of the hex encoded message:
+    /// sha256d("\x18Bitcoin Signed Message:\n" | "\x40" | hex(message))
+    BtcMessage,
+}
+
+/// Apply the prefix and hashing of the signing scheme to the message
+pub fn wrap_signing_message(scheme: SigningScheme, message: H256) -> H256 {
+    let keccak = |parts: &[&[u8]]| {
+        let mut hasher = Keccak256::new();
+        for part in parts {
+            hasher.update(part);
+        }
+        hasher.finalize()
+    };
+    let digest = match scheme {
+        SigningScheme::CkbBlake2b => return message,
+        SigningScheme::EthPersonal => {
+            keccak(&[b"\x19Ethereum Signed Message:\n32", message.as_slice()])
+        }
+        SigningScheme::EthTyped { domain_separator } => {
+            keccak(&[b"\x19\x01", domain_separator.as_slice(), message.as_slice()])
+        }
+        SigningScheme::TronPersonal => {
+            keccak(&[b"\x19TRON Signed Message:\n32", message.as_slice()])
+        }
+        SigningScheme::BtcMessage => {
+            let hex_message: String = message
+                .as_slice()
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect();
+            let mut hasher = Sha256::new();
+            hasher.update(b"\x18Bitcoin Signed Message:\n");
+            hasher.update(&[hex_message.len() as u8]);
+            hasher.update(hex_message.as_bytes());
+            Sha256::digest(&hasher.finalize())
+        }
+    };
+    let mut signing_message = [0u8; 32];
+    signing_message.copy_from_slice(&digest);
+    H256::from(signing_message)
+}
+
+fn recover_eth_address(
+    signature: Signature,
+    message: H256,
+) -> Result<[u8; 20], LockAlgorithmError> {
+    let signature: RecoverableSignature = {
+        let signature: [u8; 65] = signature.unpack();
+        if is_zero_signature(&signature) {
+            return Err(LockAlgorithmError::MissingSignature);
+        }
+        let recid = parse_recovery_id(signature[64])?;
+        let data = &signature[..64];
+        RecoverableSignature::from_compact(data, recid)
+            .map_err(|_| LockAlgorithmError::InvalidSignature)?
+    };
+    let msg = secp256k1::Message::from_slice(message.as_slice())
+        .map_err(|_| LockAlgorithmError::InvalidSignature)?;
+    let pubkey = SECP256K1
+        .recover(&msg, &signature)
+        .map_err(|_| LockAlgorithmError::InvalidSignature)?;
+    let mut hasher = Keccak256::new();
+    hasher.update(&pubkey.serialize_uncompressed()[1..]);
+    let buf = hasher.finalize();
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&buf[12..]);
+    Ok(address)
+}
+
 #[derive(Debug, Default)]
 pub struct Secp256k1Tron;
 
@@ -202,12 +474,7 @@ impl LockAlgorithm for Secp256k1Tron {
         receiver_script: Script,
         tx: L2Transaction,
     ) -> Result<bool, LockAlgorithmError> {
-        let message = calc_godwoken_signing_message(
-            &ctx.rollup_script_hash,
-            &sender_script,
-            &receiver_script,
-            &tx,
-        );
+        let message = calc_godwoken_signing_message(ctx, &sender_script, &receiver_script, &tx);
 
         self.verify_withdrawal_signature(sender_script.args().unpack(), tx.signature(), message)
     }
@@ -218,32 +485,28 @@ impl LockAlgorithm for Secp256k1Tron {
         signature: Signature,
         message: H256,
     ) -> Result<bool, LockAlgorithmError> {
-        if lock_args.len() != 52 {
-            return Err(LockAlgorithmError::InvalidLockArgs);
-        }
-        let mut hasher = Keccak256::new();
-        hasher.update("\x19TRON Signed Message:\n32");
-        hasher.update(message.as_slice());
-        let buf = hasher.finalize();
-        let mut signing_message = [0u8; 32];
-        signing_message.copy_from_slice(&buf[..]);
-        let signing_message = H256::from(signing_message);
-        let mut expected_pubkey_hash = [0u8; 20];
-        expected_pubkey_hash.copy_from_slice(&lock_args[32..52]);
+        let signing_message = wrap_signing_message(SigningScheme::TronPersonal, message);
+        self.verify_message_digest(lock_args, signature, signing_message)
+    }
+
+    fn verify_message_digest(
+        &self,
+        lock_args: Bytes,
+        signature: Signature,
+        digest: H256,
+    ) -> Result<bool, LockAlgorithmError> {
+        let expected_pubkey_hash = decode_pubkey_hash(&lock_args)?;
         let signature: RecoverableSignature = {
             let signature: [u8; 65] = signature.unpack();
-            let recid = {
-                let rec_param: i32 = match signature[64] {
-                    28 => 1,
-                    _ => 0,
-                };
-                RecoveryId::from_i32(rec_param).map_err(|_| LockAlgorithmError::InvalidSignature)?
-            };
+            if is_zero_signature(&signature) {
+                return Err(LockAlgorithmError::MissingSignature);
+            }
+            let recid = normalize_recovery_id(signature[64])?;
             let data = &signature[..64];
             RecoverableSignature::from_compact(data, recid)
                 .map_err(|_| LockAlgorithmError::InvalidSignature)?
         };
-        let msg = secp256k1::Message::from_slice(signing_message.as_slice())
+        let msg = secp256k1::Message::from_slice(digest.as_slice())
             .map_err(|_| LockAlgorithmError::InvalidSignature)?;
         let pubkey = SECP256K1
             .recover(&msg, &signature)
@@ -263,98 +526,425 @@ impl LockAlgorithm for Secp256k1Tron {
     }
 }
 
-fn calc_godwoken_signing_message(
+/// Build the account script of an Ethereum EoA,
+/// args: rollup_type_hash(32 bytes) | eth_address(20 bytes)
+pub fn build_eth_account_script(
+    lock_code_hash: &H256,
     rollup_type_hash: &H256,
+    eth_address: &[u8; 20],
+) -> Script {
+    build_secp256k1_account_script(lock_code_hash, rollup_type_hash, eth_address)
+}
+
+/// Build the account script of a Tron EoA,
+/// args: rollup_type_hash(32 bytes) | tron_address(20 bytes, without the 0x41 prefix)
+pub fn build_tron_account_script(
+    lock_code_hash: &H256,
+    rollup_type_hash: &H256,
+    tron_address: &[u8; 20],
+) -> Script {
+    build_secp256k1_account_script(lock_code_hash, rollup_type_hash, tron_address)
+}
+
+fn build_secp256k1_account_script(
+    lock_code_hash: &H256,
+    rollup_type_hash: &H256,
+    pubkey_hash: &[u8; 20],
+) -> Script {
+    let mut args = Vec::with_capacity(52);
+    args.extend_from_slice(rollup_type_hash.as_slice());
+    args.extend_from_slice(pubkey_hash);
+    Script::new_builder()
+        .code_hash(lock_code_hash.pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args(Bytes::from(args).pack())
+        .build()
+}
+
+/// Args of an account script: rollup_type_hash(32 bytes) | identity
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountArgs {
+    pub rollup_type_hash: H256,
+    /// e.g. the pubkey hash of an EoA, or the chain id(u32 LE) of a polyjuice account
+    pub identity: Bytes,
+}
+
+/// Split the args of an account script, None if the args are shorter than 32 bytes
+pub fn decode_account_args(script: &Script) -> Option<AccountArgs> {
+    split_account_args(&script.args().unpack())
+}
+
+/// Split the raw args of an account script, see `decode_account_args`
+pub fn split_account_args(args: &Bytes) -> Option<AccountArgs> {
+    if args.len() < 32 {
+        return None;
+    }
+    let mut rollup_type_hash = [0u8; 32];
+    rollup_type_hash.copy_from_slice(&args[..32]);
+    Some(AccountArgs {
+        rollup_type_hash: rollup_type_hash.into(),
+        identity: args.slice(32..),
+    })
+}
+
+/// Return the pubkey hash of secp256k1 lock args, which is the 20 bytes identity of the account args
+fn decode_pubkey_hash(lock_args: &Bytes) -> Result<[u8; 20], LockAlgorithmError> {
+    match split_account_args(lock_args) {
+        Some(AccountArgs { identity, .. }) if identity.len() == 20 => {
+            let mut pubkey_hash = [0u8; 20];
+            pubkey_hash.copy_from_slice(&identity);
+            Ok(pubkey_hash)
+        }
+        _ => Err(LockAlgorithmError::InvalidLockArgs {
+            expected: SECP256K1_LOCK_ARGS_LEN,
+            actual: lock_args.len(),
+        }),
+    }
+}
+
+/// Calculate the signing message of a L2 transaction
+///
+/// If `chain_id_domain_tag` of the rollup context is enabled, the digest becomes
+/// `blake2b(compatible_chain_id(u32 LE) | calc_message(..))` instead of `calc_message(..)`,
+/// signatures created for the untagged digest are no longer valid.
+fn calc_godwoken_signing_message(
+    ctx: &RollupContext,
     sender_script: &Script,
     receiver_script: &Script,
     tx: &L2Transaction,
 ) -> H256 {
-    tx.raw().calc_message(
-        &rollup_type_hash,
+    let message = tx.raw().calc_message(
+        ctx.rollup_script_hash(),
         &sender_script.hash().into(),
         &receiver_script.hash().into(),
-    )
+    );
+    if !ctx.chain_id_domain_tag() {
+        return message;
+    }
+    let chain_id: u32 = ctx.rollup_config().compatible_chain_id().unpack();
+    calc_chain_id_tagged_message(chain_id, &message)
 }
 
-fn try_assemble_polyjuice_args(
-    rollup_chain_id: u32,
-    raw_tx: RawL2Transaction,
-    receiver_script: Script,
-) -> Option<Bytes> {
-    let args: Bytes = raw_tx.args().unpack();
-    if args.len() < 52 {
-        return None;
-    }
-    if args[0..7] != b"\xFF\xFF\xFFPOLY"[..] {
-        return None;
+/// Convert the script hash of a polyjuice account to the EVM address form:
+/// script_hash[0..16] | to_id(u32 LE)
+pub fn script_hash_to_eth_address(hash: &H256, to_id: u32) -> [u8; 20] {
+    let mut address = [0u8; 20];
+    address[0..16].copy_from_slice(&hash.as_slice()[0..16]);
+    address[16..20].copy_from_slice(&to_id.to_le_bytes());
+    address
+}
+
+/// Type of the Ethereum transaction a polyjuice transaction is signed as,
+/// follows the EIP-2718 typed transaction envelope.
+///
+/// A typed transaction puts the type byte before the polyjuice args,
+/// the args of a legacy transaction start with the polyjuice header directly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolyjuiceTxType {
+    /// EIP-155 transaction
+    Legacy,
+    /// EIP-2930 transaction
+    AccessList,
+    /// EIP-1559 transaction
+    DynamicFee,
+}
+
+impl PolyjuiceTxType {
+    /// Unknown type bytes are treated as the legacy type
+    pub fn from_leading_byte(b: u8) -> Self {
+        match b {
+            0x01 => PolyjuiceTxType::AccessList,
+            0x02 => PolyjuiceTxType::DynamicFee,
+            _ => PolyjuiceTxType::Legacy,
+        }
     }
+}
+
+/// Fields of the Ethereum transaction assembled from a polyjuice transaction
+struct PolyjuiceTx {
+    nonce: u32,
+    gas_price: u128,
+    gas_limit: u64,
+    to: Vec<u8>,
+    value: u128,
+    data: Vec<u8>,
+    chain_id: u64,
+}
+
+fn build_legacy_preimage(tx: &PolyjuiceTx) -> Bytes {
     let mut stream = rlp::RlpStream::new();
     stream.begin_unbounded_list();
-    let nonce: u32 = raw_tx.nonce().unpack();
-    stream.append(&nonce);
+    stream.append(&tx.nonce);
+    stream.append(&tx.gas_price);
+    stream.append(&tx.gas_limit);
+    stream.append(&tx.to);
+    stream.append(&tx.value);
+    stream.append(&tx.data);
+    stream.append(&tx.chain_id);
+    stream.append(&0u8);
+    stream.append(&0u8);
+    stream.finalize_unbounded_list();
+    Bytes::from(stream.out().to_vec())
+}
+
+fn build_access_list_preimage(tx: &PolyjuiceTx) -> Bytes {
+    let mut stream = rlp::RlpStream::new();
+    stream.begin_unbounded_list();
+    stream.append(&tx.chain_id);
+    stream.append(&tx.nonce);
+    stream.append(&tx.gas_price);
+    stream.append(&tx.gas_limit);
+    stream.append(&tx.to);
+    stream.append(&tx.value);
+    stream.append(&tx.data);
+    // access list
+    stream.begin_list(0);
+    stream.finalize_unbounded_list();
+    let mut preimage = vec![0x01];
+    preimage.extend_from_slice(&stream.out());
+    Bytes::from(preimage)
+}
+
+// polyjuice only has the gas price, it is used as both max priority fee and max fee
+fn build_dynamic_fee_preimage(tx: &PolyjuiceTx) -> Bytes {
+    let mut stream = rlp::RlpStream::new();
+    stream.begin_unbounded_list();
+    stream.append(&tx.chain_id);
+    stream.append(&tx.nonce);
+    stream.append(&tx.gas_price);
+    stream.append(&tx.gas_price);
+    stream.append(&tx.gas_limit);
+    stream.append(&tx.to);
+    stream.append(&tx.value);
+    stream.append(&tx.data);
+    // access list
+    stream.begin_list(0);
+    stream.finalize_unbounded_list();
+    let mut preimage = vec![0x02];
+    preimage.extend_from_slice(&stream.out());
+    Bytes::from(preimage)
+}
+
+const POLYJUICE_MAGIC: &[u8] = b"\xFF\xFF\xFFPOLY";
+
+/// Split the tx type and the polyjuice args (started with the polyjuice magic) from the tx args,
+/// return None if the args are not polyjuice args, the magic isn't checked if `check_magic` is false
+pub(crate) fn split_polyjuice_args(
+    raw_args: &[u8],
+    check_magic: bool,
+) -> Option<(PolyjuiceTxType, &[u8])> {
+    // every slice is read via `get` so a crafted transaction can't panic the verifier
+    let has_magic = |args: &[u8]| args.get(0..7) == Some(POLYJUICE_MAGIC);
+    let (tx_type, args) = match PolyjuiceTxType::from_leading_byte(*raw_args.get(0)?) {
+        PolyjuiceTxType::Legacy => (PolyjuiceTxType::Legacy, raw_args),
+        // the type byte is only stripped when the magic follows it,
+        // magic-less args starting with 0x01 or 0x02 are kept as legacy args
+        tx_type if has_magic(&raw_args[1..]) => (tx_type, &raw_args[1..]),
+        _ => (PolyjuiceTxType::Legacy, raw_args),
+    };
+    if check_magic && !has_magic(args) {
+        return None;
+    }
+    Some((tx_type, args))
+}
+
+/// Return (gas_limit, gas_price) of polyjuice args
+fn parse_polyjuice_gas(args: &[u8]) -> Option<(u64, u128)> {
     let gas_price = {
         let mut data = [0u8; 16];
-        data.copy_from_slice(&args[16..32]);
+        data.copy_from_slice(args.get(16..32)?);
         u128::from_le_bytes(data)
     };
-    stream.append(&gas_price);
     let gas_limit = {
         let mut data = [0u8; 8];
-        data.copy_from_slice(&args[8..16]);
+        data.copy_from_slice(args.get(8..16)?);
         u64::from_le_bytes(data)
     };
-    stream.append(&gas_limit);
-    let (to, polyjuice_chain_id) = if args[7] == 3 {
+    Some((gas_limit, gas_price))
+}
+
+/// Return the max fee (gas_limit * gas_price) a polyjuice tx may pay,
+/// None if the tx is not a polyjuice tx or the fee overflows
+pub fn polyjuice_max_fee(raw_tx: &RawL2Transaction) -> Option<u128> {
+    let raw_args: Bytes = raw_tx.args().unpack();
+    let (_tx_type, args) = split_polyjuice_args(&raw_args, true)?;
+    let (gas_limit, gas_price) = parse_polyjuice_gas(args)?;
+    (gas_limit as u128).checked_mul(gas_price)
+}
+
+/// Assemble the Ethereum transaction preimage of a polyjuice tx, None if it isn't a polyjuice tx.
+///
+/// The chain id is `compatible_chain_id << 32 | polyjuice_chain_id`, a tx implying another
+/// chain id than the rollup is rejected with `ChainIdMismatch` instead of composing a digest
+/// that can't be recovered.
+fn try_assemble_polyjuice_args(
+    ctx: &RollupContext,
+    raw_tx: RawL2Transaction,
+    receiver_script: Script,
+    check_magic: bool,
+) -> Result<Option<Bytes>, LockAlgorithmError> {
+    check_polyjuice_chain_id(ctx, &raw_tx, &receiver_script, check_magic)?;
+    Ok(assemble_polyjuice_args(
+        ctx.rollup_config().compatible_chain_id().unpack(),
+        raw_tx,
+        receiver_script,
+        check_magic,
+    ))
+}
+
+/// Check the EVM chain id implied by a polyjuice tx equals the chain id of the rollup.
+///
+/// The upper half of both is `compatible_chain_id` of the rollup config, the lower half is
+/// checked against `RollupContext::polyjuice_chain_id` which the block producer always sets.
+fn check_polyjuice_chain_id(
+    ctx: &RollupContext,
+    raw_tx: &RawL2Transaction,
+    receiver_script: &Script,
+    check_magic: bool,
+) -> Result<(), LockAlgorithmError> {
+    let raw_args: Bytes = raw_tx.args().unpack();
+    let args = match split_polyjuice_args(&raw_args, check_magic) {
+        Some((_tx_type, args)) => args,
+        None => return Ok(()),
+    };
+    // not a valid polyjuice tx, leave the rejection to the assembling
+    let polyjuice_chain_id = match implied_polyjuice_chain_id(raw_tx, args, receiver_script) {
+        Some(polyjuice_chain_id) => polyjuice_chain_id,
+        None => return Ok(()),
+    };
+    let compatible_chain_id: u32 = ctx.rollup_config().compatible_chain_id().unpack();
+    let actual = evm_chain_id(compatible_chain_id, polyjuice_chain_id);
+    let expected = evm_chain_id(
+        compatible_chain_id,
+        ctx.polyjuice_chain_id().unwrap_or(polyjuice_chain_id),
+    );
+    if actual != expected {
+        return Err(LockAlgorithmError::ChainIdMismatch { expected, actual });
+    }
+    Ok(())
+}
+
+/// Polyjuice chain id implied by the args of a polyjuice tx
+fn implied_polyjuice_chain_id(
+    raw_tx: &RawL2Transaction,
+    args: &[u8],
+    receiver_script: &Script,
+) -> Option<u32> {
+    if *args.get(7)? == 3 {
         // 3 for EVMC_CREATE
         // In case of deploying a polyjuice contract, to id(creator account id)
         // is directly used as chain id
-        (vec![0u8; 0], raw_tx.to_id().unpack())
+        Some(raw_tx.to_id().unpack())
     } else {
         // For contract calling, chain id is read from scrpit args of
         // receiver_script, see the following link for more details:
         // https://github.com/nervosnetwork/godwoken-polyjuice#normal-contract-account-script
-        if receiver_script.args().len() < 36 {
-            return None;
-        }
-        let polyjuice_chain_id = {
-            let mut data = [0u8; 4];
-            data.copy_from_slice(&receiver_script.args().raw_data()[32..36]);
-            u32::from_le_bytes(data)
-        };
-        let mut to = vec![0u8; 20];
-        let receiver_hash = receiver_script.hash();
-        to[0..16].copy_from_slice(&receiver_hash[0..16]);
-        let to_id: u32 = raw_tx.to_id().unpack();
-        to[16..20].copy_from_slice(&to_id.to_le_bytes());
-        (to, polyjuice_chain_id)
+        let identity = decode_account_args(receiver_script)?.identity;
+        let mut data = [0u8; 4];
+        data.copy_from_slice(identity.get(..4)?);
+        Some(u32::from_le_bytes(data))
+    }
+}
+
+fn evm_chain_id(compatible_chain_id: u32, polyjuice_chain_id: u32) -> u64 {
+    ((compatible_chain_id as u64) << 32) | (polyjuice_chain_id as u64)
+}
+
+fn assemble_polyjuice_args(
+    rollup_chain_id: u32,
+    raw_tx: RawL2Transaction,
+    receiver_script: Script,
+    check_magic: bool,
+) -> Option<Bytes> {
+    let raw_args: Bytes = raw_tx.args().unpack();
+    let (tx_type, args) = split_polyjuice_args(&raw_args, check_magic)?;
+    let nonce: u32 = raw_tx.nonce().unpack();
+    let (gas_limit, gas_price) = parse_polyjuice_gas(args)?;
+    let polyjuice_chain_id = implied_polyjuice_chain_id(&raw_tx, args, &receiver_script)?;
+    let to = if args[7] == 3 {
+        // EVMC_CREATE has no to address
+        Vec::new()
+    } else {
+        script_hash_to_eth_address(&receiver_script.hash().into(), raw_tx.to_id().unpack()).to_vec()
     };
-    stream.append(&to);
     let value = {
         let mut data = [0u8; 16];
-        data.copy_from_slice(&args[32..48]);
+        data.copy_from_slice(args.get(32..48)?);
         u128::from_le_bytes(data)
     };
-    stream.append(&value);
     let payload_length = {
         let mut data = [0u8; 4];
-        data.copy_from_slice(&args[48..52]);
+        data.copy_from_slice(args.get(48..52)?);
         u32::from_le_bytes(data)
     } as usize;
-    if args.len() != 52 + payload_length {
+    let payload_end = 52usize.checked_add(payload_length)?;
+    if args.len() != payload_end {
         return None;
     }
-    stream.append(&args[52..52 + payload_length].to_vec());
-    let chain_id: u64 = ((rollup_chain_id as u64) << 32) | (polyjuice_chain_id as u64);
-    stream.append(&chain_id);
-    stream.append(&0u8);
-    stream.append(&0u8);
-    stream.finalize_unbounded_list();
-    Some(Bytes::from(stream.out().to_vec()))
+    let data = args.get(52..payload_end)?.to_vec();
+    let chain_id = evm_chain_id(rollup_chain_id, polyjuice_chain_id);
+    let tx = PolyjuiceTx {
+        nonce,
+        gas_price,
+        gas_limit,
+        to,
+        value,
+        data,
+        chain_id,
+    };
+    let preimage = match tx_type {
+        PolyjuiceTxType::Legacy => build_legacy_preimage(&tx),
+        PolyjuiceTxType::AccessList => build_access_list_preimage(&tx),
+        PolyjuiceTxType::DynamicFee => build_dynamic_fee_preimage(&tx),
+    };
+    Some(preimage)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::account_lock_manage::withdrawal_signing_message;
+    use crate::signing::{self, derive_pubkey_hash};
+    use gw_types::packed::{RawWithdrawalRequest, RollupConfig};
+
+    const PRIVKEY: [u8; 32] = [1u8; 32];
+
+    /// Context with a zero rollup script hash and the default rollup config
+    fn build_ctx() -> RollupContext {
+        RollupContext::new_unchecked(Default::default(), Default::default())
+    }
+
+    /// Build the lock args (rollup_type_hash | pubkey_hash) of the key for the scheme
+    fn build_lock_args(privkey: &[u8; 32], scheme: SigningScheme) -> Bytes {
+        let mut lock_args = vec![0u8; 32];
+        lock_args.extend_from_slice(&derive_pubkey_hash(privkey, scheme));
+        Bytes::from(lock_args)
+    }
+
+    #[test]
+    fn test_sign_withdrawal() {
+        let message = H256::from([42u8; 32]);
+        let cases: Vec<(SigningScheme, Box<dyn LockAlgorithm>)> = vec![
+            (SigningScheme::CkbBlake2b, Box::new(Secp256k1)),
+            (SigningScheme::EthPersonal, Box::new(Secp256k1Eth)),
+            (SigningScheme::TronPersonal, Box::new(Secp256k1Tron)),
+        ];
+        for (scheme, lock) in &cases {
+            let lock_args = build_lock_args(&PRIVKEY, *scheme);
+            let signature = signing::sign(&PRIVKEY, message, *scheme);
+            let valid = lock
+                .verify_withdrawal_signature(lock_args.clone(), signature, message)
+                .expect("verify signature");
+            assert!(valid, "{:?}", scheme);
+
+            // signed by another key
+            let signature = signing::sign(&[2u8; 32], message, *scheme);
+            let valid = lock
+                .verify_withdrawal_signature(lock_args, signature, message)
+                .expect("verify signature");
+            assert!(!valid, "{:?}", scheme);
+        }
+    }
 
     #[test]
     fn test_secp256k1_eth_withdrawal_signature() {
@@ -369,16 +959,66 @@ mod tests {
         lock_args.extend(address);
         let eth = Secp256k1Eth {};
         let result = eth
-            .verify_withdrawal_signature(lock_args.into(), test_signature, message)
+            .verify_withdrawal_signature(lock_args.into(), test_signature, message)
+            .expect("verify signature");
+        assert!(result);
+    }
+
+    #[test]
+    fn test_secp256k1_eth_polyjuice_call() {
+        let mut polyjuice_args = vec![0u8; 52];
+        polyjuice_args[0..7].copy_from_slice(b"\xFF\xFF\xFFPOLY");
+        polyjuice_args[7] = 0;
+        let gas_limit: u64 = 21000;
+        polyjuice_args[8..16].copy_from_slice(&gas_limit.to_le_bytes());
+        let gas_price: u128 = 20000000000;
+        polyjuice_args[16..32].copy_from_slice(&gas_price.to_le_bytes());
+        let value: u128 = 3000000;
+        polyjuice_args[32..48].copy_from_slice(&value.to_le_bytes());
+        let payload_length: u32 = 0;
+        polyjuice_args[48..52].copy_from_slice(&payload_length.to_le_bytes());
+
+        let raw_tx = RawL2Transaction::new_builder()
+            .nonce(9u32.pack())
+            .to_id(1234u32.pack())
+            .args(Bytes::from(polyjuice_args).pack())
+            .build();
+        let mut signature = [0u8; 65];
+        signature.copy_from_slice(&hex::decode("239ff31262bb6664d1857ea3bc5eecf3a4f74e32537c81de9fa1df2a2a48ef63115ffd8d6f5b4cc60b0fd4b02ab641106d024e49a9c0a9657c99361b39ce31ec00").expect("hex decode"));
+        let signature = Signature::from_slice(&signature[..]).unwrap();
+        let tx = L2Transaction::new_builder()
+            .raw(raw_tx)
+            .signature(signature)
+            .build();
+        let eth = Secp256k1Eth {};
+
+        let rollup_type_hash = vec![0u8; 32];
+
+        let mut sender_args = vec![];
+        sender_args.extend(&rollup_type_hash);
+        sender_args
+            .extend(&hex::decode("9d8A62f656a8d1615C1294fd71e9CFb3E4855A4F").expect("hex decode"));
+        let sender_script = Script::new_builder()
+            .args(Bytes::from(sender_args).pack())
+            .build();
+
+        let mut receiver_args = vec![];
+        receiver_args.extend(&rollup_type_hash);
+        receiver_args.extend(&23u32.to_le_bytes());
+        let receiver_script = Script::new_builder()
+            .args(Bytes::from(receiver_args).pack())
+            .build();
+        let ctx = build_ctx();
+        let result = eth
+            .verify_tx(&ctx, sender_script, receiver_script, tx)
             .expect("verify signature");
         assert!(result);
     }
 
     #[test]
-    fn test_secp256k1_eth_polyjuice_call() {
+    fn test_secp256k1_eth_forced_polyjuice_call_without_magic() {
+        // same as test_secp256k1_eth_polyjuice_call, the magic isn't a part of the signed RLP
         let mut polyjuice_args = vec![0u8; 52];
-        polyjuice_args[0..7].copy_from_slice(b"\xFF\xFF\xFFPOLY");
-        polyjuice_args[7] = 0;
         let gas_limit: u64 = 21000;
         polyjuice_args[8..16].copy_from_slice(&gas_limit.to_le_bytes());
         let gas_price: u128 = 20000000000;
@@ -400,10 +1040,8 @@ mod tests {
             .raw(raw_tx)
             .signature(signature)
             .build();
-        let eth = Secp256k1Eth {};
 
         let rollup_type_hash = vec![0u8; 32];
-
         let mut sender_args = vec![];
         sender_args.extend(&rollup_type_hash);
         sender_args
@@ -411,21 +1049,41 @@ mod tests {
         let sender_script = Script::new_builder()
             .args(Bytes::from(sender_args).pack())
             .build();
-
         let mut receiver_args = vec![];
         receiver_args.extend(&rollup_type_hash);
         receiver_args.extend(&23u32.to_le_bytes());
         let receiver_script = Script::new_builder()
             .args(Bytes::from(receiver_args).pack())
             .build();
-        let ctx = RollupContext {
-            rollup_script_hash: Default::default(),
-            rollup_config: Default::default(),
-        };
-        let result = eth
-            .verify_tx(&ctx, sender_script, receiver_script, tx)
+        let ctx = build_ctx();
+
+        // the receiver is a polyjuice account, its code hash is zero in this test
+        let forced = Secp256k1EthForcedPolyjuice::new(H256::zero());
+        let result = forced
+            .verify_tx(
+                &ctx,
+                sender_script.clone(),
+                receiver_script.clone(),
+                tx.clone(),
+            )
             .expect("verify signature");
         assert!(result);
+
+        // without the forced mode the godwoken message is verified
+        let result = Secp256k1Eth
+            .verify_tx(
+                &ctx,
+                sender_script.clone(),
+                receiver_script.clone(),
+                tx.clone(),
+            )
+            .expect("verify signature");
+        assert!(!result);
+        // the forced mode only applies to receivers of the polyjuice generator
+        let result = Secp256k1EthForcedPolyjuice::new([1u8; 32].into())
+            .verify_tx(&ctx, sender_script, receiver_script, tx)
+            .expect("verify signature");
+        assert!(!result);
     }
 
     #[test]
@@ -479,10 +1137,7 @@ mod tests {
         let receiver_script = Script::new_builder()
             .args(Bytes::from(receiver_args).pack())
             .build();
-        let ctx = RollupContext {
-            rollup_script_hash: Default::default(),
-            rollup_config: Default::default(),
-        };
+        let ctx = build_ctx();
         let result = eth
             .verify_tx(&ctx, sender_script, receiver_script, tx)
             .expect("verify signature");
@@ -534,10 +1189,7 @@ mod tests {
         let receiver_script = Script::new_builder()
             .args(Bytes::from(receiver_args).pack())
             .build();
-        let ctx = RollupContext {
-            rollup_script_hash: Default::default(),
-            rollup_config: Default::default(),
-        };
+        let ctx = build_ctx();
         let result = eth
             .verify_tx(&ctx, sender_script, receiver_script, tx)
             .expect("verify signature");
@@ -575,16 +1227,68 @@ mod tests {
         let receiver_script = Script::new_builder()
             .args(Bytes::from(receiver_args).pack())
             .build();
-        let ctx = RollupContext {
-            rollup_script_hash: Default::default(),
-            rollup_config: Default::default(),
-        };
+        let ctx = build_ctx();
         let result = eth
             .verify_tx(&ctx, sender_script, receiver_script, tx)
             .expect("verify signature");
         assert!(result);
     }
 
+    #[test]
+    fn test_secp256k1_eth_verify_tx_explain() {
+        let address = derive_pubkey_hash(&PRIVKEY, SigningScheme::EthPersonal);
+        let sender_script = Script::new_builder()
+            .args(build_lock_args(&PRIVKEY, SigningScheme::EthPersonal).pack())
+            .build();
+        let receiver_code_hash = H256::from([7u8; 32]);
+        let receiver_script = Script::new_builder()
+            .code_hash(receiver_code_hash.pack())
+            .args(Bytes::from(vec![0u8; 36]).pack())
+            .build();
+        let ctx = build_ctx();
+        let eth = Secp256k1Eth {};
+        let sign = |args: Vec<u8>| {
+            let raw_tx = RawL2Transaction::new_builder()
+                .to_id(1234u32.pack())
+                .args(Bytes::from(args).pack())
+                .build();
+            let tx = L2Transaction::new_builder().raw(raw_tx).build();
+            let message = eth
+                .calc_signing_message(&ctx, &sender_script, &receiver_script, &tx)
+                .unwrap();
+            // the signing message is already wrapped, it is signed as is
+            let signature = signing::sign(&PRIVKEY, message, SigningScheme::CkbBlake2b);
+            tx.as_builder().signature(signature).build()
+        };
+
+        let polyjuice_tx = sign(build_polyjuice_args(4, b"POLY"));
+        let (valid, explanation) = eth
+            .verify_tx_explain(&ctx, &sender_script, &receiver_script, &polyjuice_tx)
+            .expect("verify tx");
+        assert!(valid);
+        assert_eq!(
+            explanation,
+            VerifyTxExplanation {
+                algorithm: "secp256k1_eth",
+                receiver_code_hash,
+                polyjuice: true,
+                recovered_address: address,
+            }
+        );
+
+        let normal_tx = sign(vec![1u8; 8]);
+        let (valid, explanation) = eth
+            .verify_tx_explain(&ctx, &sender_script, &receiver_script, &normal_tx)
+            .expect("verify tx");
+        assert!(valid);
+        assert!(!explanation.polyjuice);
+        assert_eq!(explanation.recovered_address, address);
+        assert_eq!(
+            eth.verify_tx(&ctx, sender_script, receiver_script, normal_tx),
+            Ok(true)
+        );
+    }
+
     #[test]
     fn test_secp256k1_tron() {
         let message = H256::from([0u8; 32]);
@@ -602,4 +1306,725 @@ mod tests {
             .expect("verify signature");
         assert!(result);
     }
+
+    #[test]
+    fn test_secp256k1_tron_recovery_id_encodings() {
+        let lock_args = build_lock_args(&PRIVKEY, SigningScheme::TronPersonal);
+        let message = H256::from([42u8; 32]);
+        let signed: [u8; 65] =
+            signing::sign(&PRIVKEY, message, SigningScheme::TronPersonal).unpack();
+        let recid = signed[64];
+        let verify = |v: u8| {
+            let mut signature = signed;
+            signature[64] = v;
+            let signature = Signature::from_slice(&signature).unwrap();
+            Secp256k1Tron {}.verify_withdrawal_signature(lock_args.clone(), signature, message)
+        };
+
+        // raw, Ethereum and compressed key forms of the same signature
+        for &offset in &[0u8, 27, 31] {
+            assert_eq!(verify(recid + offset), Ok(true));
+            // the other parity recovers a different key
+            assert_ne!(verify((recid ^ 1) + offset), Ok(true));
+        }
+        for &v in &[4u8, 26, 35, 255] {
+            assert_eq!(verify(v), Err(LockAlgorithmError::InvalidRecoveryId(v)));
+        }
+    }
+
+    #[test]
+    fn test_signing_message_with_chain_id_domain_tag() {
+        let raw_tx = RawL2Transaction::new_builder()
+            .nonce(9u32.pack())
+            .to_id(1234u32.pack())
+            .build();
+        let tx = L2Transaction::new_builder().raw(raw_tx).build();
+        let sender_script = Script::new_builder()
+            .args(Bytes::from(vec![1u8; 52]).pack())
+            .build();
+        let receiver_script = Script::new_builder()
+            .args(Bytes::from(vec![2u8; 36]).pack())
+            .build();
+        let rollup_config = RollupConfig::new_builder()
+            .compatible_chain_id(42u32.pack())
+            .build();
+        let ctx = RollupContext::new_unchecked([3u8; 32].into(), rollup_config.clone());
+
+        // disabled by default, digest is unchanged
+        let untagged = calc_godwoken_signing_message(&ctx, &sender_script, &receiver_script, &tx);
+        let expected = tx.raw().calc_message(
+            ctx.rollup_script_hash(),
+            &sender_script.hash().into(),
+            &receiver_script.hash().into(),
+        );
+        assert_eq!(untagged, expected);
+
+        let ctx = ctx.with_chain_id_domain_tag(true);
+        let tagged = calc_godwoken_signing_message(&ctx, &sender_script, &receiver_script, &tx);
+        assert_ne!(tagged, untagged);
+
+        // different chain id produces a different digest
+        let ctx = RollupContext::new_unchecked(
+            [3u8; 32].into(),
+            rollup_config
+                .as_builder()
+                .compatible_chain_id(43u32.pack())
+                .build(),
+        )
+        .with_chain_id_domain_tag(true);
+        let other_chain =
+            calc_godwoken_signing_message(&ctx, &sender_script, &receiver_script, &tx);
+        assert_ne!(other_chain, tagged);
+    }
+
+    #[test]
+    fn test_secp256k1_eth_recover_sender() {
+        let raw_tx = RawL2Transaction::new_builder()
+            .nonce(9u32.pack())
+            .to_id(1234u32.pack())
+            .build();
+        let mut signature = [0u8; 65];
+        signature.copy_from_slice(&hex::decode("680e9afc606f3555d75fedb41f201ade6a5f270c3a2223730e25d93e764acc6a49ee917f9e3af4727286ae4bf3ce19a5b15f71ae359cf8c0c3fabc212cccca1e00").expect("hex decode"));
+        let signature = Signature::from_slice(&signature[..]).unwrap();
+        let tx = L2Transaction::new_builder()
+            .raw(raw_tx)
+            .signature(signature)
+            .build();
+        let eth = Secp256k1Eth {};
+
+        let rollup_type_hash = vec![0u8; 32];
+        let address = hex::decode("9d8A62f656a8d1615C1294fd71e9CFb3E4855A4F").expect("hex decode");
+
+        let mut sender_args = vec![];
+        sender_args.extend(&rollup_type_hash);
+        sender_args.extend(&address);
+        let sender_script = Script::new_builder()
+            .args(Bytes::from(sender_args).pack())
+            .build();
+
+        let mut receiver_args = vec![];
+        receiver_args.extend(&rollup_type_hash);
+        receiver_args.extend(&23u32.to_le_bytes());
+        let receiver_script = Script::new_builder()
+            .args(Bytes::from(receiver_args).pack())
+            .build();
+        let ctx = build_ctx();
+        let sender = eth
+            .recover_eth_sender(&ctx, &sender_script, &receiver_script, tx)
+            .expect("recover sender");
+        assert_eq!(&sender[..], &address[..]);
+    }
+
+    #[test]
+    fn test_invalid_lock_args_length() {
+        let eth = Secp256k1Eth {};
+        let lock_args = Bytes::from(vec![0u8; 20]);
+        let err = eth
+            .verify_withdrawal_signature(lock_args, Signature::default(), H256::zero())
+            .unwrap_err();
+        assert_eq!(
+            err,
+            LockAlgorithmError::InvalidLockArgs {
+                expected: 52,
+                actual: 20
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Invalid lock args length expected 52 actual 20"
+        );
+    }
+
+    fn build_polyjuice_args(payload_length: u32, payload: &[u8]) -> Vec<u8> {
+        let mut polyjuice_args = vec![0u8; 52];
+        polyjuice_args[0..7].copy_from_slice(b"\xFF\xFF\xFFPOLY");
+        polyjuice_args[7] = 0;
+        polyjuice_args[8..16].copy_from_slice(&21000u64.to_le_bytes());
+        polyjuice_args[16..32].copy_from_slice(&20000000000u128.to_le_bytes());
+        polyjuice_args[32..48].copy_from_slice(&3000000u128.to_le_bytes());
+        polyjuice_args[48..52].copy_from_slice(&payload_length.to_le_bytes());
+        polyjuice_args.extend_from_slice(payload);
+        polyjuice_args
+    }
+
+    fn assemble(args: Vec<u8>, receiver_args: Vec<u8>) -> Option<Bytes> {
+        let raw_tx = RawL2Transaction::new_builder()
+            .to_id(1234u32.pack())
+            .args(Bytes::from(args).pack())
+            .build();
+        let receiver_script = Script::new_builder()
+            .args(Bytes::from(receiver_args).pack())
+            .build();
+        let ctx = build_ctx();
+        try_assemble_polyjuice_args(&ctx, raw_tx, receiver_script, true).unwrap()
+    }
+
+    #[test]
+    fn test_polyjuice_chain_id_mismatch() {
+        let ctx = RollupContext::new_unchecked(
+            Default::default(),
+            RollupConfig::new_builder()
+                .compatible_chain_id(42u32.pack())
+                .build(),
+        )
+        .with_polyjuice_chain_id(Some(23));
+        let chain_id = |polyjuice_chain_id: u64| (42u64 << 32) | polyjuice_chain_id;
+        let build_raw_tx = |to_id: u32, call_kind: u8| {
+            let mut args = build_polyjuice_args(4, b"POLY");
+            args[7] = call_kind;
+            RawL2Transaction::new_builder()
+                .to_id(to_id.pack())
+                .args(Bytes::from(args).pack())
+                .build()
+        };
+        let build_receiver = |polyjuice_chain_id: u32| {
+            let mut receiver_args = vec![0u8; 32];
+            receiver_args.extend_from_slice(&polyjuice_chain_id.to_le_bytes());
+            Script::new_builder()
+                .args(Bytes::from(receiver_args).pack())
+                .build()
+        };
+
+        // call, the chain id is read from the receiver
+        let raw_tx = build_raw_tx(1234, 0);
+        let preimage = try_assemble_polyjuice_args(&ctx, raw_tx.clone(), build_receiver(23), true)
+            .unwrap()
+            .expect("polyjuice args");
+        assert_eq!(
+            decode_preimage(&preimage)[6],
+            rlp::encode(&chain_id(23)).to_vec()
+        );
+        let receiver_script = build_receiver(24);
+        let mismatch = LockAlgorithmError::ChainIdMismatch {
+            expected: chain_id(23),
+            actual: chain_id(24),
+        };
+        assert_eq!(
+            try_assemble_polyjuice_args(&ctx, raw_tx.clone(), receiver_script.clone(), true),
+            Err(mismatch.clone())
+        );
+        let tx = L2Transaction::new_builder().raw(raw_tx).build();
+        let sender_script = Script::new_builder()
+            .args(Bytes::from(vec![0u8; 52]).pack())
+            .build();
+        assert_eq!(
+            Secp256k1Eth.verify_tx(&ctx, sender_script, receiver_script, tx),
+            Err(mismatch)
+        );
+
+        // create, the chain id is the to id
+        let preimage =
+            try_assemble_polyjuice_args(&ctx, build_raw_tx(23, 3), build_receiver(0), true)
+                .unwrap()
+                .expect("polyjuice args");
+        assert_eq!(
+            decode_preimage(&preimage)[6],
+            rlp::encode(&chain_id(23)).to_vec()
+        );
+        assert_eq!(
+            try_assemble_polyjuice_args(&ctx, build_raw_tx(1234, 3), build_receiver(23), true),
+            Err(LockAlgorithmError::ChainIdMismatch {
+                expected: chain_id(23),
+                actual: chain_id(1234),
+            })
+        );
+
+        // without the polyjuice chain id, any chain id is accepted
+        let ctx = ctx.with_polyjuice_chain_id(None);
+        assert!(
+            try_assemble_polyjuice_args(&ctx, build_raw_tx(1234, 0), build_receiver(24), true)
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_polyjuice_args_with_truncated_args() {
+        let receiver_args = vec![0u8; 36];
+        let args = build_polyjuice_args(4, b"POLY");
+        assert!(assemble(args.clone(), receiver_args.clone()).is_some());
+        // every truncated args must be rejected without panic
+        for len in 0..args.len() {
+            assert!(assemble(args[..len].to_vec(), receiver_args.clone()).is_none());
+        }
+        // truncated receiver script args
+        for len in 0..36 {
+            assert!(assemble(args.clone(), vec![0u8; len]).is_none());
+        }
+    }
+
+    #[test]
+    fn test_polyjuice_args_with_oversized_args() {
+        let receiver_args = vec![0u8; 36];
+        // payload is longer than payload_length
+        let args = build_polyjuice_args(4, b"POLYJUICE");
+        assert!(assemble(args, receiver_args.clone()).is_none());
+        // payload_length overflows the args
+        let args = build_polyjuice_args(u32::MAX, b"POLY");
+        assert!(assemble(args, receiver_args.clone()).is_none());
+        let args = build_polyjuice_args(u32::MAX - 51, b"");
+        assert!(assemble(args, receiver_args).is_none());
+    }
+
+    #[test]
+    fn test_invalid_recovery_id() {
+        let mut lock_args = vec![0u8; 32];
+        lock_args.extend(&[1u8; 20]);
+        let lock_args = Bytes::from(lock_args);
+        for &recid in &[4u8, 255u8] {
+            let mut signature = [1u8; 65];
+            signature[64] = recid;
+            let signature = Signature::from_slice(&signature).unwrap();
+            let expected_err = LockAlgorithmError::InvalidRecoveryId(recid);
+
+            let err = Secp256k1 {}
+                .verify_withdrawal_signature(lock_args.clone(), signature.clone(), H256::zero())
+                .unwrap_err();
+            assert_eq!(err, expected_err);
+            let err = Secp256k1Eth {}
+                .verify_withdrawal_signature(lock_args.clone(), signature, H256::zero())
+                .unwrap_err();
+            assert_eq!(err, expected_err);
+        }
+        assert_eq!(
+            LockAlgorithmError::InvalidRecoveryId(4).to_string(),
+            "Invalid recovery id 4"
+        );
+    }
+
+    #[test]
+    fn test_zero_signature() {
+        let mut lock_args = vec![0u8; 32];
+        lock_args.extend(&[1u8; 20]);
+        let lock_args = Bytes::from(lock_args);
+        let locks: Vec<Box<dyn LockAlgorithm>> = vec![
+            Box::new(Secp256k1),
+            Box::new(Secp256k1Eth),
+            Box::new(Secp256k1EthRaw),
+            Box::new(Secp256k1Tron),
+        ];
+        for lock in &locks {
+            let err = lock
+                .verify_withdrawal_signature(lock_args.clone(), Signature::default(), H256::zero())
+                .unwrap_err();
+            assert_eq!(err, LockAlgorithmError::MissingSignature);
+        }
+
+        let ctx = build_ctx();
+        let sender_script = Script::new_builder().args(lock_args.pack()).build();
+        let tx = L2Transaction::new_builder()
+            .signature(Signature::default())
+            .build();
+        for lock in &locks {
+            let err = lock
+                .verify_tx(&ctx, sender_script.clone(), Script::default(), tx.clone())
+                .unwrap_err();
+            assert_eq!(err, LockAlgorithmError::MissingSignature);
+        }
+    }
+
+    #[test]
+    fn test_polyjuice_max_fee() {
+        let build_raw_tx = |args: Vec<u8>| {
+            RawL2Transaction::new_builder()
+                .args(Bytes::from(args).pack())
+                .build()
+        };
+        // gas limit 21000, gas price 20000000000
+        let args = build_polyjuice_args(0, &[]);
+        assert_eq!(
+            polyjuice_max_fee(&build_raw_tx(args.clone())),
+            Some(21000 * 20000000000)
+        );
+        // typed transaction
+        let mut typed_args = vec![0x02u8];
+        typed_args.extend_from_slice(&args);
+        assert_eq!(
+            polyjuice_max_fee(&build_raw_tx(typed_args)),
+            Some(21000 * 20000000000)
+        );
+
+        let mut overflow_args = args;
+        overflow_args[8..16].copy_from_slice(&u64::MAX.to_le_bytes());
+        overflow_args[16..32].copy_from_slice(&u128::MAX.to_le_bytes());
+        assert_eq!(polyjuice_max_fee(&build_raw_tx(overflow_args)), None);
+
+        assert_eq!(polyjuice_max_fee(&build_raw_tx(vec![1u8; 52])), None);
+        assert_eq!(polyjuice_max_fee(&build_raw_tx(Vec::new())), None);
+    }
+
+    #[test]
+    fn test_build_account_scripts() {
+        let lock_code_hash = H256::from([1u8; 32]);
+        let rollup_type_hash = H256::from([2u8; 32]);
+        let mut address = [0u8; 20];
+        address.copy_from_slice(
+            &hex::decode("ffafb3db9377769f5b59bfff6cd2cf942a34ab17").expect("hex decode"),
+        );
+        let script = build_eth_account_script(&lock_code_hash, &rollup_type_hash, &address);
+        let code_hash: H256 = script.code_hash().unpack();
+        assert_eq!(code_hash, lock_code_hash);
+        let hash_type: u8 = script.hash_type().into();
+        assert_eq!(hash_type, ScriptHashType::Type as u8);
+        let args: Bytes = script.args().unpack();
+        assert_eq!(args.len(), 52);
+        assert_eq!(&args[..32], rollup_type_hash.as_slice());
+        assert_eq!(&args[32..], &address[..]);
+        // the signature of test_secp256k1_eth_withdrawal_signature
+        let test_signature = Signature::from_slice(
+        &hex::decode("c2ae67217b65b785b1add7db1e9deb1df2ae2c7f57b9c29de0dfc40c59ab8d47341a863876660e3d0142b71248338ed71d2d4eb7ca078455565733095ac25a5800").expect("hex decode"))
+        .expect("create signature structure");
+        assert!(Secp256k1Eth {}
+            .verify_withdrawal_signature(args, test_signature, H256::zero())
+            .expect("verify signature"));
+
+        // the polyjuice `to` address is derived from the receiver script hash
+        let to_id = 1234u32;
+        let receiver_script =
+            build_eth_account_script(&lock_code_hash, &rollup_type_hash, &address);
+        let mut polyjuice_args = vec![0u8; 52];
+        polyjuice_args[0..7].copy_from_slice(b"\xFF\xFF\xFFPOLY");
+        let raw_tx = RawL2Transaction::new_builder()
+            .to_id(to_id.pack())
+            .args(Bytes::from(polyjuice_args).pack())
+            .build();
+        let ctx = RollupContext::new_unchecked(rollup_type_hash, Default::default());
+        let rlp_data = try_assemble_polyjuice_args(&ctx, raw_tx, receiver_script.clone(), true)
+            .unwrap()
+            .expect("polyjuice args");
+        let mut to = receiver_script.hash()[..16].to_vec();
+        to.extend_from_slice(&to_id.to_le_bytes());
+        assert!(rlp_data.windows(20).any(|w| w == &to[..]));
+
+        let mut address = [0u8; 20];
+        address.copy_from_slice(
+            &hex::decode("d0ebb370429e1cc8a7da1f7aeb2447083e15298b").expect("hex decode"),
+        );
+        let script = build_tron_account_script(&lock_code_hash, &rollup_type_hash, &address);
+        let args: Bytes = script.args().unpack();
+        assert_eq!(&args[..32], rollup_type_hash.as_slice());
+        assert_eq!(&args[32..], &address[..]);
+    }
+
+    #[test]
+    fn test_secp256k1_eth_verify_message_digest() {
+        let lock_args = build_lock_args(&PRIVKEY, SigningScheme::EthPersonal);
+        let digest = H256::from([42u8; 32]);
+
+        // the digest is signed as is, no personal sign prefix is applied
+        let signature = signing::sign(&PRIVKEY, digest, SigningScheme::CkbBlake2b);
+        assert_eq!(
+            Secp256k1Eth.verify_message_digest(lock_args.clone(), signature.clone(), digest),
+            Ok(true)
+        );
+        assert_eq!(
+            Secp256k1Eth.verify_withdrawal_signature(lock_args.clone(), signature, digest),
+            Ok(false)
+        );
+
+        // a personal signed message is not a valid signature of the digest
+        let signature = signing::sign(&PRIVKEY, digest, SigningScheme::EthPersonal);
+        assert_eq!(
+            Secp256k1Eth.verify_message_digest(lock_args.clone(), signature.clone(), digest),
+            Ok(false)
+        );
+        assert_eq!(
+            Secp256k1Eth.verify_withdrawal_signature(lock_args, signature, digest),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn test_decode_account_args() {
+        let lock_code_hash = H256::from([1u8; 32]);
+        let rollup_type_hash = H256::from([2u8; 32]);
+
+        // Eth EoA: rollup_type_hash | eth_address(20 bytes)
+        let address = [3u8; 20];
+        let script = build_eth_account_script(&lock_code_hash, &rollup_type_hash, &address);
+        let args = decode_account_args(&script).expect("decode args");
+        assert_eq!(args.rollup_type_hash, rollup_type_hash);
+        assert_eq!(&args.identity[..], &address[..]);
+
+        // polyjuice receiver: rollup_type_hash | chain_id(u32 LE)
+        let mut receiver_args = rollup_type_hash.as_slice().to_vec();
+        receiver_args.extend_from_slice(&42u32.to_le_bytes());
+        let script = Script::new_builder()
+            .args(Bytes::from(receiver_args).pack())
+            .build();
+        let args = decode_account_args(&script).expect("decode args");
+        assert_eq!(args.rollup_type_hash, rollup_type_hash);
+        assert_eq!(&args.identity[..], &42u32.to_le_bytes()[..]);
+
+        // too short to contain the rollup_type_hash
+        let script = Script::new_builder()
+            .args(Bytes::from(vec![0u8; 31]).pack())
+            .build();
+        assert_eq!(decode_account_args(&script), None);
+    }
+
+    #[test]
+    fn test_secp256k1_eth_raw_withdrawal_signature() {
+        let lock_args = build_lock_args(&PRIVKEY, SigningScheme::EthPersonal);
+        let message = H256::from([42u8; 32]);
+        let verify = |lock: &dyn LockAlgorithm, signature: Signature| {
+            lock.verify_withdrawal_signature(lock_args.clone(), signature, message)
+                .expect("verify signature")
+        };
+
+        // signed the raw message
+        let signature = signing::sign(&PRIVKEY, message, SigningScheme::CkbBlake2b);
+        assert!(verify(&Secp256k1EthRaw, signature.clone()));
+        assert!(!verify(&Secp256k1Eth, signature));
+        // signed with personal sign
+        let signature = signing::sign(&PRIVKEY, message, SigningScheme::EthPersonal);
+        assert!(!verify(&Secp256k1EthRaw, signature.clone()));
+        assert!(verify(&Secp256k1Eth, signature));
+    }
+
+    #[test]
+    fn test_secp256k1_eth_withdrawal_signing_message() {
+        let lock_args = build_lock_args(&PRIVKEY, SigningScheme::EthPersonal);
+
+        let rollup_type_hash = H256::from([3u8; 32]);
+        let req = RawWithdrawalRequest::new_builder()
+            .nonce(1u32.pack())
+            .capacity(1000u64.pack())
+            .build();
+        let message = withdrawal_signing_message(&rollup_type_hash, &req);
+        assert_eq!(message, req.calc_message(&rollup_type_hash));
+
+        // wallets sign the message via personal sign
+        let signature = signing::sign(&PRIVKEY, message, SigningScheme::EthPersonal);
+        let result = Secp256k1Eth {}
+            .verify_withdrawal_signature(lock_args, signature, message)
+            .expect("verify signature");
+        assert!(result);
+    }
+
+    #[test]
+    fn test_wrap_signing_message() {
+        let message = H256::from([0u8; 32]);
+        assert_eq!(
+            wrap_signing_message(SigningScheme::CkbBlake2b, message),
+            message
+        );
+
+        // sha256d("\x18Bitcoin Signed Message:\n" | "\x40" | "00" * 32)
+        let mut expected = [0u8; 32];
+        expected.copy_from_slice(
+            &hex::decode("4addc754534b201342581defa9fb723b142cbad902276057c89e597cfb0e4caf")
+                .expect("hex decode"),
+        );
+        assert_eq!(
+            wrap_signing_message(SigningScheme::BtcMessage, message),
+            H256::from(expected)
+        );
+        // the domain separator is committed
+        let typed = |domain_separator: [u8; 32]| {
+            wrap_signing_message(
+                SigningScheme::EthTyped {
+                    domain_separator: domain_separator.into(),
+                },
+                message,
+            )
+        };
+        assert_ne!(typed([1u8; 32]), typed([2u8; 32]));
+        assert_ne!(typed([0u8; 32]), message);
+
+        // every scheme recovers the signer of `signing::sign`
+        let schemes = vec![
+            SigningScheme::CkbBlake2b,
+            SigningScheme::EthPersonal,
+            SigningScheme::EthTyped {
+                domain_separator: [1u8; 32].into(),
+            },
+            SigningScheme::TronPersonal,
+            SigningScheme::BtcMessage,
+        ];
+        let address = derive_pubkey_hash(&PRIVKEY, SigningScheme::EthPersonal);
+        for scheme in schemes {
+            let signature = signing::sign(&PRIVKEY, message, scheme);
+            let signing_message = wrap_signing_message(scheme, message);
+            let recovered = recover_eth_address(signature, signing_message).expect("recover");
+            assert_eq!(recovered, address);
+        }
+
+        // vectors of test_secp256k1_eth_withdrawal_signature and test_secp256k1_tron
+        let cases = vec![
+            (
+                SigningScheme::EthPersonal,
+                "c2ae67217b65b785b1add7db1e9deb1df2ae2c7f57b9c29de0dfc40c59ab8d47341a863876660e3d0142b71248338ed71d2d4eb7ca078455565733095ac25a5800",
+                "ffafb3db9377769f5b59bfff6cd2cf942a34ab17",
+            ),
+            (
+                SigningScheme::TronPersonal,
+                "702ec8cd52a61093519de11433595ee7177bc8beaef2836714efe23e01bbb45f7f4a51c079f16cc742a261fe53fa3d731704a7687054764d424bd92963a82a241b",
+                "d0ebb370429e1cc8a7da1f7aeb2447083e15298b",
+            ),
+        ];
+        for (scheme, signature, address) in cases {
+            let mut signature = hex::decode(signature).expect("hex decode");
+            // normalize the Tron v byte
+            signature[64] = normalize_recovery_id(signature[64]).unwrap().to_i32() as u8;
+            let signature = Signature::from_slice(&signature).expect("signature");
+            let signing_message = wrap_signing_message(scheme, message);
+            let recovered = recover_eth_address(signature, signing_message).expect("recover");
+            assert_eq!(
+                recovered.to_vec(),
+                hex::decode(address).expect("hex decode")
+            );
+        }
+
+        // prefixes differ
+        assert_ne!(
+            wrap_signing_message(SigningScheme::EthPersonal, message),
+            wrap_signing_message(SigningScheme::TronPersonal, message)
+        );
+    }
+
+    #[test]
+    fn test_script_hash_to_eth_address() {
+        // receiver script of test_secp256k1_eth_polyjuice_call_with_to_containing_leading_zeros
+        let mut receiver_args =
+            hex::decode("cfdefce91f70f53167971f74bf1074b6b889be270306aabd34e67404b75dacab")
+                .expect("hex decode");
+        receiver_args.extend(&23u32.to_le_bytes());
+        let receiver_script = Script::new_builder()
+            .args(Bytes::from(receiver_args).pack())
+            .build();
+        let receiver_hash: H256 = receiver_script.hash().into();
+        assert_eq!(
+            hex::encode(receiver_hash.as_slice()),
+            "00002b003de527c1d67f2a2a348683ecc9598647c30884c89c5dcf6da1afbddd"
+        );
+        let address = script_hash_to_eth_address(&receiver_hash, 1234);
+        assert_eq!(
+            hex::encode(address),
+            "00002b003de527c1d67f2a2a348683ecd2040000"
+        );
+    }
+
+    fn decode_preimage(preimage: &[u8]) -> Vec<Vec<u8>> {
+        let rlp = rlp::Rlp::new(preimage);
+        (0..rlp.item_count().unwrap())
+            .map(|i| rlp.at(i).unwrap().as_raw().to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn test_polyjuice_tx_types() {
+        assert_eq!(
+            PolyjuiceTxType::from_leading_byte(0x01),
+            PolyjuiceTxType::AccessList
+        );
+        assert_eq!(
+            PolyjuiceTxType::from_leading_byte(0x02),
+            PolyjuiceTxType::DynamicFee
+        );
+        assert_eq!(
+            PolyjuiceTxType::from_leading_byte(0x00),
+            PolyjuiceTxType::Legacy
+        );
+        assert_eq!(
+            PolyjuiceTxType::from_leading_byte(0xFF),
+            PolyjuiceTxType::Legacy
+        );
+
+        let receiver_args = vec![0u8; 36];
+        let args = build_polyjuice_args(4, b"POLY");
+        let legacy = assemble(args.clone(), receiver_args.clone()).expect("legacy");
+        let legacy_items = decode_preimage(&legacy);
+        assert_eq!(legacy_items.len(), 9);
+
+        let mut typed_args = vec![0x01];
+        typed_args.extend_from_slice(&args);
+        let access_list = assemble(typed_args, receiver_args.clone()).expect("access list");
+        assert_eq!(access_list[0], 0x01);
+        let items = decode_preimage(&access_list[1..]);
+        assert_eq!(items.len(), 8);
+        // chain_id, nonce, gas_price, gas_limit, to, value, data, access_list
+        assert_eq!(items[0], legacy_items[6]);
+        assert_eq!(items[1..7], legacy_items[0..6]);
+        assert_eq!(items[7], vec![0xc0]);
+
+        let mut typed_args = vec![0x02];
+        typed_args.extend_from_slice(&args);
+        let dynamic_fee = assemble(typed_args, receiver_args.clone()).expect("dynamic fee");
+        assert_eq!(dynamic_fee[0], 0x02);
+        let items = decode_preimage(&dynamic_fee[1..]);
+        assert_eq!(items.len(), 9);
+        // chain_id, nonce, max_priority_fee, max_fee, gas_limit, to, value, data, access_list
+        assert_eq!(items[0], legacy_items[6]);
+        assert_eq!(items[1], legacy_items[0]);
+        assert_eq!(items[2], legacy_items[1]);
+        assert_eq!(items[3], legacy_items[1]);
+        assert_eq!(items[4..8], legacy_items[2..6]);
+        assert_eq!(items[8], vec![0xc0]);
+
+        // unknown type byte is handled as legacy, which isn't a polyjuice tx
+        let mut typed_args = vec![0x03];
+        typed_args.extend_from_slice(&args);
+        assert!(assemble(typed_args, receiver_args).is_none());
+    }
+
+    #[test]
+    fn test_split_polyjuice_args_type_byte() {
+        let polyjuice_args = build_polyjuice_args(4, b"POLY");
+        for &tx_type in &[0x01u8, 0x02u8] {
+            let mut args = vec![tx_type];
+            args.extend_from_slice(&polyjuice_args);
+            for &check_magic in &[true, false] {
+                let (parsed_type, parsed_args) = split_polyjuice_args(&args, check_magic).unwrap();
+                assert_eq!(parsed_type, PolyjuiceTxType::from_leading_byte(tx_type));
+                assert_eq!(parsed_args, &polyjuice_args[..]);
+            }
+
+            // magic-less args starting with a type byte aren't shifted
+            let mut args = polyjuice_args.clone();
+            args[0] = tx_type;
+            assert!(split_polyjuice_args(&args, true).is_none());
+            let (parsed_type, parsed_args) = split_polyjuice_args(&args, false).unwrap();
+            assert_eq!(parsed_type, PolyjuiceTxType::Legacy);
+            assert_eq!(parsed_args, &args[..]);
+        }
+    }
+
+    #[test]
+    fn test_secp256k1_eth_typed_polyjuice_call() {
+        let sender_script = Script::new_builder()
+            .args(build_lock_args(&PRIVKEY, SigningScheme::EthPersonal).pack())
+            .build();
+        let receiver_script = Script::new_builder()
+            .args(Bytes::from(vec![0u8; 36]).pack())
+            .build();
+        let ctx = build_ctx();
+
+        for &tx_type in &[0x01u8, 0x02u8] {
+            let mut args = vec![tx_type];
+            args.extend_from_slice(&build_polyjuice_args(4, b"POLY"));
+            let raw_tx = RawL2Transaction::new_builder()
+                .nonce(9u32.pack())
+                .to_id(1234u32.pack())
+                .args(Bytes::from(args).pack())
+                .build();
+            let preimage =
+                try_assemble_polyjuice_args(&ctx, raw_tx.clone(), receiver_script.clone(), true)
+                    .unwrap()
+                    .unwrap();
+            let mut hasher = Keccak256::new();
+            hasher.update(&preimage);
+            let mut digest = [0u8; 32];
+            digest.copy_from_slice(&hasher.finalize());
+            let signature = signing::sign(&PRIVKEY, digest.into(), SigningScheme::CkbBlake2b);
+            let tx = L2Transaction::new_builder()
+                .raw(raw_tx)
+                .signature(signature)
+                .build();
+            let result = Secp256k1Eth {}
+                .verify_tx(&ctx, sender_script.clone(), receiver_script.clone(), tx)
+                .expect("verify signature");
+            assert!(result);
+        }
+    }
 }
@@ -0,0 +1,86 @@
+//! Capacity accounting
+//!
+//! CKB capacities of requests are `u64`, the total of a batch of requests must be
+//! summed with overflow checks. Crediting an account's CKB balance goes through
+//! `State::mint_sudt` which already fails with `AmountOverflow` on overflow.
+
+use gw_common::error::ValidationError;
+use gw_types::{
+    packed::{DepositionRequest, RawWithdrawalRequest},
+    prelude::*,
+};
+
+/// Sum capacities of deposition requests, returns `CapacityOverflow` on overflow
+pub fn checked_total_capacity(requests: &[DepositionRequest]) -> Result<u64, ValidationError> {
+    checked_sum(requests.iter().map(|req| req.capacity().unpack()))
+}
+
+/// Sum capacities of withdrawal requests, returns `CapacityOverflow` on overflow
+pub fn checked_total_withdrawal_capacity(
+    requests: &[RawWithdrawalRequest],
+) -> Result<u64, ValidationError> {
+    checked_sum(requests.iter().map(|req| req.capacity().unpack()))
+}
+
+fn checked_sum<I: Iterator<Item = u64>>(capacities: I) -> Result<u64, ValidationError> {
+    capacities.try_fold(0u64, |total, capacity| {
+        total
+            .checked_add(capacity)
+            .ok_or(ValidationError::CapacityOverflow)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_deposit(capacity: u64) -> DepositionRequest {
+        DepositionRequest::new_builder()
+            .capacity(capacity.pack())
+            .build()
+    }
+
+    fn build_withdrawal(capacity: u64) -> RawWithdrawalRequest {
+        RawWithdrawalRequest::new_builder()
+            .capacity(capacity.pack())
+            .build()
+    }
+
+    #[test]
+    fn test_checked_total_capacity() {
+        assert_eq!(checked_total_capacity(&[]), Ok(0));
+        let deposits = vec![build_deposit(u64::MAX - 1), build_deposit(1)];
+        assert_eq!(checked_total_capacity(&deposits), Ok(u64::MAX));
+
+        let deposits = vec![build_deposit(u64::MAX - 1), build_deposit(2)];
+        assert_eq!(
+            checked_total_capacity(&deposits),
+            Err(ValidationError::CapacityOverflow)
+        );
+        let deposits = vec![build_deposit(u64::MAX); 3];
+        assert_eq!(
+            checked_total_capacity(&deposits),
+            Err(ValidationError::CapacityOverflow)
+        );
+    }
+
+    #[test]
+    fn test_checked_total_withdrawal_capacity() {
+        let withdrawals = vec![
+            build_withdrawal(u64::MAX / 2),
+            build_withdrawal(u64::MAX / 2),
+        ];
+        assert_eq!(
+            checked_total_withdrawal_capacity(&withdrawals),
+            Ok(u64::MAX - 1)
+        );
+        let withdrawals = vec![
+            build_withdrawal(u64::MAX / 2),
+            build_withdrawal(u64::MAX / 2 + 2),
+        ];
+        assert_eq!(
+            checked_total_withdrawal_capacity(&withdrawals),
+            Err(ValidationError::CapacityOverflow)
+        );
+    }
+}
@@ -0,0 +1,143 @@
+//! Transaction classification
+//!
+//! Admission control categorizes incoming transactions without executing them,
+//! the kind is decided by the receiver and the args header only, so it is a hint
+//! and the transaction may still fail in execution.
+
+use crate::{account_lock_manage::secp256k1::split_polyjuice_args, RollupContext};
+use gw_common::{builtins::RESERVED_ACCOUNT_ID, H256};
+use gw_types::{
+    bytes::Bytes,
+    packed::{L2Transaction, Script},
+    prelude::*,
+};
+
+/// The `call_kind` of polyjuice args to create a contract (EVMC_CREATE)
+const POLYJUICE_CALL_KIND_CREATE: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxKind {
+    /// Call the meta contract, e.g. to create an account
+    MetaCall,
+    /// Call a layer2 sUDT account, e.g. a transfer
+    SudtTransfer,
+    /// Call a polyjuice contract
+    PolyjuiceCall,
+    /// Create a polyjuice contract
+    PolyjuiceCreate,
+    Unknown,
+}
+
+/// Classify the tx by the receiver's code hash and the args header
+pub fn classify_l2_transaction(
+    ctx: &RollupContext,
+    tx: &L2Transaction,
+    receiver_script: &Script,
+) -> TxKind {
+    let raw_tx = tx.raw();
+    let to_id: u32 = raw_tx.to_id().unpack();
+    if to_id == RESERVED_ACCOUNT_ID {
+        return TxKind::MetaCall;
+    }
+    let code_hash: H256 = receiver_script.code_hash().unpack();
+    let l2_sudt_code_hash: H256 = ctx
+        .rollup_config()
+        .l2_sudt_validator_script_type_hash()
+        .unpack();
+    if code_hash == l2_sudt_code_hash {
+        return TxKind::SudtTransfer;
+    }
+    let raw_args: Bytes = raw_tx.args().unpack();
+    match split_polyjuice_args(&raw_args, true).and_then(|(_tx_type, args)| args.get(7).copied()) {
+        Some(POLYJUICE_CALL_KIND_CREATE) => TxKind::PolyjuiceCreate,
+        Some(_) => TxKind::PolyjuiceCall,
+        None => TxKind::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gw_types::packed::{RawL2Transaction, RollupConfig};
+
+    const SUDT_CODE_HASH: [u8; 32] = [1u8; 32];
+    const CONTRACT_CODE_HASH: [u8; 32] = [2u8; 32];
+
+    fn build_ctx() -> RollupContext {
+        RollupContext::new_unchecked(
+            Default::default(),
+            RollupConfig::new_builder()
+                .l2_sudt_validator_script_type_hash(SUDT_CODE_HASH.pack())
+                .build(),
+        )
+    }
+
+    fn build_tx(to_id: u32, args: Vec<u8>) -> L2Transaction {
+        let raw = RawL2Transaction::new_builder()
+            .to_id(to_id.pack())
+            .args(Bytes::from(args).pack())
+            .build();
+        L2Transaction::new_builder().raw(raw).build()
+    }
+
+    fn build_script(code_hash: [u8; 32]) -> Script {
+        Script::new_builder().code_hash(code_hash.pack()).build()
+    }
+
+    fn polyjuice_args(call_kind: u8) -> Vec<u8> {
+        let mut args = vec![0u8; 52];
+        args[0..7].copy_from_slice(b"\xFF\xFF\xFFPOLY");
+        args[7] = call_kind;
+        args
+    }
+
+    #[test]
+    fn test_classify_l2_transaction() {
+        let ctx = build_ctx();
+        let contract = build_script(CONTRACT_CODE_HASH);
+        let cases = vec![
+            (
+                build_tx(0, vec![]),
+                build_script([0u8; 32]),
+                TxKind::MetaCall,
+            ),
+            (
+                build_tx(1, vec![0u8; 8]),
+                build_script(SUDT_CODE_HASH),
+                TxKind::SudtTransfer,
+            ),
+            (
+                build_tx(2, polyjuice_args(0)),
+                contract.clone(),
+                TxKind::PolyjuiceCall,
+            ),
+            (
+                build_tx(2, polyjuice_args(3)),
+                contract.clone(),
+                TxKind::PolyjuiceCreate,
+            ),
+            // EIP-1559 typed transaction
+            (
+                build_tx(2, [&[0x02][..], &polyjuice_args(0)].concat()),
+                contract.clone(),
+                TxKind::PolyjuiceCall,
+            ),
+            // no polyjuice magic
+            (
+                build_tx(2, vec![0u8; 52]),
+                contract.clone(),
+                TxKind::Unknown,
+            ),
+            // truncated polyjuice header
+            (
+                build_tx(2, polyjuice_args(0)[..7].to_vec()),
+                contract.clone(),
+                TxKind::Unknown,
+            ),
+            (build_tx(2, vec![]), contract, TxKind::Unknown),
+        ];
+        for (tx, receiver_script, kind) in cases {
+            assert_eq!(classify_l2_transaction(&ctx, &tx, &receiver_script), kind);
+        }
+    }
+}
@@ -1,12 +1,32 @@
+use crate::{
+    error::SnapshotError,
+    snapshot::{
+        read_bytes, read_h256, read_section_header, read_u32, write_bytes, write_section_header,
+        SECTION_CODES, SECTION_LEAVES, SECTION_SCRIPTS,
+    },
+};
 use gw_common::{
     error::Error,
     smt::{default_store::DefaultStore, H256, SMT},
     state::State,
 };
 use gw_traits::CodeStore;
-use gw_types::{bytes::Bytes, packed::Script};
+use gw_types::{bytes::Bytes, packed::Script, prelude::*};
 use std::collections::HashMap;
+use std::io::{Read, Write};
+
+/// Snapshot layout:
+///
+/// magic | version: u32 | root: H256 | account_count: u32 | sections
+///
+/// sections are the leaves, scripts and codes sections described in the `snapshot` module.
+const SNAPSHOT_MAGIC: &[u8; 4] = b"GWSS";
+const SNAPSHOT_VERSION: u32 = 1;
 
+/// An in-memory state
+///
+/// Reads (`get_raw`, `calculate_root`, etc.) don't mutate the tree,
+/// so a `DummyState` can be shared between threads for concurrent reads, writes require `&mut self`.
 #[derive(Default)]
 pub struct DummyState {
     tree: SMT<DefaultStore<H256>>,
@@ -15,6 +35,89 @@ pub struct DummyState {
     codes: HashMap<H256, Bytes>,
 }
 
+impl DummyState {
+    /// Serialize the SMT leaves, account count, scripts and codes into a snapshot
+    pub fn export_snapshot<W: Write>(&self, w: &mut W) -> Result<(), SnapshotError> {
+        w.write_all(SNAPSHOT_MAGIC)?;
+        w.write_all(&SNAPSHOT_VERSION.to_le_bytes())?;
+        w.write_all(self.calculate_root()?.as_slice())?;
+        w.write_all(&self.account_count.to_le_bytes())?;
+
+        let mut leaves: Vec<(&H256, &H256)> = self
+            .tree
+            .store()
+            .leaves_map()
+            .iter()
+            .filter(|(_k, v)| !v.is_zero())
+            .collect();
+        leaves.sort_unstable_by_key(|(k, _v)| *k);
+        write_section_header(w, SECTION_LEAVES, leaves.len())?;
+        for (k, v) in leaves {
+            w.write_all(k.as_slice())?;
+            w.write_all(v.as_slice())?;
+        }
+
+        let mut scripts: Vec<_> = self.scripts.iter().collect();
+        scripts.sort_unstable_by_key(|(k, _v)| *k);
+        write_section_header(w, SECTION_SCRIPTS, scripts.len())?;
+        for (k, script) in scripts {
+            w.write_all(k.as_slice())?;
+            write_bytes(w, script.as_slice())?;
+        }
+
+        let mut codes: Vec<_> = self.codes.iter().collect();
+        codes.sort_unstable_by_key(|(k, _v)| *k);
+        write_section_header(w, SECTION_CODES, codes.len())?;
+        for (k, code) in codes {
+            w.write_all(k.as_slice())?;
+            write_bytes(w, code)?;
+        }
+        Ok(())
+    }
+
+    /// Rebuild a state from a snapshot, the rebuilt root must match the exported root
+    pub fn import_snapshot<R: Read>(r: &mut R) -> Result<DummyState, SnapshotError> {
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != SNAPSHOT_MAGIC {
+            return Err(SnapshotError::InvalidMagic);
+        }
+        let version = read_u32(r)?;
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        let expected_root = read_h256(r)?;
+
+        let mut state = DummyState::default();
+        state.account_count = read_u32(r)?;
+        for _ in 0..read_section_header(r, SECTION_LEAVES)? {
+            let key = read_h256(r)?;
+            let value = read_h256(r)?;
+            state.update_raw(key, value)?;
+        }
+        for _ in 0..read_section_header(r, SECTION_SCRIPTS)? {
+            let script_hash = read_h256(r)?;
+            let script =
+                Script::from_slice(&read_bytes(r)?).map_err(|_| SnapshotError::InvalidScript)?;
+            state.insert_script(script_hash, script);
+        }
+        for _ in 0..read_section_header(r, SECTION_CODES)? {
+            let data_hash = read_h256(r)?;
+            let data = read_bytes(r)?;
+            state.insert_data(data_hash, data.into());
+        }
+
+        let root = state.calculate_root()?;
+        if root != expected_root {
+            return Err(SnapshotError::RootMismatch {
+                expected: expected_root,
+                actual: root,
+            });
+        }
+        Ok(state)
+    }
+}
+
 impl State for DummyState {
     fn get_raw(&self, key: &H256) -> Result<H256, Error> {
         let v = self.tree.get(key)?;
@@ -24,6 +127,10 @@ impl State for DummyState {
         self.tree.update(key, value)?;
         Ok(())
     }
+    fn update_raws(&mut self, kvs: Vec<(H256, H256)>) -> Result<(), Error> {
+        self.tree.update_all(kvs)?;
+        Ok(())
+    }
     fn calculate_root(&self) -> Result<H256, Error> {
         let root = *self.tree.root();
         Ok(root)
@@ -1,5 +1,8 @@
 use ckb_vm::Error as VMError;
-use gw_common::{error::Error as StateError, sparse_merkle_tree::error::Error as SMTError, H256};
+use gw_common::{
+    account_lock::LockError, error::Error as StateError,
+    sparse_merkle_tree::error::Error as SMTError, H256,
+};
 use gw_types::packed::ChallengeTarget;
 use thiserror::Error;
 
@@ -28,14 +31,46 @@ impl From<StateError> for Error {
 
 #[derive(Error, Debug, PartialEq, Clone, Eq)]
 pub enum LockAlgorithmError {
-    #[error("Invalid lock args")]
-    InvalidLockArgs,
+    #[error("Invalid lock args length expected {expected} actual {actual}")]
+    InvalidLockArgs { expected: usize, actual: usize },
     #[error("Invalid signature")]
     InvalidSignature,
+    #[error("Missing signature")]
+    MissingSignature,
+    #[error("Invalid recovery id {0}")]
+    InvalidRecoveryId(u8),
     #[error("Unknown account lock")]
     UnknownAccountLock,
+    #[error("Unknown lock code hash {code_hash:?}")]
+    UnknownLockCodeHash { code_hash: H256 },
+    #[error("Disallowed account lock")]
+    DisallowedLock,
     #[error("Invalid transaction args")]
     InvalidTransactionArgs,
+    #[error("Chain id mismatch, the transaction implies chain id {actual} expected {expected}")]
+    ChainIdMismatch { expected: u64, actual: u64 },
+    #[error("Withdrawal is not supported by the account lock")]
+    WithdrawalNotSupported,
+    #[error("can't find script for account {account_id}")]
+    ScriptNotFound { account_id: u32 },
+    #[error("State error {0:?}")]
+    State(StateError),
+}
+
+impl From<StateError> for LockAlgorithmError {
+    fn from(err: StateError) -> Self {
+        LockAlgorithmError::State(err)
+    }
+}
+
+impl From<LockError> for LockAlgorithmError {
+    fn from(err: LockError) -> Self {
+        match err {
+            LockError::InvalidLockArgs { expected, actual } => {
+                LockAlgorithmError::InvalidLockArgs { expected, actual }
+            }
+        }
+    }
 }
 
 impl From<LockAlgorithmError> for Error {
@@ -44,6 +79,44 @@ impl From<LockAlgorithmError> for Error {
     }
 }
 
+/// Errors of an unconfigured rollup config
+#[derive(Error, Debug, PartialEq, Clone, Eq)]
+pub enum ConfigError {
+    #[error("Rollup script hash is zero")]
+    ZeroRollupScriptHash,
+    #[error("Rollup config {0} is zero")]
+    ZeroCodeHash(&'static str),
+}
+
+/// Errors of exporting or importing a state snapshot
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    #[error("IO error {0}")]
+    IO(#[from] std::io::Error),
+    #[error("State error {0:?}")]
+    State(StateError),
+    #[error("Invalid snapshot magic")]
+    InvalidMagic,
+    #[error("Unsupported snapshot version {0}")]
+    UnsupportedVersion(u32),
+    #[error("Unexpected snapshot section expected {expected} actual {actual}")]
+    UnexpectedSection { expected: u8, actual: u8 },
+    #[error("Invalid script in snapshot")]
+    InvalidScript,
+    #[error("Snapshot root mismatch expected {expected:?} actual {actual:?}")]
+    RootMismatch { expected: H256, actual: H256 },
+    #[error("Store error {0}")]
+    Store(String),
+    #[error("Snapshot can only be imported into a store without blocks")]
+    StoreNotEmpty,
+}
+
+impl From<StateError> for SnapshotError {
+    fn from(err: StateError) -> Self {
+        SnapshotError::State(err)
+    }
+}
+
 #[derive(Error, Debug, PartialEq, Clone, Eq)]
 pub enum DepositionError {
     #[error("Deposit faked CKB")]
@@ -68,6 +141,8 @@ pub enum WithdrawalError {
     WithdrawFakedCKB,
     #[error("Non positive sudt amount")]
     NonPositiveSUDTAmount,
+    #[error("Invalid sell terms")]
+    InvalidSellTerms,
 }
 
 impl From<WithdrawalError> for Error {
@@ -117,6 +192,8 @@ pub enum TransactionError {
     SMT(SMTError),
     #[error("invalid nonce expected {expected}, actual {actual}")]
     Nonce { expected: u32, actual: u32 },
+    #[error("nonce mismatch expected {expected}, got {got}")]
+    NonceMismatch { expected: u32, got: u32 },
     #[error("State error {0:?}")]
     State(StateError),
     #[error("can't find backend for script_hash {script_hash:?}")]
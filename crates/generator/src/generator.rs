@@ -1,5 +1,6 @@
 use crate::{
-    account_lock_manage::AccountLockManage,
+    account_cache::AccountCache,
+    account_lock_manage::{verify_tx_nonce, withdrawal_signing_message, AccountLockManage},
     backend_manage::BackendManage,
     error::{TransactionValidateError, WithdrawalError},
     RollupContext,
@@ -7,7 +8,7 @@ use crate::{
 use crate::{
     backend_manage::Backend,
     error::{Error, TransactionError, TransactionErrorWithContext},
-    sudt::build_l2_sudt_script,
+    sudt::sudt_account_id,
 };
 use crate::{error::AccountError, syscalls::L2Syscalls};
 use crate::{error::LockAlgorithmError, traits::StateExt};
@@ -15,7 +16,7 @@ use gw_common::{
     builtins::CKB_SUDT_ACCOUNT_ID,
     error::Error as StateError,
     h256_ext::H256Ext,
-    state::{build_account_field_key, State, GW_ACCOUNT_NONCE},
+    state::{build_account_field_key, AccountField, State},
     H256,
 };
 use gw_traits::{ChainStore, CodeStore};
@@ -24,7 +25,7 @@ use gw_types::{
     offchain::RunResult,
     packed::{
         AccountMerkleState, BlockInfo, ChallengeTarget, DepositionRequest, L2Block, L2Transaction,
-        RawL2Block, RawL2Transaction, TxReceipt, WithdrawalRequest,
+        RawL2Block, RawL2Transaction, Script, TxReceipt, WithdrawalRequest,
     },
     prelude::*,
 };
@@ -109,10 +110,7 @@ impl Generator {
         if capacity as u128 > ckb_balance {
             return Err(WithdrawalError::Overdraft.into());
         }
-        let l2_sudt_script_hash =
-            build_l2_sudt_script(&self.rollup_context, &sudt_script_hash).hash();
-        let sudt_id = state
-            .get_account_id_by_script_hash(&l2_sudt_script_hash.into())?
+        let sudt_id = sudt_account_id(&self.rollup_context, state, &sudt_script_hash)?
             .ok_or(AccountError::UnknownSUDT)?;
         if sudt_id != CKB_SUDT_ACCOUNT_ID {
             // check SUDT balance
@@ -160,8 +158,11 @@ impl Generator {
             .account_lock_manage
             .get_lock_algorithm(&lock_code_hash.into())
             .ok_or(LockAlgorithmError::UnknownAccountLock)?;
+        if !lock_algo.supports_withdrawal() {
+            return Err(LockAlgorithmError::WithdrawalNotSupported.into());
+        }
 
-        let message = raw.calc_message(&self.rollup_context.rollup_script_hash);
+        let message = withdrawal_signing_message(self.rollup_context.rollup_script_hash(), &raw);
         let valid_signature = lock_algo.verify_withdrawal_signature(
             account_script.args().unpack(),
             withdrawal_request.signature(),
@@ -182,20 +183,7 @@ impl Generator {
         state: &S,
         tx: &L2Transaction,
     ) -> Result<(), TransactionValidateError> {
-        let raw_tx = tx.raw();
-        let sender_id: u32 = raw_tx.from_id().unpack();
-
-        // verify nonce
-        let account_nonce: u32 = state.get_nonce(sender_id)?;
-        let nonce: u32 = raw_tx.nonce().unpack();
-        if nonce != account_nonce {
-            return Err(TransactionError::Nonce {
-                expected: account_nonce,
-                actual: nonce,
-            }
-            .into());
-        }
-
+        verify_tx_nonce(state, &tx.raw())?;
         Ok(())
     }
 
@@ -218,7 +206,6 @@ impl Generator {
             .into());
         }
         let script = state.get_script(&script_hash).expect("get script");
-        let lock_code_hash: [u8; 32] = script.code_hash().unpack();
 
         let receiver_script_hash = state.get_script_hash(receiver_id)?;
         if receiver_script_hash.is_zero() {
@@ -231,12 +218,12 @@ impl Generator {
             .get_script(&receiver_script_hash)
             .expect("get receiver script");
 
-        let lock_algo = self
-            .account_lock_manage()
-            .get_lock_algorithm(&lock_code_hash.into())
-            .ok_or(LockAlgorithmError::UnknownAccountLock)?;
-        let valid_signature =
-            lock_algo.verify_tx(&self.rollup_context, script, receiver_script, tx.clone())?;
+        let valid_signature = self.account_lock_manage().verify_tx(
+            &self.rollup_context,
+            script,
+            receiver_script,
+            tx.clone(),
+        )?;
         if !valid_signature {
             return Err(LockAlgorithmError::InvalidSignature.into());
         }
@@ -262,6 +249,7 @@ impl Generator {
         state.apply_deposition_requests(&self.rollup_context, &args.deposition_requests)?;
 
         // handle transactions
+        let account_cache = AccountCache::new();
         let block_info = get_block_info(&raw_block);
         let block_hash = raw_block.hash();
         let mut receipts = Vec::with_capacity(args.l2block.transactions().len());
@@ -286,7 +274,13 @@ impl Generator {
             }
             // build call context
             // NOTICE users only allowed to send HandleMessage CallType txs
-            let run_result = match self.execute_transaction(chain, state, &block_info, &raw_tx) {
+            let run_result = match self.execute_transaction_with_cache(
+                chain,
+                state,
+                &block_info,
+                &raw_tx,
+                &account_cache,
+            ) {
                 Ok(run_result) => run_result,
                 Err(err) => {
                     return Err(TransactionErrorWithContext::new(
@@ -301,10 +295,10 @@ impl Generator {
                 }
             };
             state.apply_run_result(&run_result)?;
+            account_cache.invalidate_by_run_result(&run_result);
 
             let post_state = {
-                let account_root = state.calculate_root()?;
-                let account_count = state.get_account_count()?;
+                let (account_root, account_count) = state.account_merkle_state()?;
                 AccountMerkleState::new_builder()
                     .merkle_root(account_root.pack())
                     .count(account_count.pack())
@@ -313,14 +307,7 @@ impl Generator {
             let tx_receipt = TxReceipt::new_builder()
                 .tx_witness_hash(tx.witness_hash().pack())
                 .post_state(post_state)
-                .read_data_hashes(
-                    run_result
-                        .read_data
-                        .into_iter()
-                        .map(|(hash, _)| hash.pack())
-                        .collect::<Vec<_>>()
-                        .pack(),
-                )
+                .read_data_hashes(run_result.read_data_hashes().pack())
                 .logs(run_result.logs.pack())
                 .build();
             receipts.push(tx_receipt);
@@ -331,25 +318,19 @@ impl Generator {
         Ok(result)
     }
 
-    fn load_backend<S: State + CodeStore>(&self, state: &S, script_hash: &H256) -> Option<Backend> {
-        log::debug!("load_backend for script_hash: {:?}", script_hash);
-        state
-            .get_script(&script_hash)
-            .and_then(|script| {
-                // only accept type script hash type for now
-                if script.hash_type() == ScriptHashType::Type.into() {
-                    let code_hash: [u8; 32] = script.code_hash().unpack();
-                    log::debug!("load_backend by code_hash: {:?}", code_hash);
-                    self.backend_manage.get_backend(&code_hash.into())
-                } else {
-                    log::error!(
-                        "Found a invalid account script which hash_type is data: {:?}",
-                        script
-                    );
-                    None
-                }
-            })
-            .cloned()
+    fn load_backend(&self, script: &Script) -> Option<Backend> {
+        // only accept type script hash type for now
+        if script.hash_type() == ScriptHashType::Type.into() {
+            let code_hash: [u8; 32] = script.code_hash().unpack();
+            log::debug!("load_backend by code_hash: {:?}", code_hash);
+            self.backend_manage.get_backend(&code_hash.into()).cloned()
+        } else {
+            log::error!(
+                "Found a invalid account script which hash_type is data: {:?}",
+                script
+            );
+            None
+        }
     }
 
     /// execute a layer2 tx
@@ -359,6 +340,18 @@ impl Generator {
         state: &S,
         block_info: &BlockInfo,
         raw_tx: &RawL2Transaction,
+    ) -> Result<RunResult, TransactionError> {
+        self.execute_transaction_with_cache(chain, state, block_info, raw_tx, &AccountCache::new())
+    }
+
+    /// execute a layer2 tx, reading the receiver's script through the block's account cache
+    fn execute_transaction_with_cache<S: State + CodeStore, C: ChainStore>(
+        &self,
+        chain: &C,
+        state: &S,
+        block_info: &BlockInfo,
+        raw_tx: &RawL2Transaction,
+        account_cache: &AccountCache,
     ) -> Result<RunResult, TransactionError> {
         let mut run_result = RunResult::default();
         {
@@ -375,9 +368,10 @@ impl Generator {
                 }));
             let mut machine = AsmMachine::new(machine_builder.build(), None);
             let account_id = raw_tx.to_id().unpack();
-            let script_hash = state.get_script_hash(account_id)?;
-            let backend = self
-                .load_backend(state, &script_hash)
+            let script_hash = account_cache.cached_script_hash(state, account_id)?;
+            let backend = account_cache
+                .cached_script(state, account_id)?
+                .and_then(|script| self.load_backend(&script))
                 .ok_or(TransactionError::BackendNotFound { script_hash })?;
             machine.load_program(&backend.generator, &[])?;
             let code = machine.run()?;
@@ -385,10 +379,11 @@ impl Generator {
                 return Err(TransactionError::InvalidExitCode(code));
             }
         }
+        run_result.set_gas_used_from_logs();
         // set nonce
         let sender_id: u32 = raw_tx.from_id().unpack();
         let nonce = state.get_nonce(sender_id)?;
-        let nonce_raw_key = build_account_field_key(sender_id, GW_ACCOUNT_NONCE);
+        let nonce_raw_key = build_account_field_key(sender_id, AccountField::Nonce);
         if run_result.read_values.get(&nonce_raw_key).is_none() {
             run_result
                 .read_values
@@ -1,10 +1,7 @@
-use crate::{traits::StateExt, RollupContext};
+use crate::traits::StateExt;
 use anyhow::Result;
 use gw_common::{
-    blake2b::new_blake2b,
-    builtins::{CKB_SUDT_ACCOUNT_ID, RESERVED_ACCOUNT_ID},
-    smt::H256,
-    state::State,
+    blake2b::new_blake2b, builtins::CKB_SUDT_ACCOUNT_ID, smt::H256, state::State,
     CKB_SUDT_SCRIPT_ARGS,
 };
 use gw_config::GenesisConfig;
@@ -19,7 +16,7 @@ use gw_types::{
     core::{ScriptHashType, Status},
     packed::{
         AccountMerkleState, BlockMerkleState, GlobalState, L2Block, L2BlockCommittedInfo,
-        RawL2Block, Script,
+        RawL2Block, RollupConfig, Script,
     },
     prelude::*,
 };
@@ -44,13 +41,10 @@ pub fn build_genesis_from_store(
     config: &GenesisConfig,
     secp_data: Bytes,
 ) -> Result<(StoreTransaction, GenesisWithGlobalState)> {
-    let rollup_context = RollupContext {
-        rollup_script_hash: {
-            let rollup_script_hash: [u8; 32] = config.rollup_type_hash.clone().into();
-            rollup_script_hash.into()
-        },
-        rollup_config: config.rollup_config.clone().into(),
-    };
+    // the genesis of a test chain may leave scripts unconfigured,
+    // so the config isn't validated by building a `RollupContext`
+    let rollup_script_hash: [u8; 32] = config.rollup_type_hash.clone().into();
+    let rollup_config: RollupConfig = config.rollup_config.clone().into();
     // initialize store
     db.set_account_smt_root(H256::zero())?;
     db.set_block_smt_root(H256::zero())?;
@@ -61,27 +55,24 @@ pub fn build_genesis_from_store(
     // create a reserved account
     // this account is reserved for special use
     // for example: send a tx to reserved account to create a new contract account
-    let reserved_id = tree.create_account_from_script(
-        Script::new_builder()
-            .code_hash({
-                let code_hash: [u8; 32] = config.meta_contract_validator_type_hash.clone().into();
-                code_hash.pack()
-            })
-            .hash_type(ScriptHashType::Type.into())
-            .args({
-                let rollup_script_hash: [u8; 32] = rollup_context.rollup_script_hash.into();
-                Bytes::from(rollup_script_hash.to_vec()).pack()
-            })
-            .build(),
-    )?;
-    assert_eq!(
-        reserved_id, RESERVED_ACCOUNT_ID,
-        "reserved account id must be zero"
-    );
+    let meta_script = Script::new_builder()
+        .code_hash({
+            let code_hash: [u8; 32] = config.meta_contract_validator_type_hash.clone().into();
+            code_hash.pack()
+        })
+        .hash_type(ScriptHashType::Type.into())
+        .args(Bytes::from(rollup_script_hash.to_vec()).pack())
+        .build();
+    let meta_script_hash: H256 = meta_script.hash().into();
+    tree.insert_script(meta_script_hash, meta_script);
+    tree.init_reserved_account(meta_script_hash)?;
 
     // setup CKB simple UDT contract
-    let ckb_sudt_script =
-        crate::sudt::build_l2_sudt_script(&rollup_context, &CKB_SUDT_SCRIPT_ARGS.into());
+    let ckb_sudt_script = crate::sudt::build_l2_sudt_script_from_config(
+        &rollup_script_hash.into(),
+        &rollup_config,
+        &CKB_SUDT_SCRIPT_ARGS.into(),
+    );
     let ckb_sudt_id = tree.create_account_from_script(ckb_sudt_script)?;
     assert_eq!(
         ckb_sudt_id, CKB_SUDT_ACCOUNT_ID,
@@ -90,8 +81,7 @@ pub fn build_genesis_from_store(
 
     // calculate post state
     let post_account = {
-        let root = tree.calculate_root()?;
-        let count = tree.get_account_count()?;
+        let (root, count) = tree.account_merkle_state()?;
         AccountMerkleState::new_builder()
             .merkle_root(root.pack())
             .count(count.pack())
@@ -134,7 +124,7 @@ pub fn build_genesis_from_store(
             .build();
         let rollup_config_hash = {
             let mut hasher = new_blake2b();
-            hasher.update(rollup_context.rollup_config.as_slice());
+            hasher.update(rollup_config.as_slice());
             let mut hash = [0u8; 32];
             hasher.finalize(&mut hash);
             hash
@@ -1,16 +1,24 @@
 //! Generator handle layer2 transactions and blocks,
 //! and generate new status that can be committed to layer1
 
+pub mod account_cache;
 pub mod account_lock_manage;
 pub mod backend_manage;
+pub mod capacity;
+pub mod classify;
 pub mod dummy_state;
 pub mod error;
 pub mod generator;
 pub mod genesis;
+pub mod overlay;
+#[cfg(any(test, feature = "signing"))]
+pub mod signing;
+pub mod snapshot;
 pub mod sudt;
 pub mod syscalls;
 pub mod traits;
 pub mod types;
+pub mod withdrawal;
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,334 @@
+//! Overlays collect writes upon a read-only state
+//!
+//! The base state is untouched, writes, scripts and data are kept in the overlay.
+//! Transactions touching disjoint accounts can be executed on their own overlays in parallel,
+//! then the diffs of overlays are committed to the base state in order.
+
+use gw_common::{
+    builtins::CKB_SUDT_ACCOUNT_ID,
+    error::Error,
+    h256_ext::H256Ext,
+    state::{
+        build_account_field_key, build_account_key, build_data_hash_key,
+        build_script_hash_to_account_id_key, AccountField, State, GW_ACCOUNT_NONCE,
+        GW_ACCOUNT_SCRIPT_HASH,
+    },
+    H256,
+};
+use gw_traits::CodeStore;
+use gw_types::{bytes::Bytes, packed::Script};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
+
+/// A state view which reads through to the base state and keeps writes in memory
+///
+/// An overlay created by `new_overlay_for_accounts` only reads and writes keys of the given accounts:
+/// nonce, script hash, account kv and sUDT balances held by the accounts.
+/// Other accounts and `set_account_count` return `Error::KeyOutOfOverlay`.
+///
+/// Raw keys, e.g. the keys syscalls read and write, are checked by the account id in nonce
+/// and script hash keys. Other keys are hashed, their owner can't be recovered from the raw key,
+/// so a hashed key is only accepted if the overlay has attributed it to a listed account:
+/// * script hash to id keys and CKB balances of the listed accounts, attributed on creation
+/// * keys built by the account kv and sUDT methods of `State`, attributed on use
+/// * keys attributed by `allow_sudt` and `allow_account_kv`, e.g. the storage a contract
+///   computes by itself
+/// * data hash keys written by `store_data_hash`, they're content addressed rather than owned
+///
+/// Any other raw key returns `Error::KeyOutOfOverlay`.
+///
+/// The overlay doesn't keep a tree, so `calculate_root` returns `Error::UnsupportedByOverlay` after any write.
+pub struct OverlayState<'a, S> {
+    base: &'a S,
+    writes: HashMap<H256, H256>,
+    account_count: u32,
+    scripts: HashMap<H256, Script>,
+    data: HashMap<H256, Bytes>,
+    /// None if the overlay isn't restricted
+    restriction: Option<Restriction>,
+}
+
+struct Restriction {
+    account_ids: HashSet<u32>,
+    /// Hashed raw keys attributed to the accounts
+    hashed_keys: RefCell<HashSet<H256>>,
+}
+
+impl<'a, S: State> OverlayState<'a, S> {
+    fn new(base: &'a S, account_ids: Option<&[u32]>) -> Result<Self, Error> {
+        let account_count = base.get_account_count()?;
+        let mut overlay = OverlayState {
+            base,
+            writes: HashMap::default(),
+            account_count,
+            scripts: HashMap::default(),
+            data: HashMap::default(),
+            restriction: None,
+        };
+        if let Some(account_ids) = account_ids {
+            let mut hashed_keys = HashSet::default();
+            for &id in account_ids {
+                let script_hash = base.get_script_hash(id)?;
+                if !script_hash.is_zero() {
+                    hashed_keys.insert(build_script_hash_to_account_id_key(script_hash.as_slice()));
+                }
+            }
+            overlay.restriction = Some(Restriction {
+                account_ids: account_ids.iter().copied().collect(),
+                hashed_keys: RefCell::new(hashed_keys),
+            });
+            // fees are paid in CKB
+            overlay.allow_sudt(CKB_SUDT_ACCOUNT_ID);
+        }
+        Ok(overlay)
+    }
+
+    /// Attribute the balances of the listed accounts in the sUDT to the overlay,
+    /// does nothing if the overlay isn't restricted
+    pub fn allow_sudt(&mut self, sudt_id: u32) {
+        if let Some(restriction) = self.restriction.as_ref() {
+            let mut hashed_keys = restriction.hashed_keys.borrow_mut();
+            for &id in &restriction.account_ids {
+                hashed_keys.insert(build_account_field_key(id, AccountField::Sudt(sudt_id)));
+            }
+        }
+    }
+
+    /// Attribute the kv of the account to the overlay, so it can be read and written
+    /// by the raw key, e.g. the storage key computed by a contract
+    pub fn allow_account_kv(&mut self, id: u32, key: &H256) -> Result<(), Error> {
+        self.attribute(id, build_account_key(id, key.as_slice()))
+    }
+
+    /// Keys written to the overlay with their latest values
+    pub fn writes(&self) -> &HashMap<H256, H256> {
+        &self.writes
+    }
+
+    /// Detach the diff from the base, so it can be committed once the borrow of the base ends
+    pub fn into_diff(self) -> OverlayDiff {
+        OverlayDiff {
+            writes: self.writes,
+            account_count: self.account_count,
+            scripts: self.scripts,
+            data: self.data,
+        }
+    }
+
+    fn check_account(&self, id: u32) -> Result<(), Error> {
+        match self.restriction.as_ref() {
+            Some(restriction) if !restriction.account_ids.contains(&id) => {
+                Err(Error::KeyOutOfOverlay)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the account then attribute the hashed key built from the account id to it
+    fn attribute(&self, id: u32, key: H256) -> Result<(), Error> {
+        self.check_account(id)?;
+        if let Some(restriction) = self.restriction.as_ref() {
+            restriction.hashed_keys.borrow_mut().insert(key);
+        }
+        Ok(())
+    }
+
+    fn check_raw_key(&self, key: &H256) -> Result<(), Error> {
+        let restriction = match self.restriction.as_ref() {
+            Some(restriction) => restriction,
+            None => return Ok(()),
+        };
+        match field_key_account(key) {
+            Some(id) => self.check_account(id),
+            None if restriction.hashed_keys.borrow().contains(key) => Ok(()),
+            None => Err(Error::KeyOutOfOverlay),
+        }
+    }
+
+    /// Attribute a content addressed key, which isn't owned by any account
+    fn attribute_content_key(&self, key: H256) {
+        if let Some(restriction) = self.restriction.as_ref() {
+            restriction.hashed_keys.borrow_mut().insert(key);
+        }
+    }
+
+    fn read(&self, key: &H256) -> Result<H256, Error> {
+        match self.writes.get(key) {
+            Some(value) => Ok(*value),
+            None => self.base.get_raw(key),
+        }
+    }
+}
+
+/// Writes, account count, scripts and data of an overlay, see `OverlayState::into_diff`
+#[derive(Debug, Default, Clone)]
+pub struct OverlayDiff {
+    pub writes: HashMap<H256, H256>,
+    pub account_count: u32,
+    pub scripts: HashMap<H256, Script>,
+    pub data: HashMap<H256, Bytes>,
+}
+
+impl OverlayDiff {
+    /// Commit the diff into a state, e.g. the base of the overlay,
+    /// the writes are applied in one batched update
+    pub fn commit_into<S: State + CodeStore>(self, state: &mut S) -> Result<(), Error> {
+        state.update_raws(self.writes.into_iter().collect())?;
+        state.set_account_count(self.account_count)?;
+        for (script_hash, script) in self.scripts {
+            state.insert_script(script_hash, script);
+        }
+        for (data_hash, data) in self.data {
+            state.insert_data(data_hash, data);
+        }
+        Ok(())
+    }
+}
+
+/// Return the account id of a nonce or script hash key, see `build_account_field_key`
+fn field_key_account(key: &H256) -> Option<u32> {
+    let key = key.as_slice();
+    let is_field_key = (key[4] == GW_ACCOUNT_NONCE || key[4] == GW_ACCOUNT_SCRIPT_HASH)
+        && key[5..].iter().all(|b| *b == 0);
+    if !is_field_key {
+        return None;
+    }
+    let id = key[..4].try_into().expect("id bytes");
+    Some(u32::from_le_bytes(id))
+}
+
+impl<'a, S: State> State for OverlayState<'a, S> {
+    fn get_raw(&self, key: &H256) -> Result<H256, Error> {
+        self.check_raw_key(key)?;
+        self.read(key)
+    }
+
+    fn update_raw(&mut self, key: H256, value: H256) -> Result<(), Error> {
+        self.check_raw_key(&key)?;
+        self.writes.insert(key, value);
+        Ok(())
+    }
+
+    fn get_account_count(&self) -> Result<u32, Error> {
+        Ok(self.account_count)
+    }
+
+    fn set_account_count(&mut self, count: u32) -> Result<(), Error> {
+        if self.restriction.is_some() {
+            return Err(Error::KeyOutOfOverlay);
+        }
+        self.account_count = count;
+        Ok(())
+    }
+
+    fn calculate_root(&self) -> Result<H256, Error> {
+        if !self.writes.is_empty() {
+            return Err(Error::UnsupportedByOverlay);
+        }
+        self.base.calculate_root()
+    }
+
+    fn store_data_hash(&mut self, data_hash: H256) -> Result<(), Error> {
+        let key = build_data_hash_key(data_hash.as_slice());
+        self.attribute_content_key(key);
+        self.writes.insert(key, H256::one());
+        Ok(())
+    }
+
+    fn is_data_hash_exist(&self, data_hash: &H256) -> Result<bool, Error> {
+        let key = build_data_hash_key(data_hash.as_slice());
+        self.attribute_content_key(key);
+        Ok(self.read(&key)? == H256::one())
+    }
+
+    // account kv and sUDT balances are checked and attributed by the account id
+
+    fn get_value(&self, id: u32, key: &H256) -> Result<H256, Error> {
+        let raw_key = build_account_key(id, key.as_slice());
+        self.attribute(id, raw_key)?;
+        self.read(&raw_key)
+    }
+
+    fn update_value(&mut self, id: u32, key: &H256, value: H256) -> Result<(), Error> {
+        let raw_key = build_account_key(id, key.as_slice());
+        self.attribute(id, raw_key)?;
+        self.writes.insert(raw_key, value);
+        Ok(())
+    }
+
+    fn get_sudt_balance(&self, sudt_id: u32, id: u32) -> Result<u128, Error> {
+        let raw_key = build_account_field_key(id, AccountField::Sudt(sudt_id));
+        self.attribute(id, raw_key)?;
+        let balance = self.read(&raw_key)?;
+        Ok(balance.to_u128())
+    }
+
+    fn mint_sudt(&mut self, sudt_id: u32, id: u32, amount: u128) -> Result<(), Error> {
+        let balance = self.get_sudt_balance(sudt_id, id)?;
+        let balance = balance.checked_add(amount).ok_or(Error::AmountOverflow)?;
+        self.writes.insert(
+            build_account_field_key(id, AccountField::Sudt(sudt_id)),
+            H256::from_u128(balance),
+        );
+        Ok(())
+    }
+
+    fn burn_sudt(&mut self, sudt_id: u32, id: u32, amount: u128) -> Result<(), Error> {
+        let balance = self.get_sudt_balance(sudt_id, id)?;
+        let balance = balance.checked_sub(amount).ok_or(Error::AmountOverflow)?;
+        self.writes.insert(
+            build_account_field_key(id, AccountField::Sudt(sudt_id)),
+            H256::from_u128(balance),
+        );
+        Ok(())
+    }
+}
+
+/// Scripts and data are content addressed, they can be inserted by any overlay
+impl<'a, S: CodeStore> CodeStore for OverlayState<'a, S> {
+    fn insert_script(&mut self, script_hash: H256, script: Script) {
+        self.scripts.insert(script_hash, script);
+    }
+
+    fn get_script(&self, script_hash: &H256) -> Option<Script> {
+        self.scripts
+            .get(script_hash)
+            .cloned()
+            .or_else(|| self.base.get_script(script_hash))
+    }
+
+    fn insert_data(&mut self, data_hash: H256, code: Bytes) {
+        self.data.insert(data_hash, code);
+    }
+
+    fn get_data(&self, data_hash: &H256) -> Option<Bytes> {
+        self.data
+            .get(data_hash)
+            .cloned()
+            .or_else(|| self.base.get_data(data_hash))
+    }
+}
+
+pub trait OverlayExt: State + Sized {
+    /// Create an overlay which can read and write any key
+    fn new_overlay(&self) -> Result<OverlayState<'_, Self>, Error>;
+    /// Create an overlay restricted to keys of the accounts, see `OverlayState`
+    fn new_overlay_for_accounts(
+        &self,
+        account_ids: &[u32],
+    ) -> Result<OverlayState<'_, Self>, Error>;
+}
+
+impl<S: State> OverlayExt for S {
+    fn new_overlay(&self) -> Result<OverlayState<'_, Self>, Error> {
+        OverlayState::new(self, None)
+    }
+
+    fn new_overlay_for_accounts(
+        &self,
+        account_ids: &[u32],
+    ) -> Result<OverlayState<'_, Self>, Error> {
+        OverlayState::new(self, Some(account_ids))
+    }
+}
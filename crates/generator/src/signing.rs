@@ -0,0 +1,81 @@
+//! Signing helpers for tests
+//!
+//! Production code only recovers public keys from signatures,
+//! this module signs messages the way wallets of each `SigningScheme` do.
+//! Enabled in tests or with the `signing` feature.
+
+use crate::account_lock_manage::secp256k1::{wrap_signing_message, SigningScheme, SECP256K1};
+use gw_common::{blake2b::new_blake2b, H256};
+use gw_types::{packed::Signature, prelude::*};
+use sha3::{Digest, Keccak256};
+
+/// Derive the 20 bytes pubkey hash of the private key for the scheme
+///
+/// * CkbBlake2b: blake2b(compressed pubkey)[..20]
+/// * EthPersonal, EthTyped and TronPersonal: keccak256(uncompressed pubkey)[12..]
+///
+/// BtcMessage is only a signing scheme here, no lock algorithm keeps a Bitcoin pubkey hash
+/// (ripemd160(sha256(pubkey))) in its args, so it panics.
+pub fn derive_pubkey_hash(privkey: &[u8; 32], scheme: SigningScheme) -> [u8; 20] {
+    let secret_key = secp256k1::SecretKey::from_slice(privkey).expect("secret key");
+    let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, &secret_key);
+    let mut pubkey_hash = [0u8; 20];
+    match scheme {
+        SigningScheme::CkbBlake2b => {
+            let mut buf = [0u8; 32];
+            let mut hasher = new_blake2b();
+            hasher.update(&pubkey.serialize());
+            hasher.finalize(&mut buf);
+            pubkey_hash.copy_from_slice(&buf[..20]);
+        }
+        SigningScheme::EthPersonal
+        | SigningScheme::EthTyped { .. }
+        | SigningScheme::TronPersonal => {
+            let mut hasher = Keccak256::new();
+            hasher.update(&pubkey.serialize_uncompressed()[1..]);
+            pubkey_hash.copy_from_slice(&hasher.finalize()[12..]);
+        }
+        SigningScheme::BtcMessage => panic!("Bitcoin pubkey hash isn't supported"),
+    }
+    pubkey_hash
+}
+
+/// Sign the message with the private key, the message is wrapped by the scheme first,
+/// the signature is accepted by the lock algorithm of the scheme
+pub fn sign(privkey: &[u8; 32], message: H256, scheme: SigningScheme) -> Signature {
+    let secret_key = secp256k1::SecretKey::from_slice(privkey).expect("secret key");
+    let signing_message = wrap_signing_message(scheme, message);
+    let msg = secp256k1::Message::from_slice(signing_message.as_slice()).expect("message");
+    let (recid, data) = SECP256K1
+        .sign_recoverable(&msg, &secret_key)
+        .serialize_compact();
+    let mut signature = [0u8; 65];
+    signature[..64].copy_from_slice(&data);
+    signature[64] = recid.to_i32() as u8;
+    Signature::from_slice(&signature).expect("signature")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derive_pubkey_hash() {
+        // the well known address of private key 0x0101..01
+        let address = hex::decode("1a642f0e3c3af545e7acbd38b07251b3990914f1").unwrap();
+        let privkey = [1u8; 32];
+        assert_eq!(
+            &derive_pubkey_hash(&privkey, SigningScheme::EthPersonal)[..],
+            &address[..]
+        );
+        // Tron shares the address of Ethereum without the 0x41 prefix
+        assert_eq!(
+            &derive_pubkey_hash(&privkey, SigningScheme::TronPersonal)[..],
+            &address[..]
+        );
+        assert_ne!(
+            &derive_pubkey_hash(&privkey, SigningScheme::CkbBlake2b)[..],
+            &address[..]
+        );
+    }
+}
@@ -0,0 +1,234 @@
+//! State snapshots
+//!
+//! A snapshot is `magic | version: u32 | header | sections`, each section is
+//! `tag: u8 | entries_count: u64 | entries` and sections are written in tag order.
+//! All integers are little endian, variable length values are prefixed with a u32 length.
+//!
+//! `DummyState` snapshots hold the leaves, scripts and codes sections. Store snapshots
+//! additionally hold the blocks section, see `export_store_snapshot`.
+
+use crate::error::SnapshotError;
+use gw_common::{smt::SMT, state::State, H256};
+use gw_store::{
+    state_db::{StateDBTransaction, StateDBVersion, StateTree},
+    transaction::StoreTransaction,
+};
+use gw_traits::CodeStore;
+use gw_types::{packed::Script, prelude::*};
+use std::fmt::Display;
+use std::io::{Read, Write};
+
+/// entries: key: H256 | value: H256
+pub(crate) const SECTION_LEAVES: u8 = 1;
+/// entries: script_hash: H256 | script: bytes
+pub(crate) const SECTION_SCRIPTS: u8 = 2;
+/// entries: data_hash: H256 | data: bytes
+pub(crate) const SECTION_CODES: u8 = 3;
+/// entries: block_hash: H256, in block number order from the genesis
+pub(crate) const SECTION_BLOCKS: u8 = 4;
+
+/// Store snapshot layout:
+///
+/// magic | version: u32 | account_root: H256 | account_count: u32 | block_root: H256 | sections
+///
+/// the entries count of the blocks section is the block count.
+const STORE_SNAPSHOT_MAGIC: &[u8; 4] = b"GWST";
+const STORE_SNAPSHOT_VERSION: u32 = 1;
+
+/// Serialize the account tree leaves, account count, scripts and data of the account root
+/// submitted to `db`, and the hashes of the main chain blocks, into a snapshot.
+///
+/// Block bodies, transactions and receipts aren't included.
+pub fn export_store_snapshot<W: Write>(
+    db: &StoreTransaction,
+    w: &mut W,
+) -> Result<(), SnapshotError> {
+    let account_root = db.get_account_smt_root().map_err(store_error)?;
+    let account_count = db.get_account_count().map_err(store_error)?;
+    let block_root = db.get_block_smt_root().map_err(store_error)?;
+    // read the latest version of every key
+    let state_db =
+        StateDBTransaction::from_version(db, StateDBVersion::from_future_state(u64::MAX, u32::MAX))
+            .map_err(store_error)?;
+    let smt_store = state_db.account_smt_store().map_err(store_error)?;
+    let tree = StateTree::new(&state_db, SMT::new(account_root, smt_store), account_count);
+
+    w.write_all(STORE_SNAPSHOT_MAGIC)?;
+    w.write_all(&STORE_SNAPSHOT_VERSION.to_le_bytes())?;
+    w.write_all(account_root.as_slice())?;
+    w.write_all(&account_count.to_le_bytes())?;
+    w.write_all(block_root.as_slice())?;
+
+    let mut leaves = tree.get_kv_range(&[], usize::MAX)?;
+    leaves.sort_unstable_by_key(|(k, _v)| *k);
+    write_section_header(w, SECTION_LEAVES, leaves.len())?;
+    for (k, v) in leaves {
+        w.write_all(k.as_slice())?;
+        w.write_all(v.as_slice())?;
+    }
+
+    let mut scripts: Vec<(H256, Script)> = tree
+        .get_script_hashes()
+        .into_iter()
+        .filter_map(|k| tree.get_script(&k).map(|script| (k, script)))
+        .collect();
+    scripts.sort_unstable_by_key(|(k, _v)| *k);
+    write_section_header(w, SECTION_SCRIPTS, scripts.len())?;
+    for (k, script) in scripts {
+        w.write_all(k.as_slice())?;
+        write_bytes(w, script.as_slice())?;
+    }
+
+    let mut codes: Vec<_> = tree
+        .get_data_hashes()
+        .into_iter()
+        .filter_map(|k| tree.get_data(&k).map(|data| (k, data)))
+        .collect();
+    codes.sort_unstable_by_key(|(k, _v)| *k);
+    write_section_header(w, SECTION_CODES, codes.len())?;
+    for (k, code) in codes {
+        w.write_all(k.as_slice())?;
+        write_bytes(w, &code)?;
+    }
+
+    let block_count = db.block_count().map_err(store_error)?;
+    write_section_header(w, SECTION_BLOCKS, block_count as usize)?;
+    for number in 0..block_count {
+        let block_hash = db
+            .get_block_hash_by_number(number)
+            .map_err(store_error)?
+            .ok_or_else(|| SnapshotError::Store(format!("block {} hash not found", number)))?;
+        w.write_all(block_hash.as_slice())?;
+    }
+    Ok(())
+}
+
+/// Restore a store snapshot into `db`, which must not have any block.
+/// Both rebuilt roots must match the exported roots, `db` isn't committed.
+///
+/// The state is written at the genesis version and the account tree is submitted,
+/// since block bodies aren't restored, read it with a `StateTree` built from the submitted root
+/// rather than a history version.
+pub fn import_store_snapshot<R: Read>(
+    db: &StoreTransaction,
+    r: &mut R,
+) -> Result<(), SnapshotError> {
+    let mut magic = [0u8; 4];
+    r.read_exact(&mut magic)?;
+    if &magic != STORE_SNAPSHOT_MAGIC {
+        return Err(SnapshotError::InvalidMagic);
+    }
+    let version = read_u32(r)?;
+    if version != STORE_SNAPSHOT_VERSION {
+        return Err(SnapshotError::UnsupportedVersion(version));
+    }
+    if db.block_count().map_err(store_error)? != 0 {
+        return Err(SnapshotError::StoreNotEmpty);
+    }
+    let expected_account_root = read_h256(r)?;
+    let account_count = read_u32(r)?;
+    let expected_block_root = read_h256(r)?;
+
+    let state_db = StateDBTransaction::from_version(db, StateDBVersion::from_genesis())
+        .map_err(store_error)?;
+    let mut tree = state_db.account_state_tree().map_err(store_error)?;
+    // the count is untrusted, the leaves grow with the entries actually read
+    let mut leaves = Vec::new();
+    for _ in 0..read_section_header(r, SECTION_LEAVES)? {
+        let key = read_h256(r)?;
+        let value = read_h256(r)?;
+        leaves.push((key, value));
+    }
+    tree.update_raws(leaves)?;
+    tree.set_account_count(account_count)?;
+    for _ in 0..read_section_header(r, SECTION_SCRIPTS)? {
+        let script_hash = read_h256(r)?;
+        let script =
+            Script::from_slice(&read_bytes(r)?).map_err(|_| SnapshotError::InvalidScript)?;
+        tree.insert_script(script_hash, script);
+    }
+    for _ in 0..read_section_header(r, SECTION_CODES)? {
+        let data_hash = read_h256(r)?;
+        let data = read_bytes(r)?;
+        tree.insert_data(data_hash, data.into());
+    }
+    let account_root = tree.calculate_root()?;
+    if account_root != expected_account_root {
+        return Err(SnapshotError::RootMismatch {
+            expected: expected_account_root,
+            actual: account_root,
+        });
+    }
+    tree.submit_tree().map_err(store_error)?;
+
+    let mut block_hashes = Vec::new();
+    for _ in 0..read_section_header(r, SECTION_BLOCKS)? {
+        block_hashes.push(read_h256(r)?);
+    }
+    db.restore_main_chain(&block_hashes).map_err(store_error)?;
+    let block_root = db.get_block_smt_root().map_err(store_error)?;
+    if block_root != expected_block_root {
+        return Err(SnapshotError::RootMismatch {
+            expected: expected_block_root,
+            actual: block_root,
+        });
+    }
+    Ok(())
+}
+
+fn store_error<E: Display>(err: E) -> SnapshotError {
+    SnapshotError::Store(err.to_string())
+}
+
+pub(crate) fn write_section_header<W: Write>(
+    w: &mut W,
+    tag: u8,
+    len: usize,
+) -> Result<(), SnapshotError> {
+    w.write_all(&[tag])?;
+    w.write_all(&(len as u64).to_le_bytes())?;
+    Ok(())
+}
+
+pub(crate) fn write_bytes<W: Write>(w: &mut W, data: &[u8]) -> Result<(), SnapshotError> {
+    w.write_all(&(data.len() as u32).to_le_bytes())?;
+    w.write_all(data)?;
+    Ok(())
+}
+
+pub(crate) fn read_section_header<R: Read>(r: &mut R, expected: u8) -> Result<u64, SnapshotError> {
+    let mut tag = [0u8; 1];
+    r.read_exact(&mut tag)?;
+    if tag[0] != expected {
+        return Err(SnapshotError::UnexpectedSection {
+            expected,
+            actual: tag[0],
+        });
+    }
+    let mut buf = [0u8; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+pub(crate) fn read_u32<R: Read>(r: &mut R) -> Result<u32, SnapshotError> {
+    let mut buf = [0u8; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+pub(crate) fn read_h256<R: Read>(r: &mut R) -> Result<H256, SnapshotError> {
+    let mut buf = [0u8; 32];
+    r.read_exact(&mut buf)?;
+    Ok(buf.into())
+}
+
+/// The length prefix is untrusted, the buffer grows with the data actually read
+pub(crate) fn read_bytes<R: Read>(r: &mut R) -> Result<Vec<u8>, SnapshotError> {
+    let len = read_u32(r)?;
+    let mut buf = Vec::new();
+    r.take(len.into()).read_to_end(&mut buf)?;
+    if buf.len() != len as usize {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(buf)
+}
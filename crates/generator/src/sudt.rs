@@ -1,22 +1,84 @@
-use gw_common::H256;
-use gw_types::{bytes::Bytes, core::ScriptHashType, packed::Script, prelude::*};
+use gw_common::{error::Error, state::State, H256};
+use gw_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    packed::{RollupConfig, Script},
+    prelude::*,
+};
 
 use crate::RollupContext;
 
 pub fn build_l2_sudt_script(rollup_context: &RollupContext, l1_sudt_script_hash: &H256) -> Script {
+    build_l2_sudt_script_from_config(
+        rollup_context.rollup_script_hash(),
+        rollup_context.rollup_config(),
+        l1_sudt_script_hash,
+    )
+}
+
+/// Same as `build_l2_sudt_script`, for the genesis which doesn't build a `RollupContext`
+pub(crate) fn build_l2_sudt_script_from_config(
+    rollup_script_hash: &H256,
+    rollup_config: &RollupConfig,
+    l1_sudt_script_hash: &H256,
+) -> Script {
     let args = {
         let mut args = Vec::with_capacity(64);
-        args.extend(rollup_context.rollup_script_hash.as_slice());
+        args.extend(rollup_script_hash.as_slice());
         args.extend(l1_sudt_script_hash.as_slice());
         Bytes::from(args)
     };
     Script::new_builder()
         .args(args.pack())
-        .code_hash(
-            rollup_context
-                .rollup_config
-                .l2_sudt_validator_script_type_hash(),
-        )
+        .code_hash(rollup_config.l2_sudt_validator_script_type_hash())
         .hash_type(ScriptHashType::Type.into())
         .build()
 }
+
+/// Resolve the layer2 sUDT account id of a layer1 sUDT script hash,
+/// returns None if the account of the token isn't created yet
+pub fn sudt_account_id<S: State>(
+    rollup_context: &RollupContext,
+    state: &S,
+    l1_sudt_script_hash: &H256,
+) -> Result<Option<u32>, Error> {
+    let l2_sudt_script_hash = build_l2_sudt_script(rollup_context, l1_sudt_script_hash).hash();
+    state.get_account_id_by_script_hash(&l2_sudt_script_hash.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dummy_state::DummyState, traits::StateExt};
+    use gw_common::{builtins::CKB_SUDT_ACCOUNT_ID, CKB_SUDT_SCRIPT_ARGS};
+    use gw_types::packed::RollupConfig;
+
+    #[test]
+    fn test_sudt_account_id() {
+        let ctx = RollupContext::new_unchecked(
+            [42u8; 32].into(),
+            RollupConfig::new_builder()
+                .l2_sudt_validator_script_type_hash([7u8; 32].pack())
+                .build(),
+        );
+        let mut state = DummyState::default();
+        state.create_account(H256::zero()).unwrap();
+        let ckb_sudt_script = build_l2_sudt_script(&ctx, &CKB_SUDT_SCRIPT_ARGS.into());
+        state.create_account_from_script(ckb_sudt_script).unwrap();
+        let l1_sudt_script_hash = H256::from([1u8; 32]);
+        let sudt_id = state
+            .create_account_from_script(build_l2_sudt_script(&ctx, &l1_sudt_script_hash))
+            .unwrap();
+
+        assert_eq!(
+            sudt_account_id(&ctx, &state, &CKB_SUDT_SCRIPT_ARGS.into()),
+            Ok(Some(CKB_SUDT_ACCOUNT_ID))
+        );
+        assert_eq!(
+            sudt_account_id(&ctx, &state, &l1_sudt_script_hash),
+            Ok(Some(sudt_id))
+        );
+        // unregistered token
+        assert_eq!(sudt_account_id(&ctx, &state, &[2u8; 32].into()), Ok(None));
+    }
+}
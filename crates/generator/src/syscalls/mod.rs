@@ -7,10 +7,7 @@ use ckb_vm::{
 use gw_common::{
     blake2b::new_blake2b,
     h256_ext::H256Ext,
-    state::{
-        build_account_field_key, build_script_hash_to_account_id_key, State, GW_ACCOUNT_NONCE,
-        GW_ACCOUNT_SCRIPT_HASH,
-    },
+    state::{build_account_field_key, build_script_hash_to_account_id_key, AccountField, State},
     H256,
 };
 use gw_traits::{ChainStore, CodeStore};
@@ -175,14 +172,14 @@ impl<'a, S: State, C: ChainStore, Mac: SupportMachine> Syscalls<Mac> for L2Sysca
                 }
                 let is_eoa_account = self
                     .rollup_context
-                    .rollup_config
+                    .rollup_config()
                     .allowed_eoa_type_hashes()
                     .into_iter()
                     .any(|type_hash| type_hash == script.code_hash());
                 if !is_eoa_account {
                     let is_contract_account = self
                         .rollup_context
-                        .rollup_config
+                        .rollup_config()
                         .allowed_contract_type_hashes()
                         .into_iter()
                         .any(|type_hash| type_hash == script.code_hash());
@@ -194,7 +191,7 @@ impl<'a, S: State, C: ChainStore, Mac: SupportMachine> Syscalls<Mac> for L2Sysca
                     // check contract script prefix
                     let args: Bytes = script.args().unpack();
                     if args.len() < 32
-                        || !args.starts_with(self.rollup_context.rollup_script_hash.as_slice())
+                        || !args.starts_with(self.rollup_context.rollup_script_hash().as_slice())
                     {
                         machine.set_register(A0, Mac::REG::from_u8(ERROR_INVALID_CONTRACT_SCRIPT));
                         return Ok(true);
@@ -203,11 +200,12 @@ impl<'a, S: State, C: ChainStore, Mac: SupportMachine> Syscalls<Mac> for L2Sysca
 
                 // Same logic from State::create_account()
                 let id = self.get_account_count()?;
-                self.result
-                    .write_values
-                    .insert(build_account_field_key(id, GW_ACCOUNT_NONCE), H256::zero());
                 self.result.write_values.insert(
-                    build_account_field_key(id, GW_ACCOUNT_SCRIPT_HASH),
+                    build_account_field_key(id, AccountField::Nonce),
+                    H256::zero(),
+                );
+                self.result.write_values.insert(
+                    build_account_field_key(id, AccountField::ScriptHash),
                     script_hash.into(),
                 );
                 // script hash to id
@@ -357,7 +355,7 @@ impl<'a, S: State, C: ChainStore, Mac: SupportMachine> Syscalls<Mac> for L2Sysca
                 Ok(true)
             }
             SYS_LOAD_ROLLUP_CONFIG => {
-                let data = self.rollup_context.rollup_config.as_slice();
+                let data = self.rollup_context.rollup_config().as_slice();
                 store_data(machine, data)?;
                 machine.set_register(A0, Mac::REG::from_u8(SUCCESS));
                 Ok(true)
@@ -412,7 +410,7 @@ impl<'a, S: State, C: ChainStore> L2Syscalls<'a, S, C> {
     }
     fn get_script_hash(&mut self, id: u32) -> Result<H256, VMError> {
         let value = self
-            .get_raw(&build_account_field_key(id, GW_ACCOUNT_SCRIPT_HASH))
+            .get_raw(&build_account_field_key(id, AccountField::ScriptHash))
             .map_err(|err| {
                 log::error!("syscall error: get script hash by account id : {:?}", err);
                 VMError::Unexpected
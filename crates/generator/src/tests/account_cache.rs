@@ -0,0 +1,64 @@
+use crate::{account_cache::AccountCache, dummy_state::DummyState, traits::StateExt};
+use gw_common::{
+    state::{build_account_field_key, AccountField, State},
+    H256,
+};
+use gw_traits::CodeStore;
+use gw_types::{
+    bytes::Bytes, core::ScriptHashType, offchain::RunResult, packed::Script, prelude::*,
+};
+
+fn build_script(args: u8) -> Script {
+    Script::new_builder()
+        .hash_type(ScriptHashType::Type.into())
+        .args(Bytes::from(vec![args; 32]).pack())
+        .build()
+}
+
+#[test]
+fn test_refresh_mutated_account() {
+    let mut state = DummyState::default();
+    let cache = AccountCache::new();
+    let script = build_script(1);
+    let id = state.create_account_from_script(script.clone()).unwrap();
+
+    assert_eq!(
+        cache.cached_script_hash(&state, id).unwrap(),
+        script.hash().into()
+    );
+    assert_eq!(cache.cached_script(&state, id).unwrap(), Some(script));
+    // a missing account isn't cached
+    assert_eq!(
+        cache.cached_script_hash(&state, id + 1).unwrap(),
+        H256::zero()
+    );
+    assert_eq!(cache.cached_script(&state, id + 1).unwrap(), None);
+
+    // replace the account with a new script
+    state.remove_last_account().unwrap();
+    let new_script = build_script(2);
+    assert_eq!(
+        state
+            .create_account_from_script(new_script.clone())
+            .unwrap(),
+        id
+    );
+    cache.invalidate(id);
+    assert_eq!(
+        cache.cached_script_hash(&state, id).unwrap(),
+        new_script.hash().into()
+    );
+    assert_eq!(cache.cached_script(&state, id).unwrap(), Some(new_script));
+
+    // the script hash is rewritten by a transaction
+    let other_script = build_script(3);
+    state.insert_script(other_script.hash().into(), other_script.clone());
+    let mut run_result = RunResult::default();
+    run_result.write_values.insert(
+        build_account_field_key(id, AccountField::ScriptHash),
+        other_script.hash().into(),
+    );
+    state.apply_run_result(&run_result).unwrap();
+    cache.invalidate_by_run_result(&run_result);
+    assert_eq!(cache.cached_script(&state, id).unwrap(), Some(other_script));
+}
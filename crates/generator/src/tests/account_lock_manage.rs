@@ -0,0 +1,331 @@
+use crate::{
+    account_lock_manage::{
+        always_success::AlwaysSuccess, verify_l2_transaction, verify_tx_nonce, AccountLockManage,
+        LockAlgorithm,
+    },
+    backend_manage::BackendManage,
+    dummy_state::DummyState,
+    error::{Error, LockAlgorithmError, TransactionError, TransactionValidateError},
+    traits::StateExt,
+    Generator, RollupContext,
+};
+use gw_common::{state::State, H256};
+use gw_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    packed::{
+        L2Transaction, RawL2Transaction, RawWithdrawalRequest, RollupConfig, Script, Signature,
+        WithdrawalRequest,
+    },
+    prelude::*,
+};
+
+const ALLOWED_LOCK_CODE_HASH: [u8; 32] = [1u8; 32];
+const DISALLOWED_LOCK_CODE_HASH: [u8; 32] = [2u8; 32];
+const NO_WITHDRAWAL_LOCK_CODE_HASH: [u8; 32] = [3u8; 32];
+const ARGS_MATCH_LOCK_CODE_HASH: [u8; 32] = [4u8; 32];
+
+/// A lock which signs transactions but not withdrawals
+struct NoWithdrawal;
+
+impl LockAlgorithm for NoWithdrawal {
+    fn supports_withdrawal(&self) -> bool {
+        false
+    }
+
+    fn verify_withdrawal_signature(
+        &self,
+        _lock_args: Bytes,
+        _signature: Signature,
+        _message: H256,
+    ) -> Result<bool, LockAlgorithmError> {
+        unreachable!("withdrawal is not supported")
+    }
+
+    fn verify_message_digest(
+        &self,
+        _lock_args: Bytes,
+        _signature: Signature,
+        _digest: H256,
+    ) -> Result<bool, LockAlgorithmError> {
+        Ok(true)
+    }
+
+    fn verify_tx(
+        &self,
+        _ctx: &RollupContext,
+        _sender_script: Script,
+        _receiver_script: Script,
+        _tx: L2Transaction,
+    ) -> Result<bool, LockAlgorithmError> {
+        Ok(true)
+    }
+}
+
+/// A lock which accepts a tx if the signature equals to the sender's lock args
+struct ArgsMatch;
+
+impl LockAlgorithm for ArgsMatch {
+    fn verify_withdrawal_signature(
+        &self,
+        _lock_args: Bytes,
+        _signature: Signature,
+        _message: H256,
+    ) -> Result<bool, LockAlgorithmError> {
+        Ok(false)
+    }
+
+    fn verify_message_digest(
+        &self,
+        _lock_args: Bytes,
+        _signature: Signature,
+        _digest: H256,
+    ) -> Result<bool, LockAlgorithmError> {
+        Ok(false)
+    }
+
+    fn verify_tx(
+        &self,
+        _ctx: &RollupContext,
+        sender_script: Script,
+        receiver_script: Script,
+        tx: L2Transaction,
+    ) -> Result<bool, LockAlgorithmError> {
+        let to_id: u32 = tx.raw().to_id().unpack();
+        assert_eq!(receiver_script.args().raw_data(), vec![to_id as u8; 32]);
+        Ok(tx.signature().raw_data() == sender_script.args().raw_data())
+    }
+}
+
+fn build_generator() -> Generator {
+    let mut account_lock_manage = AccountLockManage::default();
+    for code_hash in &[ALLOWED_LOCK_CODE_HASH, DISALLOWED_LOCK_CODE_HASH] {
+        account_lock_manage
+            .register_lock_algorithm((*code_hash).into(), Box::new(AlwaysSuccess::default()));
+    }
+    account_lock_manage
+        .register_lock_algorithm(NO_WITHDRAWAL_LOCK_CODE_HASH.into(), Box::new(NoWithdrawal));
+    let rollup_context = RollupContext::new_unchecked(
+        Default::default(),
+        RollupConfig::new_builder()
+            .allowed_eoa_type_hashes(
+                vec![
+                    ALLOWED_LOCK_CODE_HASH,
+                    NO_WITHDRAWAL_LOCK_CODE_HASH,
+                    ARGS_MATCH_LOCK_CODE_HASH,
+                ]
+                .pack(),
+            )
+            .build(),
+    );
+    Generator::new(
+        BackendManage::from_config(Vec::new()).expect("backend manage"),
+        account_lock_manage,
+        rollup_context,
+    )
+}
+
+fn build_account_script(code_hash: [u8; 32], args: u8) -> Script {
+    Script::new_builder()
+        .code_hash(code_hash.pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args(Bytes::from(vec![args; 32]).pack())
+        .build()
+}
+
+#[test]
+fn test_is_allowed_lock() {
+    let generator = build_generator();
+    let ctx = generator.rollup_context();
+    let manage = generator.account_lock_manage();
+    assert!(manage.is_allowed_lock(ctx, &ALLOWED_LOCK_CODE_HASH.into()));
+    assert!(!manage.is_allowed_lock(ctx, &DISALLOWED_LOCK_CODE_HASH.into()));
+    assert!(!manage.is_allowed_lock(ctx, &H256::zero()));
+}
+
+#[test]
+fn test_reject_tx_from_disallowed_lock() {
+    let generator = build_generator();
+    let mut state = DummyState::default();
+    let allowed_id = state
+        .create_account_from_script(build_account_script(ALLOWED_LOCK_CODE_HASH, 1))
+        .expect("create account");
+    let disallowed_id = state
+        .create_account_from_script(build_account_script(DISALLOWED_LOCK_CODE_HASH, 2))
+        .expect("create account");
+    let build_tx = |from_id: u32, to_id: u32| {
+        let raw = RawL2Transaction::new_builder()
+            .from_id(from_id.pack())
+            .to_id(to_id.pack())
+            .build();
+        L2Transaction::new_builder().raw(raw).build()
+    };
+
+    let tx = build_tx(allowed_id, disallowed_id);
+    assert_eq!(generator.check_transaction_signature(&state, &tx), Ok(()));
+    let tx = build_tx(disallowed_id, allowed_id);
+    assert_eq!(
+        generator.check_transaction_signature(&state, &tx),
+        Err(TransactionValidateError::Unlock(
+            LockAlgorithmError::DisallowedLock
+        ))
+    );
+}
+
+#[test]
+fn test_reject_withdrawal_of_unsupported_lock() {
+    let generator = build_generator();
+    let mut state = DummyState::default();
+    let build_withdrawal = |script: &Script| {
+        let raw = RawWithdrawalRequest::new_builder()
+            .account_script_hash(script.hash().pack())
+            .build();
+        WithdrawalRequest::new_builder().raw(raw).build()
+    };
+
+    let script = build_account_script(ALLOWED_LOCK_CODE_HASH, 1);
+    state
+        .create_account_from_script(script.clone())
+        .expect("create account");
+    let withdrawal = build_withdrawal(&script);
+    assert_eq!(
+        generator.check_withdrawal_request_signature(&state, &withdrawal),
+        Ok(())
+    );
+
+    let script = build_account_script(NO_WITHDRAWAL_LOCK_CODE_HASH, 2);
+    state
+        .create_account_from_script(script.clone())
+        .expect("create account");
+    let withdrawal = build_withdrawal(&script);
+    assert_eq!(
+        generator.check_withdrawal_request_signature(&state, &withdrawal),
+        Err(Error::Unlock(LockAlgorithmError::WithdrawalNotSupported))
+    );
+}
+
+#[test]
+fn test_verify_l2_transaction() {
+    let generator = build_generator();
+    let ctx = generator.rollup_context();
+    let mut manage = AccountLockManage::default();
+    manage.register_lock_algorithm(ARGS_MATCH_LOCK_CODE_HASH.into(), Box::new(ArgsMatch));
+
+    // the account id is used as script args to make each script unique
+    let mut state = DummyState::default();
+    let sender_id = state
+        .create_account_from_script(build_account_script(ARGS_MATCH_LOCK_CODE_HASH, 0))
+        .expect("create account");
+    let receiver_id = state
+        .create_account_from_script(build_account_script(ALLOWED_LOCK_CODE_HASH, 1))
+        .expect("create account");
+    let build_tx = |from_id: u32, to_id: u32, signature: Vec<u8>| {
+        let raw = RawL2Transaction::new_builder()
+            .from_id(from_id.pack())
+            .to_id(to_id.pack())
+            .build();
+        L2Transaction::new_builder()
+            .raw(raw)
+            .signature(Bytes::from(signature).pack())
+            .build()
+    };
+
+    let tx = build_tx(sender_id, receiver_id, vec![sender_id as u8; 32]);
+    assert_eq!(verify_l2_transaction(&manage, ctx, &state, &tx), Ok(true));
+    let tx = build_tx(sender_id, receiver_id, vec![42; 32]);
+    assert_eq!(verify_l2_transaction(&manage, ctx, &state, &tx), Ok(false));
+
+    // the sender's lock isn't registered
+    let tx = build_tx(receiver_id, sender_id, vec![receiver_id as u8; 32]);
+    assert_eq!(
+        verify_l2_transaction(&manage, ctx, &state, &tx),
+        Err(LockAlgorithmError::UnknownLockCodeHash {
+            code_hash: ALLOWED_LOCK_CODE_HASH.into()
+        })
+    );
+
+    // the receiver doesn't exist
+    let tx = build_tx(sender_id, 42, vec![sender_id as u8; 32]);
+    assert_eq!(
+        verify_l2_transaction(&manage, ctx, &state, &tx),
+        Err(LockAlgorithmError::ScriptNotFound { account_id: 42 })
+    );
+}
+
+#[test]
+fn test_verify_tx_dispatch_by_lock_code_hash() {
+    let generator = build_generator();
+    let ctx = generator.rollup_context();
+    let mut manage = AccountLockManage::default();
+    manage.register_lock_algorithm(ARGS_MATCH_LOCK_CODE_HASH.into(), Box::new(ArgsMatch));
+    manage.register_lock_algorithm(NO_WITHDRAWAL_LOCK_CODE_HASH.into(), Box::new(NoWithdrawal));
+
+    let receiver_script = build_account_script(ALLOWED_LOCK_CODE_HASH, 1);
+    let raw = RawL2Transaction::new_builder().to_id(1u32.pack()).build();
+    // the signature doesn't match args of any sender
+    let tx = L2Transaction::new_builder()
+        .raw(raw)
+        .signature(Bytes::from(vec![42u8; 32]).pack())
+        .build();
+
+    // ArgsMatch rejects the signature
+    let sender_script = build_account_script(ARGS_MATCH_LOCK_CODE_HASH, 0);
+    assert_eq!(
+        manage.verify_tx(ctx, sender_script, receiver_script.clone(), tx.clone()),
+        Ok(false)
+    );
+    // NoWithdrawal accepts any tx
+    let sender_script = build_account_script(NO_WITHDRAWAL_LOCK_CODE_HASH, 0);
+    assert_eq!(
+        manage.verify_tx(ctx, sender_script, receiver_script.clone(), tx.clone()),
+        Ok(true)
+    );
+    // the sender's lock isn't registered
+    let sender_script = build_account_script(ALLOWED_LOCK_CODE_HASH, 0);
+    assert_eq!(
+        manage.verify_tx(ctx, sender_script, receiver_script.clone(), tx.clone()),
+        Err(LockAlgorithmError::UnknownLockCodeHash {
+            code_hash: ALLOWED_LOCK_CODE_HASH.into()
+        })
+    );
+    // the sender's lock isn't allowed by the rollup config
+    manage.register_lock_algorithm(DISALLOWED_LOCK_CODE_HASH.into(), Box::new(NoWithdrawal));
+    let sender_script = build_account_script(DISALLOWED_LOCK_CODE_HASH, 0);
+    assert_eq!(
+        manage.verify_tx(ctx, sender_script, receiver_script, tx),
+        Err(LockAlgorithmError::DisallowedLock)
+    );
+}
+
+#[test]
+fn test_verify_tx_nonce() {
+    let mut state = DummyState::default();
+    let sender_id = state
+        .create_account_from_script(build_account_script(ALLOWED_LOCK_CODE_HASH, 0))
+        .expect("create account");
+    state.set_nonce(sender_id, 2).expect("set nonce");
+    let build_raw_tx = |nonce: u32| {
+        RawL2Transaction::new_builder()
+            .from_id(sender_id.pack())
+            .nonce(nonce.pack())
+            .build()
+    };
+
+    assert_eq!(verify_tx_nonce(&state, &build_raw_tx(2)), Ok(()));
+    // a replayed tx
+    assert_eq!(
+        verify_tx_nonce(&state, &build_raw_tx(1)),
+        Err(TransactionError::NonceMismatch {
+            expected: 2,
+            got: 1
+        })
+    );
+    // a tx from the future
+    assert_eq!(
+        verify_tx_nonce(&state, &build_raw_tx(3)),
+        Err(TransactionError::NonceMismatch {
+            expected: 2,
+            got: 3
+        })
+    );
+}
@@ -0,0 +1,119 @@
+use crate::{dummy_state::DummyState, error::SnapshotError, traits::StateExt};
+use gw_common::{h256_ext::H256Ext, state::State, H256};
+use gw_traits::CodeStore;
+use gw_types::{bytes::Bytes, core::ScriptHashType, packed::Script, prelude::*};
+use std::{sync::Arc, thread};
+
+fn build_populated_state() -> DummyState {
+    let mut state = DummyState::default();
+    for i in 1..=3u8 {
+        let script = Script::new_builder()
+            .code_hash([i; 32].pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(vec![i; 20]).pack())
+            .build();
+        let id = state.create_account_from_script(script).unwrap();
+        state.set_nonce(id, i as u32).unwrap();
+    }
+    state.insert_data([9u8; 32].into(), Bytes::from(vec![9u8; 100]));
+    state
+}
+
+#[test]
+fn test_snapshot_round_trip() {
+    let state = build_populated_state();
+    let mut snapshot = Vec::new();
+    state.export_snapshot(&mut snapshot).unwrap();
+
+    let imported = DummyState::import_snapshot(&mut snapshot.as_slice()).unwrap();
+    assert_eq!(
+        imported.calculate_root().unwrap(),
+        state.calculate_root().unwrap()
+    );
+    assert_eq!(imported.get_account_count().unwrap(), 3);
+    for id in 0..3 {
+        let script_hash = imported.get_script_hash(id).unwrap();
+        assert_eq!(script_hash, state.get_script_hash(id).unwrap());
+        assert_eq!(imported.get_nonce(id).unwrap(), id + 1);
+        assert_eq!(
+            imported.get_script(&script_hash).map(|s| s.as_bytes()),
+            state.get_script(&script_hash).map(|s| s.as_bytes())
+        );
+    }
+    let data_hash: H256 = [9u8; 32].into();
+    assert_eq!(imported.get_data(&data_hash), state.get_data(&data_hash));
+
+    // the export is deterministic
+    let mut exported_again = Vec::new();
+    imported.export_snapshot(&mut exported_again).unwrap();
+    assert_eq!(exported_again, snapshot);
+}
+
+#[test]
+fn test_import_invalid_snapshot() {
+    let state = build_populated_state();
+    let mut snapshot = Vec::new();
+    state.export_snapshot(&mut snapshot).unwrap();
+
+    let mut unknown_version = snapshot.clone();
+    unknown_version[4] = 2;
+    assert!(matches!(
+        DummyState::import_snapshot(&mut unknown_version.as_slice()),
+        Err(SnapshotError::UnsupportedVersion(2))
+    ));
+
+    // tamper the exported root
+    let mut wrong_root = snapshot.clone();
+    wrong_root[8] ^= 1;
+    assert!(matches!(
+        DummyState::import_snapshot(&mut wrong_root.as_slice()),
+        Err(SnapshotError::RootMismatch { .. })
+    ));
+
+    let truncated = &snapshot[..snapshot.len() - 1];
+    assert!(matches!(
+        DummyState::import_snapshot(&mut &truncated[..]),
+        Err(SnapshotError::IO(_))
+    ));
+
+    // the length of the last code claims more bytes than the snapshot has
+    let mut oversized = snapshot.clone();
+    let len_offset = oversized.len() - 100 - 4;
+    oversized[len_offset..len_offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(matches!(
+        DummyState::import_snapshot(&mut oversized.as_slice()),
+        Err(SnapshotError::IO(_))
+    ));
+}
+
+#[test]
+fn test_concurrent_reads() {
+    let mut state = build_populated_state();
+    let keys: Vec<H256> = (0..100u32).map(H256::from_u32).collect();
+    for (i, key) in keys.iter().enumerate() {
+        state
+            .update_raw(*key, H256::from_u32(i as u32 + 1))
+            .unwrap();
+    }
+    let root = state.calculate_root().unwrap();
+    let state = Arc::new(state);
+    let keys = Arc::new(keys);
+
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let state = Arc::clone(&state);
+            let keys = Arc::clone(&keys);
+            thread::spawn(move || {
+                for _ in 0..50 {
+                    for (i, key) in keys.iter().enumerate() {
+                        assert_eq!(state.get_raw(key).unwrap(), H256::from_u32(i as u32 + 1));
+                    }
+                    assert_eq!(state.calculate_root().unwrap(), root);
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().expect("reader thread");
+    }
+}
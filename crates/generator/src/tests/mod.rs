@@ -1 +1,8 @@
+mod account_cache;
+mod account_lock_manage;
+mod dummy_state;
 mod genesis;
+mod overlay;
+mod snapshot;
+mod state;
+mod state_ext;
@@ -0,0 +1,210 @@
+use crate::{dummy_state::DummyState, overlay::OverlayExt};
+use gw_common::{
+    builtins::CKB_SUDT_ACCOUNT_ID,
+    error::Error,
+    h256_ext::H256Ext,
+    state::{
+        build_account_field_key, build_account_key, build_script_hash_to_account_id_key,
+        AccountField, State,
+    },
+    H256,
+};
+use gw_traits::CodeStore;
+use gw_types::{bytes::Bytes, packed::Script, prelude::*};
+
+#[test]
+fn test_overlay_keeps_writes() {
+    let mut state = DummyState::default();
+    let id = state.create_account([1u8; 32].into()).unwrap();
+    let root = state.calculate_root().unwrap();
+
+    let mut overlay = state.new_overlay().unwrap();
+    assert_eq!(overlay.calculate_root(), Ok(root));
+    overlay.set_nonce(id, 1).unwrap();
+    overlay.create_account([2u8; 32].into()).unwrap();
+    assert_eq!(overlay.get_nonce(id), Ok(1));
+    assert_eq!(overlay.get_account_count(), Ok(2));
+    assert_eq!(overlay.calculate_root(), Err(Error::UnsupportedByOverlay));
+    let diff = overlay.into_diff();
+    assert_eq!(diff.account_count, 2);
+
+    // the base state is untouched
+    assert_eq!(state.get_nonce(id), Ok(0));
+    assert_eq!(state.calculate_root(), Ok(root));
+
+    for (key, value) in diff.writes {
+        state.update_raw(key, value).unwrap();
+    }
+    assert_eq!(state.get_nonce(id), Ok(1));
+}
+
+#[test]
+fn test_overlay_commit_into() {
+    let build_state = || {
+        let mut state = DummyState::default();
+        let sudt_id = state.create_account([1u8; 32].into()).unwrap();
+        let id = state.create_account([2u8; 32].into()).unwrap();
+        state.mint_sudt(sudt_id, id, 100).unwrap();
+        (state, sudt_id, id)
+    };
+    let script = Script::new_builder()
+        .args(Bytes::from(vec![3u8; 32]).pack())
+        .build();
+    let script_hash: H256 = script.hash().into();
+    let data = Bytes::from(vec![4u8; 8]);
+    let data_hash = H256::from([4u8; 32]);
+    let (mut base, sudt_id, id) = build_state();
+    let (mut expected, _, _) = build_state();
+
+    let diff = {
+        let mut overlay = base.new_overlay().unwrap();
+        overlay.set_nonce(id, 1).unwrap();
+        overlay.burn_sudt(sudt_id, id, 10).unwrap();
+        overlay
+            .update_value(id, &H256::from([4u8; 32]), [5u8; 32].into())
+            .unwrap();
+        overlay.insert_script(script_hash, script.clone());
+        overlay.create_account(script_hash).unwrap();
+        overlay.store_data_hash(data_hash).unwrap();
+        overlay.insert_data(data_hash, data.clone());
+        // scripts and data are read through the overlay
+        assert!(overlay.get_script(&script_hash).is_some());
+        assert!(base.get_script(&script_hash).is_none());
+        assert_eq!(overlay.get_data(&data_hash), Some(data.clone()));
+        overlay.into_diff()
+    };
+    // the borrow of the base ended, commit the diff into it
+    diff.commit_into(&mut base).unwrap();
+
+    expected.set_nonce(id, 1).unwrap();
+    expected.burn_sudt(sudt_id, id, 10).unwrap();
+    expected
+        .update_value(id, &H256::from([4u8; 32]), [5u8; 32].into())
+        .unwrap();
+    expected.create_account(script_hash).unwrap();
+    expected.store_data_hash(data_hash).unwrap();
+
+    assert_eq!(base.calculate_root(), expected.calculate_root());
+    assert_eq!(base.get_account_count(), Ok(3));
+    assert_eq!(base.get_sudt_balance(sudt_id, id), Ok(90));
+    assert_eq!(
+        base.get_script(&script_hash).map(|s| s.as_bytes()),
+        Some(script.as_bytes())
+    );
+    assert_eq!(base.get_data(&data_hash), Some(data));
+}
+
+#[test]
+fn test_overlay_for_accounts() {
+    let mut state = DummyState::default();
+    let sudt_id = state.create_account([1u8; 32].into()).unwrap();
+    let a = state.create_account([2u8; 32].into()).unwrap();
+    let b = state.create_account([3u8; 32].into()).unwrap();
+    state.mint_sudt(sudt_id, a, 100).unwrap();
+    state.mint_sudt(sudt_id, b, 100).unwrap();
+
+    let mut overlay = state.new_overlay_for_accounts(&[a]).unwrap();
+    overlay.set_nonce(a, 1).unwrap();
+    overlay.burn_sudt(sudt_id, a, 10).unwrap();
+    overlay
+        .update_value(a, &H256::from([4u8; 32]), [5u8; 32].into())
+        .unwrap();
+    assert_eq!(overlay.get_sudt_balance(sudt_id, a), Ok(90));
+    assert_eq!(overlay.get_script_hash(a), Ok([2u8; 32].into()));
+
+    // writes to an unlisted account are rejected
+    assert_eq!(overlay.set_nonce(b, 1), Err(Error::KeyOutOfOverlay));
+    assert_eq!(
+        overlay.mint_sudt(sudt_id, b, 10),
+        Err(Error::KeyOutOfOverlay)
+    );
+    assert_eq!(
+        overlay.update_value(b, &H256::from([4u8; 32]), [5u8; 32].into()),
+        Err(Error::KeyOutOfOverlay)
+    );
+    assert_eq!(
+        overlay.create_account([6u8; 32].into()),
+        Err(Error::KeyOutOfOverlay)
+    );
+    // so are reads
+    assert_eq!(overlay.get_nonce(b), Err(Error::KeyOutOfOverlay));
+    assert_eq!(overlay.writes().len(), 3);
+
+    // raw keys used by syscalls
+    let nonce_key = build_account_field_key(a, AccountField::Nonce);
+    assert_eq!(overlay.get_raw(&nonce_key), Ok(H256::from_u32(1)));
+    let kv_key = build_account_key(a, &[4u8; 32]);
+    assert_eq!(overlay.get_raw(&kv_key), Ok([5u8; 32].into()));
+    let balance_key = build_account_field_key(a, AccountField::Sudt(sudt_id));
+    assert_eq!(overlay.get_raw(&balance_key), Ok(H256::from_u128(90)));
+    overlay.update_raw(kv_key, [6u8; 32].into()).unwrap();
+    assert_eq!(
+        overlay.get_value(a, &[4u8; 32].into()),
+        Ok([6u8; 32].into())
+    );
+    // the raw keys of another account are rejected, hashed or not
+    assert_eq!(
+        overlay.update_raw(build_account_key(b, &[4u8; 32]), [6u8; 32].into()),
+        Err(Error::KeyOutOfOverlay)
+    );
+    assert_eq!(
+        overlay.get_raw(&build_account_field_key(b, AccountField::Sudt(sudt_id))),
+        Err(Error::KeyOutOfOverlay)
+    );
+    let script_hash_key = build_account_field_key(b, AccountField::ScriptHash);
+    assert_eq!(
+        overlay.get_raw(&script_hash_key),
+        Err(Error::KeyOutOfOverlay)
+    );
+    assert_eq!(
+        overlay.update_raw(
+            build_account_field_key(b, AccountField::Nonce),
+            H256::from_u32(1)
+        ),
+        Err(Error::KeyOutOfOverlay)
+    );
+}
+
+#[test]
+fn test_overlay_attributes_hashed_keys() {
+    let mut state = DummyState::default();
+    state.create_account([9u8; 32].into()).unwrap();
+    let ckb_id = state.create_account([1u8; 32].into()).unwrap();
+    assert_eq!(ckb_id, CKB_SUDT_ACCOUNT_ID);
+    let sudt_id = state.create_account([2u8; 32].into()).unwrap();
+    let a = state.create_account([3u8; 32].into()).unwrap();
+    let b = state.create_account([4u8; 32].into()).unwrap();
+    state.mint_sudt(ckb_id, a, 100).unwrap();
+    state.mint_sudt(sudt_id, a, 100).unwrap();
+
+    let mut overlay = state.new_overlay_for_accounts(&[a]).unwrap();
+    // CKB balances and the script hash to id keys are attributed on creation
+    let ckb_key = build_account_field_key(a, AccountField::Sudt(ckb_id));
+    assert_eq!(overlay.get_raw(&ckb_key), Ok(H256::from_u128(100)));
+    let id_key = build_script_hash_to_account_id_key(&[3u8; 32]);
+    assert_eq!(overlay.get_raw(&id_key), Ok(H256::from_u32(a)));
+    assert_eq!(
+        overlay.get_raw(&build_script_hash_to_account_id_key(&[4u8; 32])),
+        Err(Error::KeyOutOfOverlay)
+    );
+
+    // other hashed keys are rejected until they're attributed
+    let sudt_key = build_account_field_key(a, AccountField::Sudt(sudt_id));
+    assert_eq!(overlay.get_raw(&sudt_key), Err(Error::KeyOutOfOverlay));
+    overlay.allow_sudt(sudt_id);
+    assert_eq!(overlay.get_raw(&sudt_key), Ok(H256::from_u128(100)));
+
+    let storage_key = H256::from([7u8; 32]);
+    let raw_key = build_account_key(a, storage_key.as_slice());
+    assert_eq!(
+        overlay.update_raw(raw_key, [8u8; 32].into()),
+        Err(Error::KeyOutOfOverlay)
+    );
+    overlay.allow_account_kv(a, &storage_key).unwrap();
+    overlay.update_raw(raw_key, [8u8; 32].into()).unwrap();
+    assert_eq!(overlay.get_value(a, &storage_key), Ok([8u8; 32].into()));
+    assert_eq!(
+        overlay.allow_account_kv(b, &storage_key),
+        Err(Error::KeyOutOfOverlay)
+    );
+}
@@ -0,0 +1,138 @@
+use crate::{
+    error::SnapshotError,
+    genesis::init_genesis,
+    snapshot::{export_store_snapshot, import_store_snapshot},
+    traits::StateExt,
+};
+use gw_common::{smt::SMT, state::State, H256};
+use gw_config::GenesisConfig;
+use gw_store::{
+    state_db::{StateDBTransaction, StateDBVersion, StateTree},
+    transaction::StoreTransaction,
+    Store,
+};
+use gw_traits::CodeStore;
+use gw_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    packed::{L2BlockCommittedInfo, RollupConfig, Script},
+    prelude::*,
+};
+
+fn build_store() -> Store {
+    let config = GenesisConfig {
+        timestamp: 42,
+        meta_contract_validator_type_hash: [1u8; 32].into(),
+        rollup_config: RollupConfig::default().into(),
+        rollup_type_hash: [42u8; 32].into(),
+        secp_data_dep: Default::default(),
+    };
+    let store = Store::open_tmp().unwrap();
+    init_genesis(
+        &store,
+        &config,
+        L2BlockCommittedInfo::default(),
+        Bytes::default(),
+    )
+    .unwrap();
+    let db = store.begin_transaction();
+    {
+        let state_db =
+            StateDBTransaction::from_version(&db, StateDBVersion::from_future_state(1, 0)).unwrap();
+        let mut tree = state_db.account_state_tree().unwrap();
+        let script = Script::new_builder()
+            .code_hash([3u8; 32].pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(vec![3u8; 20]).pack())
+            .build();
+        let id = tree.create_account_from_script(script).unwrap();
+        tree.set_nonce(id, 7).unwrap();
+        tree.insert_data([9u8; 32].into(), Bytes::from(vec![9u8; 100]));
+        tree.submit_tree().unwrap();
+    }
+    db.commit().unwrap();
+    store
+}
+
+fn latest_state_db(db: &StoreTransaction) -> StateDBTransaction<'_> {
+    StateDBTransaction::from_version(db, StateDBVersion::from_future_state(u64::MAX, u32::MAX))
+        .unwrap()
+}
+
+// a restored store has no block bodies, so read the submitted root rather than a history version
+fn submitted_tree<'a, 'db>(
+    db: &StoreTransaction,
+    state_db: &'a StateDBTransaction<'db>,
+) -> StateTree<'a, 'db> {
+    let root = db.get_account_smt_root().unwrap();
+    let count = db.get_account_count().unwrap();
+    let smt = SMT::new(root, state_db.account_smt_store().unwrap());
+    StateTree::new(state_db, smt, count)
+}
+
+#[test]
+fn test_store_snapshot_round_trip() {
+    let store = build_store();
+    let db = store.begin_transaction();
+    let mut snapshot = Vec::new();
+    export_store_snapshot(&db, &mut snapshot).unwrap();
+
+    let restored = Store::open_tmp().unwrap();
+    let restored_db = restored.begin_transaction();
+    import_store_snapshot(&restored_db, &mut snapshot.as_slice()).unwrap();
+    restored_db.commit().unwrap();
+
+    let restored_db = restored.begin_transaction();
+    assert_eq!(
+        restored_db.get_account_smt_root().unwrap(),
+        db.get_account_smt_root().unwrap()
+    );
+    assert_eq!(
+        restored_db.get_account_count().unwrap(),
+        db.get_account_count().unwrap()
+    );
+    assert_eq!(
+        restored_db.block_merkle_state().unwrap(),
+        db.block_merkle_state().unwrap()
+    );
+    assert_eq!(
+        restored_db.get_tip_block_hash().unwrap(),
+        db.get_tip_block_hash().unwrap()
+    );
+
+    let state_db = latest_state_db(&db);
+    let tree = submitted_tree(&db, &state_db);
+    let restored_state_db = latest_state_db(&restored_db);
+    let restored_tree = submitted_tree(&restored_db, &restored_state_db);
+    let account_count = tree.get_account_count().unwrap();
+    assert!(account_count > 2);
+    for id in 0..account_count {
+        let script_hash = tree.get_script_hash(id).unwrap();
+        assert_eq!(restored_tree.get_script_hash(id).unwrap(), script_hash);
+        assert_eq!(
+            restored_tree.get_nonce(id).unwrap(),
+            tree.get_nonce(id).unwrap()
+        );
+        assert_eq!(
+            restored_tree.get_script(&script_hash).map(|s| s.as_bytes()),
+            tree.get_script(&script_hash).map(|s| s.as_bytes())
+        );
+    }
+    assert_eq!(restored_tree.get_nonce(account_count - 1).unwrap(), 7);
+    let data_hash: H256 = [9u8; 32].into();
+    assert_eq!(
+        restored_tree.get_data(&data_hash),
+        Some(Bytes::from(vec![9u8; 100]))
+    );
+
+    // the export of the restored store is the same
+    let mut exported_again = Vec::new();
+    export_store_snapshot(&restored_db, &mut exported_again).unwrap();
+    assert_eq!(exported_again, snapshot);
+
+    // only a store without blocks can be restored into
+    assert!(matches!(
+        import_store_snapshot(&db, &mut snapshot.as_slice()),
+        Err(SnapshotError::StoreNotEmpty)
+    ));
+}
@@ -0,0 +1,84 @@
+use crate::dummy_state::DummyState;
+use gw_common::{
+    builtins::RESERVED_ACCOUNT_ID,
+    error::Error,
+    state::{build_account_field_key, AccountField, State},
+    H256,
+};
+
+#[test]
+fn test_remove_last_account() {
+    let mut state = DummyState::default();
+    assert_eq!(state.remove_last_account(), Err(Error::NoAccountToRemove));
+
+    let first_script_hash: H256 = [1u8; 32].into();
+    state.create_account(first_script_hash).unwrap();
+    let prev_root = state.calculate_root().unwrap();
+
+    let script_hash: H256 = [2u8; 32].into();
+    let id = state.create_account(script_hash).unwrap();
+    state.set_nonce(id, 5).unwrap();
+    assert_ne!(state.calculate_root().unwrap(), prev_root);
+
+    state.remove_last_account().unwrap();
+    assert_eq!(state.calculate_root().unwrap(), prev_root);
+    assert_eq!(state.get_account_count().unwrap(), 1);
+    assert_eq!(
+        state.get_account_id_by_script_hash(&script_hash).unwrap(),
+        None
+    );
+    assert_eq!(
+        state
+            .get_account_id_by_script_hash(&first_script_hash)
+            .unwrap(),
+        Some(0)
+    );
+}
+
+#[test]
+fn test_is_account_created() {
+    let mut state = DummyState::default();
+    let id = state.create_account([1u8; 32].into()).unwrap();
+    let other_id = state.create_account([2u8; 32].into()).unwrap();
+    assert!(state.is_account_created(id).unwrap());
+    assert!(state.is_account_created(other_id).unwrap());
+    // beyond account count
+    assert!(!state.is_account_created(other_id + 1).unwrap());
+    assert!(!state.is_account_created(u32::MAX).unwrap());
+
+    // a gap, the script hash leaf is cleared but the count is unchanged
+    state
+        .update_raw(
+            build_account_field_key(id, AccountField::ScriptHash),
+            H256::zero(),
+        )
+        .unwrap();
+    assert_eq!(state.get_account_count().unwrap(), 2);
+    assert!(!state.is_account_created(id).unwrap());
+    assert!(state.is_account_created(other_id).unwrap());
+}
+
+#[test]
+fn test_init_reserved_account() {
+    let mut state = DummyState::default();
+    let meta_script_hash: H256 = [1u8; 32].into();
+    state.init_reserved_account(meta_script_hash).unwrap();
+    assert_eq!(state.get_account_count().unwrap(), 1);
+    assert_eq!(
+        state.get_script_hash(RESERVED_ACCOUNT_ID).unwrap(),
+        meta_script_hash
+    );
+    assert_eq!(
+        state
+            .get_account_id_by_script_hash(&meta_script_hash)
+            .unwrap(),
+        Some(RESERVED_ACCOUNT_ID)
+    );
+
+    // can't initialize twice
+    assert_eq!(
+        state.init_reserved_account([2u8; 32].into()),
+        Err(Error::AccountAlreadyExists)
+    );
+    assert_eq!(state.get_account_count().unwrap(), 1);
+}
@@ -0,0 +1,250 @@
+use crate::{
+    dummy_state::DummyState,
+    error::{AccountError, Error, WithdrawalError},
+    sudt::build_l2_sudt_script,
+    traits::StateExt,
+    RollupContext,
+};
+use gw_common::{builtins::CKB_SUDT_ACCOUNT_ID, state::State, CKB_SUDT_SCRIPT_ARGS, H256};
+use gw_traits::{CodeStore, InMemoryCodeStore};
+use gw_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    offchain::RunResult,
+    packed::{DepositionRequest, RawWithdrawalRequest, RollupConfig, Script, WithdrawalRequest},
+    prelude::*,
+};
+
+#[test]
+fn test_get_account_meta() {
+    let mut state = DummyState::default();
+    let code_hash = [7u8; 32];
+    let script = Script::new_builder()
+        .code_hash(code_hash.pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args(Bytes::from(vec![1u8; 20]).pack())
+        .build();
+    let id = state
+        .create_account_from_script(script.clone())
+        .expect("create account");
+    state.set_nonce(id, 3).expect("set nonce");
+
+    let meta = state.get_account_meta(id).expect("get account meta");
+    assert_eq!(meta.script_hash, H256::from(script.hash()));
+    assert_eq!(meta.nonce, 3);
+    assert_eq!(meta.code_hash, H256::from(code_hash));
+
+    let err = state.get_account_meta(id + 1).unwrap_err();
+    assert_eq!(err, Error::Account(AccountError::UnknownAccount));
+}
+
+#[test]
+fn test_resolve_account_script() {
+    let mut state = DummyState::default();
+    let script = Script::new_builder()
+        .code_hash([7u8; 32].pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args(Bytes::from(vec![1u8; 20]).pack())
+        .build();
+    let id = state
+        .create_account_from_script(script.clone())
+        .expect("create account");
+
+    // the script is found
+    let resolved = state
+        .resolve_account_script(id, &state)
+        .expect("resolve script");
+    assert_eq!(resolved, Some(script));
+    let code_store = InMemoryCodeStore::default().with_script(script.clone());
+    let resolved = state
+        .resolve_account_script(id, &code_store)
+        .expect("resolve script");
+    assert_eq!(resolved, Some(script));
+    // the account exists, but the script is missing from the code store
+    let empty_code_store = InMemoryCodeStore::default();
+    let resolved = state
+        .resolve_account_script(id, &empty_code_store)
+        .expect("resolve script");
+    assert_eq!(resolved, None);
+    // the account doesn't exist
+    let err = state.resolve_account_script(id + 1, &state).unwrap_err();
+    assert_eq!(err, Error::Account(AccountError::UnknownAccount));
+}
+
+#[test]
+fn test_apply_run_result() {
+    let mut run_result = RunResult::default();
+    for i in 1u8..=3 {
+        run_result
+            .write_values
+            .insert([i; 32].into(), [i + 10; 32].into());
+    }
+    // reads must not be applied
+    run_result
+        .read_values
+        .insert([4u8; 32].into(), [14u8; 32].into());
+    let script = Script::new_builder()
+        .code_hash([7u8; 32].pack())
+        .hash_type(ScriptHashType::Type.into())
+        .build();
+    let script_hash: H256 = script.hash().into();
+    run_result
+        .new_scripts
+        .insert(script_hash, script.as_slice().to_vec());
+
+    let mut state = DummyState::default();
+    state
+        .apply_run_result(&run_result)
+        .expect("apply run result");
+
+    let mut expected_state = DummyState::default();
+    for (k, v) in &run_result.write_values {
+        expected_state.update_raw(*k, *v).expect("update");
+    }
+    assert_eq!(
+        state.calculate_root().unwrap(),
+        expected_state.calculate_root().unwrap()
+    );
+    assert_eq!(state.get_raw(&[4u8; 32].into()).unwrap(), H256::zero());
+    assert_eq!(state.get_script(&script_hash), Some(script));
+}
+
+#[test]
+fn test_apply_deposition_request() {
+    let mut state = DummyState::default();
+    let ctx = RollupContext::new_unchecked([42u8; 32].into(), RollupConfig::default());
+    // reserved accounts
+    state.create_account(H256::zero()).unwrap();
+    let ckb_sudt_script = build_l2_sudt_script(&ctx, &CKB_SUDT_SCRIPT_ARGS.into());
+    state
+        .create_account_from_script(ckb_sudt_script)
+        .expect("create CKB sUDT account");
+
+    let user_script = Script::new_builder()
+        .code_hash([3u8; 32].pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args(Bytes::from(vec![4u8; 20]).pack())
+        .build();
+    let sudt_script_hash = [5u8; 32];
+    let request = DepositionRequest::new_builder()
+        .script(user_script.clone())
+        .capacity(1000u64.pack())
+        .sudt_script_hash(sudt_script_hash.pack())
+        .amount(200u128.pack())
+        .build();
+
+    // new account
+    let account_count = state.get_account_count().unwrap();
+    let id = state
+        .apply_deposition_request(&ctx, &request)
+        .expect("apply deposition");
+    assert_eq!(id, account_count);
+    assert_eq!(
+        state
+            .get_account_id_by_script_hash(&user_script.hash().into())
+            .unwrap(),
+        Some(id)
+    );
+    let sudt_id = {
+        let l2_sudt_script = build_l2_sudt_script(&ctx, &sudt_script_hash.into());
+        state
+            .get_account_id_by_script_hash(&l2_sudt_script.hash().into())
+            .unwrap()
+            .expect("sUDT account")
+    };
+    assert_eq!(
+        state.get_sudt_balance(CKB_SUDT_ACCOUNT_ID, id).unwrap(),
+        1000
+    );
+    assert_eq!(state.get_sudt_balance(sudt_id, id).unwrap(), 200);
+
+    // existing account
+    let account_count = state.get_account_count().unwrap();
+    let existing_id = state
+        .apply_deposition_request(&ctx, &request)
+        .expect("apply deposition");
+    assert_eq!(existing_id, id);
+    assert_eq!(state.get_account_count().unwrap(), account_count);
+    assert_eq!(
+        state.get_sudt_balance(CKB_SUDT_ACCOUNT_ID, id).unwrap(),
+        2000
+    );
+    assert_eq!(state.get_sudt_balance(sudt_id, id).unwrap(), 400);
+}
+
+#[test]
+fn test_apply_withdrawal_request() {
+    let mut state = DummyState::default();
+    let ctx = RollupContext::new_unchecked([42u8; 32].into(), RollupConfig::default());
+    // reserved accounts
+    state.create_account(H256::zero()).unwrap();
+    let ckb_sudt_script = build_l2_sudt_script(&ctx, &CKB_SUDT_SCRIPT_ARGS.into());
+    state
+        .create_account_from_script(ckb_sudt_script)
+        .expect("create CKB sUDT account");
+
+    let user_script = Script::new_builder()
+        .code_hash([3u8; 32].pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args(Bytes::from(vec![4u8; 20]).pack())
+        .build();
+    let sudt_script_hash = [5u8; 32];
+    let deposition = DepositionRequest::new_builder()
+        .script(user_script.clone())
+        .capacity(1000u64.pack())
+        .sudt_script_hash(sudt_script_hash.pack())
+        .amount(200u128.pack())
+        .build();
+    let id = state
+        .apply_deposition_request(&ctx, &deposition)
+        .expect("apply deposition");
+    let sudt_id = {
+        let l2_sudt_script = build_l2_sudt_script(&ctx, &sudt_script_hash.into());
+        state
+            .get_account_id_by_script_hash(&l2_sudt_script.hash().into())
+            .unwrap()
+            .expect("sUDT account")
+    };
+    let build_withdrawal = |nonce: u32, capacity: u64, amount: u128| {
+        let raw = RawWithdrawalRequest::new_builder()
+            .nonce(nonce.pack())
+            .capacity(capacity.pack())
+            .amount(amount.pack())
+            .account_script_hash(user_script.hash().pack())
+            .sudt_script_hash(sudt_script_hash.pack())
+            .build();
+        WithdrawalRequest::new_builder().raw(raw).build()
+    };
+
+    // failed withdrawals don't touch the state
+    let root = state.calculate_root().unwrap();
+    let err = state
+        .apply_withdrawal_request(&ctx, &build_withdrawal(1, 100, 10))
+        .unwrap_err();
+    assert_eq!(
+        err,
+        Error::Withdrawal(WithdrawalError::Nonce {
+            expected: 0,
+            actual: 1
+        })
+    );
+    let err = state
+        .apply_withdrawal_request(&ctx, &build_withdrawal(0, 1001, 10))
+        .unwrap_err();
+    assert_eq!(err, Error::Withdrawal(WithdrawalError::Overdraft));
+    let err = state
+        .apply_withdrawal_request(&ctx, &build_withdrawal(0, 100, 201))
+        .unwrap_err();
+    assert_eq!(err, Error::Withdrawal(WithdrawalError::Overdraft));
+    assert_eq!(state.calculate_root().unwrap(), root);
+
+    state
+        .apply_withdrawal_request(&ctx, &build_withdrawal(0, 100, 10))
+        .expect("apply withdrawal");
+    assert_eq!(
+        state.get_sudt_balance(CKB_SUDT_ACCOUNT_ID, id).unwrap(),
+        900
+    );
+    assert_eq!(state.get_sudt_balance(sudt_id, id).unwrap(), 190);
+    assert_eq!(state.get_nonce(id).unwrap(), 1);
+}
@@ -1,7 +1,7 @@
-use crate::sudt::build_l2_sudt_script;
+use crate::sudt::{build_l2_sudt_script, sudt_account_id};
 use crate::{
     error::{AccountError, DepositionError, Error, WithdrawalError},
-    RollupContext,
+    AccountMeta, RollupContext,
 };
 use gw_common::{builtins::CKB_SUDT_ACCOUNT_ID, state::State, CKB_SUDT_SCRIPT_ARGS};
 use gw_traits::CodeStore;
@@ -15,13 +15,26 @@ use gw_types::{
 
 pub trait StateExt {
     fn create_account_from_script(&mut self, script: Script) -> Result<u32, Error>;
+    fn get_account_meta(&self, id: u32) -> Result<AccountMeta, Error>;
+    /// Resolve the script of an account from `code_store`,
+    /// returns `None` if the account exists but its script is missing from the `code_store`
+    fn resolve_account_script<C: CodeStore>(
+        &self,
+        id: u32,
+        code_store: &C,
+    ) -> Result<Option<Script>, Error>;
     fn apply_run_result(&mut self, run_result: &RunResult) -> Result<(), Error>;
+    /// Apply a deposition request, the depositor's account is created if it doesn't exist.
+    /// Returns the account id of the depositor
     fn apply_deposition_request(
         &mut self,
         ctx: &RollupContext,
         deposition_request: &DepositionRequest,
-    ) -> Result<(), Error>;
+    ) -> Result<u32, Error>;
 
+    /// Apply a withdrawal request, debit the CKB capacity and sUDT amount of the account
+    /// and increase its nonce. The nonce and both balances are checked before any write,
+    /// so the state is untouched if an error is returned
     fn apply_withdrawal_request(
         &mut self,
         ctx: &RollupContext,
@@ -64,6 +77,36 @@ impl<S: State + CodeStore> StateExt for S {
         Ok(id)
     }
 
+    fn get_account_meta(&self, id: u32) -> Result<AccountMeta, Error> {
+        if id >= self.get_account_count()? {
+            return Err(AccountError::UnknownAccount.into());
+        }
+        let script_hash = self.get_script_hash(id)?;
+        let nonce = self.get_nonce(id)?;
+        let code_hash = self
+            .get_script(&script_hash)
+            .ok_or(AccountError::ScriptNotFound { account_id: id })?
+            .code_hash()
+            .unpack();
+        Ok(AccountMeta {
+            script_hash,
+            nonce,
+            code_hash,
+        })
+    }
+
+    fn resolve_account_script<C: CodeStore>(
+        &self,
+        id: u32,
+        code_store: &C,
+    ) -> Result<Option<Script>, Error> {
+        if id >= self.get_account_count()? {
+            return Err(AccountError::UnknownAccount.into());
+        }
+        let script_hash = self.get_script_hash(id)?;
+        Ok(code_store.get_script(&script_hash))
+    }
+
     fn apply_run_result(&mut self, run_result: &RunResult) -> Result<(), Error> {
         for (k, v) in &run_result.write_values {
             self.update_raw(*k, *v)?;
@@ -86,7 +129,7 @@ impl<S: State + CodeStore> StateExt for S {
         &mut self,
         ctx: &RollupContext,
         request: &DepositionRequest,
-    ) -> Result<(), Error> {
+    ) -> Result<u32, Error> {
         // find or create user account
         let account_script_hash = request.script().hash();
         let id = match self.get_account_id_by_script_hash(&account_script_hash.into())? {
@@ -122,7 +165,7 @@ impl<S: State + CodeStore> StateExt for S {
             return Err(DepositionError::DepositFakedCKB.into());
         }
 
-        Ok(())
+        Ok(id)
     }
 
     fn apply_withdrawal_request(
@@ -132,27 +175,41 @@ impl<S: State + CodeStore> StateExt for S {
     ) -> Result<(), Error> {
         let raw = request.raw();
         let account_script_hash: [u8; 32] = raw.account_script_hash().unpack();
-        let l2_sudt_script_hash: [u8; 32] =
-            build_l2_sudt_script(&ctx, &raw.sudt_script_hash().unpack()).hash();
         let amount: u128 = raw.amount().unpack();
         // find user account
         let id = self
             .get_account_id_by_script_hash(&account_script_hash.into())?
             .ok_or(AccountError::UnknownAccount)?; // find Simple UDT account
         let capacity: u64 = raw.capacity().unpack();
+        let sudt_id = sudt_account_id(ctx, self, &raw.sudt_script_hash().unpack())?
+            .ok_or(AccountError::UnknownSUDT)?;
+        if sudt_id == CKB_SUDT_ACCOUNT_ID && amount != 0 {
+            return Err(WithdrawalError::WithdrawFakedCKB.into());
+        }
+        // check nonce
+        let nonce = self.get_nonce(id)?;
+        let request_nonce: u32 = raw.nonce().unpack();
+        if request_nonce != nonce {
+            return Err(WithdrawalError::Nonce {
+                expected: nonce,
+                actual: request_nonce,
+            }
+            .into());
+        }
+        // check balances before burning
+        if capacity as u128 > self.get_sudt_balance(CKB_SUDT_ACCOUNT_ID, id)? {
+            return Err(WithdrawalError::Overdraft.into());
+        }
+        if sudt_id != CKB_SUDT_ACCOUNT_ID && amount > self.get_sudt_balance(sudt_id, id)? {
+            return Err(WithdrawalError::Overdraft.into());
+        }
         // burn CKB
         self.burn_sudt(CKB_SUDT_ACCOUNT_ID, id, capacity.into())?;
-        let sudt_id = self
-            .get_account_id_by_script_hash(&l2_sudt_script_hash.into())?
-            .ok_or(AccountError::UnknownSUDT)?;
         if sudt_id != CKB_SUDT_ACCOUNT_ID {
             // burn sudt
             self.burn_sudt(sudt_id, id, amount)?;
-        } else if amount != 0 {
-            return Err(WithdrawalError::WithdrawFakedCKB.into());
         }
         // increase nonce
-        let nonce = self.get_nonce(id)?;
         let new_nonce = nonce.checked_add(1).ok_or(AccountError::NonceOverflow)?;
         self.set_nonce(id, new_nonce)?;
         Ok(())
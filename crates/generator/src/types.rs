@@ -1,11 +1,148 @@
-use gw_common::H256;
-use gw_types::packed::{ChallengeTarget, ChallengeWitness, RollupConfig};
+use crate::error::ConfigError;
+use gw_common::{error::ValidationError, H256};
+use gw_types::{
+    core::Status,
+    packed::{Byte32, ChallengeTarget, ChallengeWitness, GlobalState, RollupConfig},
+    prelude::*,
+};
+use std::convert::TryFrom;
 use std::fmt::{self, Display};
 
+/// Built by `RollupContext::new` which validates the rollup config
 #[derive(Clone)]
 pub struct RollupContext {
-    pub rollup_script_hash: H256,
-    pub rollup_config: RollupConfig,
+    rollup_script_hash: H256,
+    rollup_config: RollupConfig,
+    /// Mix `compatible_chain_id` into the L2 transaction signing message,
+    /// so a transaction can't be replayed on a fork which shares the rollup type hash.
+    /// Disabled by default for backward compatibility
+    chain_id_domain_tag: bool,
+    /// Polyjuice chain id of the rollup (the id of the polyjuice creator account),
+    /// the lower half of the EVM chain id `compatible_chain_id << 32 | polyjuice_chain_id`.
+    /// The rollup config doesn't carry it, the block producer sets it from the required
+    /// `polyjuice_chain_id` of the chain config. Contexts built without it, e.g. in tests,
+    /// don't check the chain id of polyjuice txs
+    polyjuice_chain_id: Option<u32>,
+}
+
+impl RollupContext {
+    /// Build the context, rejects an unconfigured rollup config of which the rollup script hash
+    /// or any of the required script type hashes is zero
+    pub fn new(rollup_script_hash: H256, rollup_config: RollupConfig) -> Result<Self, ConfigError> {
+        if rollup_script_hash.is_zero() {
+            return Err(ConfigError::ZeroRollupScriptHash);
+        }
+        let required_hashes: [(&'static str, Byte32); 7] = [
+            (
+                "l1_sudt_script_type_hash",
+                rollup_config.l1_sudt_script_type_hash(),
+            ),
+            (
+                "custodian_script_type_hash",
+                rollup_config.custodian_script_type_hash(),
+            ),
+            (
+                "deposition_script_type_hash",
+                rollup_config.deposition_script_type_hash(),
+            ),
+            (
+                "withdrawal_script_type_hash",
+                rollup_config.withdrawal_script_type_hash(),
+            ),
+            (
+                "challenge_script_type_hash",
+                rollup_config.challenge_script_type_hash(),
+            ),
+            (
+                "stake_script_type_hash",
+                rollup_config.stake_script_type_hash(),
+            ),
+            (
+                "l2_sudt_validator_script_type_hash",
+                rollup_config.l2_sudt_validator_script_type_hash(),
+            ),
+        ];
+        for (name, hash) in required_hashes.iter() {
+            let hash: H256 = hash.unpack();
+            if hash.is_zero() {
+                return Err(ConfigError::ZeroCodeHash(*name));
+            }
+        }
+        Ok(RollupContext {
+            rollup_script_hash,
+            rollup_config,
+            chain_id_domain_tag: false,
+            polyjuice_chain_id: None,
+        })
+    }
+
+    /// Build the context without validating the rollup config,
+    /// for tests which only configure the scripts they use
+    #[cfg(any(test, feature = "testing"))]
+    pub fn new_unchecked(rollup_script_hash: H256, rollup_config: RollupConfig) -> Self {
+        RollupContext {
+            rollup_script_hash,
+            rollup_config,
+            chain_id_domain_tag: false,
+            polyjuice_chain_id: None,
+        }
+    }
+
+    /// Enable or disable mixing `compatible_chain_id` into the L2 transaction signing message
+    pub fn with_chain_id_domain_tag(mut self, chain_id_domain_tag: bool) -> Self {
+        self.chain_id_domain_tag = chain_id_domain_tag;
+        self
+    }
+
+    /// Set the polyjuice chain id which polyjuice txs must imply
+    pub fn with_polyjuice_chain_id(mut self, polyjuice_chain_id: Option<u32>) -> Self {
+        self.polyjuice_chain_id = polyjuice_chain_id;
+        self
+    }
+
+    pub fn rollup_script_hash(&self) -> &H256 {
+        &self.rollup_script_hash
+    }
+
+    pub fn rollup_config(&self) -> &RollupConfig {
+        &self.rollup_config
+    }
+
+    pub fn chain_id_domain_tag(&self) -> bool {
+        self.chain_id_domain_tag
+    }
+
+    pub fn polyjuice_chain_id(&self) -> Option<u32> {
+        self.polyjuice_chain_id
+    }
+
+    /// Number of blocks a block takes to be finalized
+    pub fn finality_blocks(&self) -> u64 {
+        self.rollup_config.finality_blocks().unpack()
+    }
+}
+
+/// Return true if the block is at least `finality_blocks` behind the tip
+pub fn is_block_finalized(ctx: &RollupContext, block_number: u64, tip: u64) -> bool {
+    block_number <= tip && tip - block_number >= ctx.finality_blocks()
+}
+
+/// Return `ChainHalted` unless the rollup status is Running,
+/// no new transaction or withdrawal is accepted while the rollup is halting
+pub fn ensure_running(global_state: &GlobalState) -> Result<(), ValidationError> {
+    let status: u8 = global_state.status().into();
+    if Status::try_from(status) != Ok(Status::Running) {
+        return Err(ValidationError::ChainHalted { status });
+    }
+    Ok(())
+}
+
+/// Frequently used fields of an account
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AccountMeta {
+    pub script_hash: H256,
+    pub nonce: u32,
+    pub code_hash: H256,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -19,3 +156,94 @@ impl Display for ChallengeContext {
         write!(f, "{{target: {}, witness: {}}}", self.target, self.witness)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_rollup_config(type_hash: [u8; 32]) -> RollupConfig {
+        RollupConfig::new_builder()
+            .l1_sudt_script_type_hash(type_hash.pack())
+            .custodian_script_type_hash(type_hash.pack())
+            .deposition_script_type_hash(type_hash.pack())
+            .withdrawal_script_type_hash(type_hash.pack())
+            .challenge_script_type_hash(type_hash.pack())
+            .stake_script_type_hash(type_hash.pack())
+            .l2_sudt_validator_script_type_hash(type_hash.pack())
+            .build()
+    }
+
+    #[test]
+    fn test_ensure_running() {
+        let build_global_state =
+            |status: u8| GlobalState::new_builder().status(status.into()).build();
+        assert_eq!(
+            ensure_running(&build_global_state(Status::Running.into())),
+            Ok(())
+        );
+        assert_eq!(
+            ensure_running(&build_global_state(Status::Halting.into())),
+            Err(ValidationError::ChainHalted { status: 1 })
+        );
+        // unknown status
+        assert_eq!(
+            ensure_running(&build_global_state(42)),
+            Err(ValidationError::ChainHalted { status: 42 })
+        );
+    }
+
+    #[test]
+    fn test_new_rollup_context() {
+        let rollup_script_hash: H256 = [1u8; 32].into();
+        let ctx = RollupContext::new(rollup_script_hash, build_rollup_config([2u8; 32]))
+            .expect("valid config");
+        assert_eq!(ctx.rollup_script_hash(), &rollup_script_hash);
+        assert!(!ctx.chain_id_domain_tag());
+        assert_eq!(ctx.polyjuice_chain_id(), None);
+        let ctx = ctx.with_polyjuice_chain_id(Some(23));
+        assert_eq!(ctx.polyjuice_chain_id(), Some(23));
+        assert!(ctx.with_chain_id_domain_tag(true).chain_id_domain_tag());
+
+        assert_eq!(
+            RollupContext::new(rollup_script_hash, build_rollup_config([0u8; 32])).err(),
+            Some(ConfigError::ZeroCodeHash("l1_sudt_script_type_hash"))
+        );
+        assert_eq!(
+            RollupContext::new(rollup_script_hash, RollupConfig::default()).err(),
+            Some(ConfigError::ZeroCodeHash("l1_sudt_script_type_hash"))
+        );
+        let partial_config = build_rollup_config([2u8; 32])
+            .as_builder()
+            .stake_script_type_hash([0u8; 32].pack())
+            .build();
+        assert_eq!(
+            RollupContext::new(rollup_script_hash, partial_config).err(),
+            Some(ConfigError::ZeroCodeHash("stake_script_type_hash"))
+        );
+        assert_eq!(
+            RollupContext::new(H256::zero(), build_rollup_config([2u8; 32])).err(),
+            Some(ConfigError::ZeroRollupScriptHash)
+        );
+    }
+
+    #[test]
+    fn test_is_block_finalized() {
+        let ctx = RollupContext::new_unchecked(
+            H256::zero(),
+            RollupConfig::new_builder()
+                .finality_blocks(100u64.pack())
+                .build(),
+        );
+        assert_eq!(ctx.finality_blocks(), 100);
+        let tip = 1000;
+        // exactly finality_blocks behind tip
+        assert!(is_block_finalized(&ctx, 900, tip));
+        assert!(is_block_finalized(&ctx, 899, tip));
+        assert!(!is_block_finalized(&ctx, 901, tip));
+        assert!(!is_block_finalized(&ctx, tip, tip));
+        assert!(!is_block_finalized(&ctx, tip + 1, tip));
+        // the chain is shorter than finality_blocks
+        assert!(!is_block_finalized(&ctx, 0, 99));
+        assert!(is_block_finalized(&ctx, 0, 100));
+    }
+}
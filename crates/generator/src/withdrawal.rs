@@ -0,0 +1,116 @@
+//! Withdrawal sell orders
+//!
+//! A withdrawal request can be sold before the challenge period ends,
+//! a buyer pays `sell_capacity` CKB and `sell_amount` sUDT to the `payment_lock_hash`
+//! to unlock the withdrawal cell immediately.
+
+use crate::error::WithdrawalError;
+use gw_common::{error::ValidationError, H256};
+use gw_types::{packed::RawWithdrawalRequest, prelude::*};
+
+/// Check the sell terms of a withdrawal request,
+/// the selling price can't exceed the withdrawal itself
+pub fn validate_sell_terms(req: &RawWithdrawalRequest) -> Result<(), WithdrawalError> {
+    let capacity: u64 = req.capacity().unpack();
+    let amount: u128 = req.amount().unpack();
+    let sell_capacity: u64 = req.sell_capacity().unpack();
+    let sell_amount: u128 = req.sell_amount().unpack();
+    if sell_capacity > capacity || sell_amount > amount {
+        return Err(WithdrawalError::InvalidSellTerms);
+    }
+    Ok(())
+}
+
+/// Check the layer1 locks of a withdrawal request,
+/// the payment lock must exist on the chain to receive the payment of a buyer,
+/// the owner lock may be anything
+pub fn validate_locks<F: Fn(&H256) -> bool>(
+    req: &RawWithdrawalRequest,
+    lock_exists: F,
+) -> Result<(), ValidationError> {
+    let lock_hash: H256 = req.payment_lock_hash().unpack();
+    if !lock_exists(&lock_hash) {
+        return Err(ValidationError::UnknownPaymentLock { lock_hash });
+    }
+    Ok(())
+}
+
+/// Return true if the offered payment satisfies the sell terms of the withdrawal request
+pub fn can_buy(req: &RawWithdrawalRequest, offered_capacity: u64, offered_amount: u128) -> bool {
+    if validate_sell_terms(req).is_err() {
+        return false;
+    }
+    let sell_capacity: u64 = req.sell_capacity().unpack();
+    let sell_amount: u128 = req.sell_amount().unpack();
+    offered_capacity >= sell_capacity && offered_amount >= sell_amount
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_request(
+        capacity: u64,
+        amount: u128,
+        sell_capacity: u64,
+        sell_amount: u128,
+    ) -> RawWithdrawalRequest {
+        RawWithdrawalRequest::new_builder()
+            .capacity(capacity.pack())
+            .amount(amount.pack())
+            .sell_capacity(sell_capacity.pack())
+            .sell_amount(sell_amount.pack())
+            .build()
+    }
+
+    #[test]
+    fn test_can_buy_exact_match() {
+        let req = build_request(1000_00000000, 500, 900_00000000, 400);
+        assert!(validate_sell_terms(&req).is_ok());
+        assert!(can_buy(&req, 900_00000000, 400));
+    }
+
+    #[test]
+    fn test_can_not_buy_with_insufficient_offer() {
+        let req = build_request(1000_00000000, 500, 900_00000000, 400);
+        assert!(!can_buy(&req, 900_00000000 - 1, 400));
+        assert!(!can_buy(&req, 900_00000000, 399));
+    }
+
+    #[test]
+    fn test_invalid_sell_terms() {
+        let req = build_request(1000_00000000, 500, 1000_00000001, 400);
+        assert_eq!(
+            validate_sell_terms(&req),
+            Err(WithdrawalError::InvalidSellTerms)
+        );
+        assert!(!can_buy(&req, u64::MAX, u128::MAX));
+
+        let req = build_request(1000_00000000, 500, 900_00000000, 501);
+        assert_eq!(
+            validate_sell_terms(&req),
+            Err(WithdrawalError::InvalidSellTerms)
+        );
+    }
+
+    #[test]
+    fn test_validate_locks() {
+        let payment_lock_hash = H256::from([1u8; 32]);
+        let req = build_request(1000_00000000, 500, 900_00000000, 400)
+            .as_builder()
+            .owner_lock_hash([2u8; 32].pack())
+            .payment_lock_hash(payment_lock_hash.pack())
+            .build();
+        // the owner lock isn't required to exist
+        assert_eq!(
+            validate_locks(&req, |lock_hash| *lock_hash == payment_lock_hash),
+            Ok(())
+        );
+        assert_eq!(
+            validate_locks(&req, |_| false),
+            Err(ValidationError::UnknownPaymentLock {
+                lock_hash: payment_lock_hash
+            })
+        );
+    }
+}
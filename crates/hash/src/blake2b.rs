@@ -4,8 +4,72 @@ pub const BLAKE2B_KEY: &[u8] = &[];
 pub const BLAKE2B_LEN: usize = 32;
 pub const CKB_PERSONALIZATION: &[u8] = b"ckb-default-hash";
 
+/// Hash function backend, implemented by `Blake2b` by default.
+/// Implement it to plug in an accelerated or a mock hash function
+pub trait Hasher {
+    fn update(&mut self, data: &[u8]);
+    fn finalize(self, dst: &mut [u8]);
+}
+
+impl Hasher for Blake2b {
+    fn update(&mut self, data: &[u8]) {
+        Blake2b::update(self, data)
+    }
+    fn finalize(self, dst: &mut [u8]) {
+        Blake2b::finalize(self, dst)
+    }
+}
+
 pub fn new_blake2b() -> Blake2b {
     Blake2bBuilder::new(32)
         .personal(CKB_PERSONALIZATION)
         .build()
 }
+
+/// Hash data with the given hasher
+pub fn hash_with<H: Hasher>(mut hasher: H, data: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hasher.update(data);
+    hasher.finalize(&mut hash);
+    hash
+}
+
+/// Hash data with the default blake2b hasher
+pub fn blake2b_256(data: &[u8]) -> [u8; 32] {
+    hash_with(new_blake2b(), data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A stub hasher that returns the sum of bytes
+    #[derive(Default)]
+    struct SumHasher(u64);
+
+    impl Hasher for SumHasher {
+        fn update(&mut self, data: &[u8]) {
+            self.0 += data.iter().map(|b| *b as u64).sum::<u64>();
+        }
+        fn finalize(self, dst: &mut [u8]) {
+            dst[..8].copy_from_slice(&self.0.to_le_bytes());
+        }
+    }
+
+    #[test]
+    fn test_stub_hasher() {
+        let hash = hash_with(SumHasher::default(), &[1, 2, 3]);
+        let mut expected = [0u8; 32];
+        expected[0] = 6;
+        assert_eq!(hash, expected);
+    }
+
+    #[test]
+    fn test_default_hasher() {
+        let mut expected = [0u8; 32];
+        let mut hasher = new_blake2b();
+        hasher.update(b"godwoken");
+        hasher.finalize(&mut expected);
+        assert_eq!(blake2b_256(b"godwoken"), expected);
+    }
+}
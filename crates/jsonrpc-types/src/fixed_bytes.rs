@@ -2,6 +2,7 @@ use faster_hex::{hex_decode, hex_encode};
 use gw_types::{packed, prelude::*};
 use std::fmt::{self, Debug};
 use std::hash::{Hash, Hasher};
+use std::str::FromStr;
 
 #[derive(Clone)]
 pub struct Byte65(pub [u8; 65]);
@@ -38,6 +39,61 @@ impl Byte65 {
     }
 }
 
+/// Errors of parsing a fixed bytes hex string
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum FromHexError {
+    MissingPrefix,
+    InvalidLength { expected: usize, actual: usize },
+    InvalidHex,
+}
+
+impl fmt::Display for FromHexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FromHexError::MissingPrefix => write!(f, "missing 0x prefix"),
+            FromHexError::InvalidLength { expected, actual } => write!(
+                f,
+                "invalid length, expected: {} actual: {}",
+                expected, actual
+            ),
+            FromHexError::InvalidHex => write!(f, "invalid hex character"),
+        }
+    }
+}
+
+impl std::error::Error for FromHexError {}
+
+impl FromStr for Byte65 {
+    type Err = FromHexError;
+
+    /// Parse a 0x-prefixed hex string of 130 hex chars
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() < 2 || &s.as_bytes()[0..2] != b"0x" {
+            return Err(FromHexError::MissingPrefix);
+        }
+        let hex = &s.as_bytes()[2..];
+        if hex.len() != 130 {
+            return Err(FromHexError::InvalidLength {
+                expected: 130,
+                actual: hex.len(),
+            });
+        }
+        let mut buffer = [0u8; 65];
+        hex_decode(hex, &mut buffer).map_err(|_| FromHexError::InvalidHex)?;
+        Ok(Byte65(buffer))
+    }
+}
+
+impl fmt::Display for Byte65 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut buffer = [0u8; 130];
+        hex_encode(&self.0, &mut buffer).map_err(|_| fmt::Error)?;
+        write!(f, "0x{}", unsafe {
+            ::std::str::from_utf8_unchecked(&buffer)
+        })
+    }
+}
+
 impl From<packed::Signature> for Byte65 {
     fn from(packed: packed::Signature) -> Self {
         let mut inner: [u8; 65] = [0u8; 65];
@@ -65,13 +121,11 @@ impl<'b> serde::de::Visitor<'b> for Byte32Visitor {
     where
         E: serde::de::Error,
     {
-        if v.len() < 2 || &v.as_bytes()[0..2] != b"0x" || v.len() != 66 {
-            return Err(E::invalid_value(serde::de::Unexpected::Str(v), &self));
+        match v.parse() {
+            Ok(bytes) => Ok(bytes),
+            Err(FromHexError::InvalidHex) => Err(E::custom("invalid hex character")),
+            Err(_) => Err(E::invalid_value(serde::de::Unexpected::Str(v), &self)),
         }
-        let mut buffer = [0u8; 65]; // we checked length
-        hex_decode(&v.as_bytes()[2..], &mut buffer)
-            .map_err(|e| E::custom(format_args!("{:?}", e)))?;
-        Ok(Byte65(buffer))
     }
 
     fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
@@ -104,3 +158,43 @@ impl<'de> serde::Deserialize<'de> for Byte65 {
         deserializer.deserialize_str(Byte32Visitor)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_byte65_from_str() {
+        let hex = format!("0x{}", "ab".repeat(65));
+        let bytes: Byte65 = hex.parse().unwrap();
+        assert_eq!(bytes, Byte65([0xab; 65]));
+        assert_eq!(bytes.to_string(), hex);
+
+        let json = serde_json::to_string(&bytes).unwrap();
+        assert_eq!(json, format!("\"{}\"", hex));
+        let decoded: Byte65 = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, bytes);
+    }
+
+    #[test]
+    fn test_byte65_from_str_wrong_length() {
+        let hex = format!("0x{}", "ab".repeat(32));
+        assert_eq!(
+            hex.parse::<Byte65>(),
+            Err(FromHexError::InvalidLength {
+                expected: 130,
+                actual: 64
+            })
+        );
+    }
+
+    #[test]
+    fn test_byte65_from_str_missing_prefix() {
+        let hex = "ab".repeat(65);
+        assert_eq!(hex.parse::<Byte65>(), Err(FromHexError::MissingPrefix));
+        assert_eq!(
+            format!("0x{}", "zz".repeat(65)).parse::<Byte65>(),
+            Err(FromHexError::InvalidHex)
+        );
+    }
+}
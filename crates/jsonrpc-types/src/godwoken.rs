@@ -1,14 +1,19 @@
 use crate::blockchain::Script;
 use crate::fixed_bytes::Byte65;
+use crate::packed_conversion::{checked_sum, verify_l2block};
+pub use crate::packed_conversion::{
+    ConversionError, SizeLimits, TooLarge, DEFAULT_MAX_BLOCK_TXS, DEFAULT_MAX_TX_ARGS_SIZE,
+};
 use anyhow::{anyhow, Error as JsonError};
 use ckb_fixed_hash::H256;
 use ckb_jsonrpc_types::{JsonBytes, Uint128, Uint32, Uint64};
+use gw_common::merkle_utils::calculate_state_checkpoint;
 use gw_types::{bytes::Bytes, offchain, packed, prelude::*};
 use serde::{Deserialize, Serialize};
 use std::convert::{TryFrom, TryInto};
 
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Default)]
-#[serde(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
 pub struct RawL2Transaction {
     pub from_id: Uint32,
     pub to_id: Uint32,
@@ -16,14 +21,23 @@ pub struct RawL2Transaction {
     pub args: JsonBytes,
 }
 
-impl From<RawL2Transaction> for packed::RawL2Transaction {
-    fn from(tx: RawL2Transaction) -> Self {
+impl RawL2Transaction {
+    /// Convert into the packed type, rejecting `args` larger than the limit
+    pub fn try_into_packed(
+        self,
+        limits: &SizeLimits,
+    ) -> Result<packed::RawL2Transaction, JsonError> {
+        SizeLimits::check("args", limits.max_tx_args_size, self.args.len())?;
+        Ok(self.pack_unchecked())
+    }
+
+    fn pack_unchecked(self) -> packed::RawL2Transaction {
         let RawL2Transaction {
             from_id,
             to_id,
             nonce,
             args,
-        } = tx;
+        } = self;
         let args: Bytes = args.into_bytes();
         packed::RawL2Transaction::new_builder()
             .from_id(u32::from(from_id).pack())
@@ -34,6 +48,14 @@ impl From<RawL2Transaction> for packed::RawL2Transaction {
     }
 }
 
+impl TryFrom<RawL2Transaction> for packed::RawL2Transaction {
+    type Error = JsonError;
+
+    fn try_from(tx: RawL2Transaction) -> Result<Self, Self::Error> {
+        tx.try_into_packed(&SizeLimits::default())
+    }
+}
+
 impl From<packed::RawL2Transaction> for RawL2Transaction {
     fn from(raw_l2_transaction: packed::RawL2Transaction) -> RawL2Transaction {
         let from_id: u32 = raw_l2_transaction.from_id().unpack();
@@ -49,20 +71,19 @@ impl From<packed::RawL2Transaction> for RawL2Transaction {
 }
 
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Default)]
-#[serde(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
 pub struct L2Transaction {
     pub raw: RawL2Transaction,
+    /// A signature which isn't 65 bytes is rejected by the deserialization of `Byte65`,
+    /// so the conversion into `packed::Signature` is always valid
     pub signature: Byte65,
 }
 
-impl From<L2Transaction> for packed::L2Transaction {
-    fn from(tx: L2Transaction) -> Self {
-        let L2Transaction { raw, signature } = tx;
+impl TryFrom<L2Transaction> for packed::L2Transaction {
+    type Error = JsonError;
 
-        packed::L2Transaction::new_builder()
-            .raw(raw.into())
-            .signature(signature.into())
-            .build()
+    fn try_from(tx: L2Transaction) -> Result<Self, Self::Error> {
+        tx.try_into_packed(&SizeLimits::default())
     }
 }
 
@@ -75,19 +96,68 @@ impl From<packed::L2Transaction> for L2Transaction {
     }
 }
 
+impl L2Transaction {
+    /// Convert into the packed type, rejecting `raw.args` larger than the limit
+    pub fn try_into_packed(self, limits: &SizeLimits) -> Result<packed::L2Transaction, JsonError> {
+        SizeLimits::check("args", limits.max_tx_args_size, self.raw.args.len())?;
+        Ok(self.pack_unchecked())
+    }
+
+    fn pack_unchecked(self) -> packed::L2Transaction {
+        let L2Transaction { raw, signature } = self;
+
+        packed::L2Transaction::new_builder()
+            .raw(raw.pack_unchecked())
+            .signature(signature.into())
+            .build()
+    }
+
+    /// Transaction hash, computed from `raw`
+    pub fn tx_hash(&self) -> H256 {
+        let tx = self.clone().pack_unchecked();
+        H256::from(tx.hash())
+    }
+
+    /// Witness hash, computed from `raw` and `signature`,
+    /// the `tx_witness_root` of `SubmitTransactions` commits to this hash
+    pub fn witness_hash(&self) -> H256 {
+        let tx = self.clone().pack_unchecked();
+        H256::from(tx.witness_hash())
+    }
+
+    /// Canonical binary form, the molecule encoding of the packed transaction
+    pub fn to_molecule_bytes(&self) -> Bytes {
+        let tx = self.clone().pack_unchecked();
+        tx.as_bytes()
+    }
+
+    pub fn from_molecule_bytes(data: &[u8]) -> Result<Self, JsonError> {
+        let tx = packed::L2Transaction::from_slice(data)
+            .map_err(|err| anyhow!("Invalid L2Transaction molecule bytes {}", err))?;
+        Ok(tx.into())
+    }
+}
+
 #[derive(Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash, Debug)]
 #[serde(rename_all = "snake_case")]
 pub struct L2TransactionView {
     #[serde(flatten)]
     pub inner: L2Transaction,
+    // transaction hash
     pub hash: H256,
+    pub witness_hash: H256,
 }
 
 impl From<packed::L2Transaction> for L2TransactionView {
     fn from(l2_tx: packed::L2Transaction) -> L2TransactionView {
-        let hash = H256::from(l2_tx.raw().hash());
+        let hash = H256::from(l2_tx.hash());
+        let witness_hash = H256::from(l2_tx.witness_hash());
         let inner = L2Transaction::from(l2_tx);
-        L2TransactionView { inner, hash }
+        L2TransactionView {
+            inner,
+            hash,
+            witness_hash,
+        }
     }
 }
 
@@ -137,6 +207,57 @@ pub struct TxReceipt {
     pub logs: Vec<LogItem>,
 }
 
+impl TxReceipt {
+    /// The compacted account root after the tx: `blake2b(account_root | account_count(u32 LE))`
+    pub fn compacted_post_account_root(&self) -> H256 {
+        let root: gw_common::H256 = self.post_state.merkle_root.0.into();
+        let count: u32 = self.post_state.count.into();
+        let checkpoint: [u8; 32] = calculate_state_checkpoint(&root, count).into();
+        H256(checkpoint)
+    }
+}
+
+/// Convert a packed `Byte32` to a JSON hash, the byte order is preserved
+pub fn byte32_to_json_h256(b: &packed::Byte32) -> H256 {
+    b.unpack()
+}
+
+/// Convert a JSON hash to a packed `Byte32`, the byte order is preserved
+pub fn json_h256_to_byte32(h: &H256) -> packed::Byte32 {
+    h.pack()
+}
+
+/// Sort hashes in the SMT key order, so the output doesn't depend on the input order
+pub fn canonical_hashes(mut hashes: Vec<H256>) -> Vec<H256> {
+    hashes.sort_unstable_by_key(|hash| gw_common::H256::from(hash.0));
+    hashes
+}
+
+/// Sort KV pairs by key in the SMT key order, the leaves of a merkle proof are compiled in this order
+pub fn canonical_kv_state(mut kv_state: Vec<KVPair>) -> Vec<KVPair> {
+    kv_state.sort_unstable_by_key(|pair| gw_common::H256::from(pair.k.0));
+    kv_state
+}
+
+/// Build a receipt from the account state after the tx
+pub fn build_tx_receipt(
+    tx_witness_hash: H256,
+    post_root: H256,
+    post_count: u32,
+    read_data_hashes: Vec<H256>,
+    logs: Vec<LogItem>,
+) -> TxReceipt {
+    TxReceipt {
+        tx_witness_hash,
+        post_state: AccountMerkleState {
+            merkle_root: post_root,
+            count: post_count.into(),
+        },
+        read_data_hashes: canonical_hashes(read_data_hashes),
+        logs,
+    }
+}
+
 impl From<TxReceipt> for packed::TxReceipt {
     fn from(json: TxReceipt) -> packed::TxReceipt {
         let TxReceipt {
@@ -145,17 +266,13 @@ impl From<TxReceipt> for packed::TxReceipt {
             read_data_hashes,
             logs,
         } = json;
-        let tx_witness_hash: [u8; 32] = tx_witness_hash.into();
-        let read_data_hashes: Vec<_> = read_data_hashes
+        let read_data_hashes: Vec<_> = canonical_hashes(read_data_hashes)
             .into_iter()
-            .map(|hash| {
-                let hash: [u8; 32] = hash.into();
-                hash.pack()
-            })
+            .map(|hash| hash.0)
             .collect();
         let logs: Vec<packed::LogItem> = logs.into_iter().map(|item| item.into()).collect();
         packed::TxReceipt::new_builder()
-            .tx_witness_hash(tx_witness_hash.pack())
+            .tx_witness_hash(tx_witness_hash.0.pack())
             .post_state(post_state.into())
             .read_data_hashes(read_data_hashes.pack())
             .logs(logs.pack())
@@ -165,19 +282,16 @@ impl From<TxReceipt> for packed::TxReceipt {
 
 impl From<packed::TxReceipt> for TxReceipt {
     fn from(data: packed::TxReceipt) -> TxReceipt {
-        let tx_witness_hash: [u8; 32] = data.tx_witness_hash().unpack();
         let post_state: AccountMerkleState = data.post_state().into();
         let read_data_hashes: Vec<_> = data
             .read_data_hashes()
             .into_iter()
-            .map(|hash| {
-                let hash: [u8; 32] = hash.unpack();
-                hash.into()
-            })
+            .map(|hash| H256(hash.unpack()))
             .collect();
+        let read_data_hashes = canonical_hashes(read_data_hashes);
         let logs: Vec<LogItem> = data.logs().into_iter().map(|item| item.into()).collect();
         TxReceipt {
-            tx_witness_hash: tx_witness_hash.into(),
+            tx_witness_hash: H256(data.tx_witness_hash().unpack()),
             post_state,
             read_data_hashes,
             logs,
@@ -296,8 +410,30 @@ pub struct L2Block {
     pub withdrawals: Vec<WithdrawalRequest>,
 }
 
-impl From<L2Block> for packed::L2Block {
-    fn from(json: L2Block) -> packed::L2Block {
+impl TryFrom<L2Block> for packed::L2Block {
+    type Error = JsonError;
+
+    fn try_from(json: L2Block) -> Result<Self, Self::Error> {
+        json.try_into_packed(&SizeLimits::default())
+    }
+}
+
+impl L2Block {
+    /// Convert into the packed type, rejecting blocks with too many transactions
+    /// or transactions with oversized `args`
+    pub fn try_into_packed(self, limits: &SizeLimits) -> Result<packed::L2Block, JsonError> {
+        SizeLimits::check(
+            "transactions",
+            limits.max_block_txs,
+            self.transactions.len(),
+        )?;
+        for tx in &self.transactions {
+            SizeLimits::check("args", limits.max_tx_args_size, tx.raw.args.len())?;
+        }
+        Ok(self.pack_unchecked())
+    }
+
+    fn pack_unchecked(self) -> packed::L2Block {
         let L2Block {
             raw,
             kv_state,
@@ -305,11 +441,16 @@ impl From<L2Block> for packed::L2Block {
             transactions,
             block_proof,
             withdrawals,
-        } = json;
-        let kv_pair_vec: Vec<packed::KVPair> = kv_state.into_iter().map(|k| k.into()).collect();
+        } = self;
+        let kv_pair_vec: Vec<packed::KVPair> = canonical_kv_state(kv_state)
+            .into_iter()
+            .map(|k| k.into())
+            .collect();
         let packed_kv_state = packed::KVPairVec::new_builder().set(kv_pair_vec).build();
-        let transaction_vec: Vec<packed::L2Transaction> =
-            transactions.into_iter().map(|t| t.into()).collect();
+        let transaction_vec: Vec<packed::L2Transaction> = transactions
+            .into_iter()
+            .map(L2Transaction::pack_unchecked)
+            .collect();
         let packed_transactions = packed::L2TransactionVec::new_builder()
             .set(transaction_vec)
             .build();
@@ -329,17 +470,21 @@ impl From<L2Block> for packed::L2Block {
     }
 }
 
+/// Lazily convert the transactions of a packed block,
+/// so callers streaming a big block don't hold all the JSON transactions at once
+pub fn transactions_iter(block: &packed::L2Block) -> impl Iterator<Item = L2Transaction> + '_ {
+    block.transactions().into_iter().map(|t| t.into())
+}
+
 impl From<packed::L2Block> for L2Block {
     fn from(l2_block: packed::L2Block) -> L2Block {
         Self {
             raw: l2_block.raw().into(),
-            kv_state: l2_block.kv_state().into_iter().map(|k| k.into()).collect(),
+            kv_state: canonical_kv_state(
+                l2_block.kv_state().into_iter().map(|k| k.into()).collect(),
+            ),
             kv_state_proof: JsonBytes::from_bytes(l2_block.kv_state_proof().unpack()),
-            transactions: l2_block
-                .transactions()
-                .into_iter()
-                .map(|t| t.into())
-                .collect(),
+            transactions: transactions_iter(&l2_block).collect(),
             block_proof: JsonBytes::from_bytes(l2_block.block_proof().unpack()),
             withdrawals: l2_block
                 .withdrawals()
@@ -350,6 +495,40 @@ impl From<packed::L2Block> for L2Block {
     }
 }
 
+impl L2Block {
+    /// Canonical binary form, the molecule encoding of the packed block
+    pub fn to_molecule_bytes(&self) -> Bytes {
+        let block = self.clone().pack_unchecked();
+        block.as_bytes()
+    }
+
+    pub fn from_molecule_bytes(data: &[u8]) -> Result<Self, JsonError> {
+        let block = packed::L2Block::from_slice(data)
+            .map_err(|err| anyhow!("Invalid L2Block molecule bytes {}", err))?;
+        Ok(block.into())
+    }
+}
+
+/// Serialize the block as JSON into the writer,
+/// the output is the same as `serde_json::to_string` but isn't buffered as a whole in memory.
+/// Prefer it for large blocks, e.g. blocks with thousands of transactions
+pub fn serialize_l2block_streaming<W: std::io::Write>(
+    block: &L2Block,
+    w: &mut W,
+) -> serde_json::Result<()> {
+    serde_json::to_writer(w, block)
+}
+
+/// Convert untrusted molecule bytes into a JSON block,
+/// the bytes are verified and the size limits are checked on the reader,
+/// so nothing is unpacked before the declared lengths are known to be sane
+pub fn l2_block_from_verified(
+    bytes: &[u8],
+    limits: &SizeLimits,
+) -> Result<L2Block, ConversionError> {
+    verify_l2block(bytes, limits).map(Into::into)
+}
+
 #[derive(Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash, Debug)]
 #[serde(rename_all = "snake_case")]
 pub struct RawL2Block {
@@ -381,10 +560,7 @@ impl From<RawL2Block> for packed::RawL2Block {
             state_checkpoint_list,
         } = json;
 
-        let state_checkpoint_list = state_checkpoint_list
-            .into_iter()
-            .map(|checkpoint| checkpoint.pack())
-            .pack();
+        let state_checkpoint_list = state_checkpoint_list.iter().map(json_h256_to_byte32).pack();
         packed::RawL2Block::new_builder()
             .number(u64::from(number).pack())
             .parent_block_hash(parent_block_hash.pack())
@@ -408,7 +584,7 @@ impl From<packed::RawL2Block> for RawL2Block {
         let state_checkpoint_list = raw_l2_block
             .state_checkpoint_list()
             .into_iter()
-            .map(|checkpoint| checkpoint.unpack())
+            .map(|checkpoint| byte32_to_json_h256(&checkpoint))
             .collect();
         Self {
             number: number.into(),
@@ -442,13 +618,11 @@ impl From<packed::L2Block> for L2BlockView {
         Self {
             hash: H256::from(l2_block.raw().hash()),
             raw: l2_block.raw().into(),
-            kv_state: l2_block.kv_state().into_iter().map(|k| k.into()).collect(),
+            kv_state: canonical_kv_state(
+                l2_block.kv_state().into_iter().map(|k| k.into()).collect(),
+            ),
             kv_state_proof: JsonBytes::from_bytes(l2_block.kv_state_proof().unpack()),
-            transactions: l2_block
-                .transactions()
-                .into_iter()
-                .map(|t| t.into())
-                .collect(),
+            transactions: transactions_iter(&l2_block).collect(),
             block_proof: JsonBytes::from_bytes(l2_block.block_proof().unpack()),
             withdrawal_requests: l2_block
                 .withdrawals()
@@ -648,8 +822,14 @@ impl From<packed::GlobalState> for GlobalState {
     }
 }
 
+/// Sum amounts of requests, e.g. deposited sUDT amounts,
+/// returns `ConversionError::AmountOverflow` instead of wrapping around
+pub fn checked_sum_amounts(amounts: &[Uint128]) -> Result<Uint128, ConversionError> {
+    checked_sum(amounts.iter().map(|amount| u128::from(*amount))).map(Into::into)
+}
+
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Default)]
-#[serde(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
 pub struct DepositionRequest {
     pub script: Script,
     pub sudt_script_hash: H256,
@@ -691,6 +871,7 @@ impl From<packed::DepositionRequest> for DepositionRequest {
 #[serde(rename_all = "snake_case")]
 pub struct WithdrawalRequest {
     pub raw: RawWithdrawalRequest,
+    /// See `L2Transaction::signature`
     pub signature: Byte65,
 }
 
@@ -714,7 +895,7 @@ impl From<packed::WithdrawalRequest> for WithdrawalRequest {
 }
 
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Default)]
-#[serde(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
 pub struct RawWithdrawalRequest {
     pub nonce: Uint32,
     pub capacity: Uint64,
@@ -917,16 +1098,406 @@ pub struct RunResult {
     pub return_data: JsonBytes,
     // log data
     pub logs: Vec<LogItem>,
+    // gas used by polyjuice, 0 for non-EVM executions
+    pub gas_used: Uint64,
 }
 
 impl From<offchain::RunResult> for RunResult {
     fn from(data: offchain::RunResult) -> RunResult {
         let offchain::RunResult {
-            return_data, logs, ..
+            return_data,
+            logs,
+            gas_used,
+            ..
         } = data;
         RunResult {
             return_data: JsonBytes::from_vec(return_data),
             logs: logs.into_iter().map(Into::into).collect(),
+            gas_used: gas_used.into(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_l2_transaction_hashes() {
+        let raw = packed::RawL2Transaction::new_builder()
+            .from_id(1u32.pack())
+            .to_id(2u32.pack())
+            .nonce(3u32.pack())
+            .build();
+        let unsigned_tx = packed::L2Transaction::new_builder().raw(raw).build();
+        let signed_tx = unsigned_tx
+            .clone()
+            .as_builder()
+            .signature([1u8; 65].pack())
+            .build();
+
+        let unsigned = L2Transaction::from(unsigned_tx);
+        let signed = L2Transaction::from(signed_tx.clone());
+        // tx hash doesn't commit to the signature
+        assert_eq!(unsigned.tx_hash(), signed.tx_hash());
+        assert_ne!(unsigned.witness_hash(), signed.witness_hash());
+        assert_ne!(signed.tx_hash(), signed.witness_hash());
+
+        let view = L2TransactionView::from(signed_tx);
+        assert_eq!(view.hash, signed.tx_hash());
+        assert_eq!(view.witness_hash, signed.witness_hash());
+    }
+
+    #[test]
+    fn test_molecule_bytes_round_trip() {
+        let raw = packed::RawL2Transaction::new_builder()
+            .from_id(1u32.pack())
+            .to_id(2u32.pack())
+            .nonce(3u32.pack())
+            .args(Bytes::from(vec![4u8; 10]).pack())
+            .build();
+        let tx: L2Transaction = packed::L2Transaction::new_builder()
+            .raw(raw)
+            .signature([5u8; 65].pack())
+            .build()
+            .into();
+        let bytes = tx.to_molecule_bytes();
+        assert_eq!(L2Transaction::from_molecule_bytes(&bytes).unwrap(), tx);
+
+        let kv_pair = packed::KVPair::new_builder()
+            .k([1u8; 32].pack())
+            .v([2u8; 32].pack())
+            .build();
+        let block: L2Block = packed::L2Block::new_builder()
+            .transactions(vec![packed::L2Transaction::try_from(tx).unwrap()].pack())
+            .kv_state(vec![kv_pair].pack())
+            .build()
+            .into();
+        let bytes = block.to_molecule_bytes();
+        assert_eq!(L2Block::from_molecule_bytes(&bytes).unwrap(), block);
+
+        // truncated bytes are rejected
+        assert!(L2Block::from_molecule_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_transactions_iter() {
+        let txs: Vec<packed::L2Transaction> = (0u32..5)
+            .map(|nonce| {
+                let raw = packed::RawL2Transaction::new_builder()
+                    .nonce(nonce.pack())
+                    .build();
+                packed::L2Transaction::new_builder().raw(raw).build()
+            })
+            .collect();
+        let block = packed::L2Block::new_builder()
+            .transactions(txs.pack())
+            .build();
+
+        let eager: Vec<L2Transaction> =
+            block.transactions().into_iter().map(|t| t.into()).collect();
+        let lazy: Vec<L2Transaction> = transactions_iter(&block).collect();
+        assert_eq!(lazy, eager);
+        assert_eq!(lazy, L2Block::from(block.clone()).transactions);
+        assert_eq!(
+            transactions_iter(&block).nth(3).unwrap().raw.nonce,
+            3u32.into()
+        );
+        assert_eq!(transactions_iter(&packed::L2Block::default()).count(), 0);
+    }
+
+    #[test]
+    fn test_size_limits() {
+        let limits = SizeLimits {
+            max_tx_args_size: 16,
+            max_block_txs: 2,
+        };
+        let build_tx = |args_len: usize| L2Transaction {
+            raw: RawL2Transaction {
+                args: JsonBytes::from_vec(vec![0u8; args_len]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // args at and just over the limit
+        assert!(build_tx(16).raw.try_into_packed(&limits).is_ok());
+        assert!(build_tx(16).try_into_packed(&limits).is_ok());
+        let err = build_tx(17).raw.try_into_packed(&limits).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<TooLarge>(),
+            Some(&TooLarge {
+                field: "args",
+                limit: 16,
+                actual: 17
+            })
+        );
+        assert!(build_tx(17).try_into_packed(&limits).is_err());
+
+        // transactions at and just over the limit
+        let block = L2Block {
+            transactions: vec![build_tx(0); 2],
+            ..Default::default()
+        };
+        assert!(block.try_into_packed(&limits).is_ok());
+        let block = L2Block {
+            transactions: vec![build_tx(0); 3],
+            ..Default::default()
+        };
+        let err = block.try_into_packed(&limits).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<TooLarge>(),
+            Some(&TooLarge {
+                field: "transactions",
+                limit: 2,
+                actual: 3
+            })
+        );
+        // an oversized transaction inside the block
+        let block = L2Block {
+            transactions: vec![build_tx(17)],
+            ..Default::default()
+        };
+        assert!(block.try_into_packed(&limits).is_err());
+
+        // verified molecule bytes
+        let block = L2Block {
+            transactions: vec![build_tx(16); 2],
+            ..Default::default()
+        };
+        let bytes = block.to_molecule_bytes();
+        assert_eq!(l2_block_from_verified(&bytes, &limits), Ok(block));
+        let block = L2Block {
+            transactions: vec![build_tx(17)],
+            ..Default::default()
+        };
+        assert_eq!(
+            l2_block_from_verified(&block.to_molecule_bytes(), &limits),
+            Err(ConversionError::TooLarge(TooLarge {
+                field: "args",
+                limit: 16,
+                actual: 17
+            }))
+        );
+        // an inflated total size is rejected before any unpacking
+        let mut inflated = bytes.to_vec();
+        inflated[..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            l2_block_from_verified(&inflated, &limits),
+            Err(ConversionError::InvalidMolecule(_))
+        ));
+        // an inflated field offset is rejected as well
+        let mut inflated = bytes.to_vec();
+        inflated[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            l2_block_from_verified(&inflated, &limits),
+            Err(ConversionError::InvalidMolecule(_))
+        ));
+
+        // default limits
+        let tx = build_tx(DEFAULT_MAX_TX_ARGS_SIZE);
+        assert!(packed::L2Transaction::try_from(tx).is_ok());
+        let tx = build_tx(DEFAULT_MAX_TX_ARGS_SIZE + 1);
+        assert!(packed::L2Transaction::try_from(tx).is_err());
+    }
+
+    #[test]
+    fn test_deny_unknown_fields_of_requests() {
+        let raw_tx = serde_json::json!({
+            "from_id": "0x1",
+            "to_id": "0x2",
+            "nonce": "0x3",
+            "args": "0x",
+        });
+        assert!(serde_json::from_value::<RawL2Transaction>(raw_tx.clone()).is_ok());
+        let typo = serde_json::json!({
+            "form_id": "0x1",
+            "to_id": "0x2",
+            "nonce": "0x3",
+            "args": "0x",
+        });
+        assert!(serde_json::from_value::<RawL2Transaction>(typo).is_err());
+
+        let tx = serde_json::json!({ "raw": raw_tx, "signature": Byte65::default() });
+        assert!(serde_json::from_value::<L2Transaction>(tx.clone()).is_ok());
+        let mut unknown = tx;
+        unknown["extra"] = serde_json::json!(1);
+        assert!(serde_json::from_value::<L2Transaction>(unknown).is_err());
+
+        let mut deposit = serde_json::to_value(DepositionRequest::default()).unwrap();
+        deposit["extra"] = serde_json::json!(1);
+        assert!(serde_json::from_value::<DepositionRequest>(deposit).is_err());
+
+        let mut withdrawal = serde_json::to_value(RawWithdrawalRequest::default()).unwrap();
+        withdrawal["extra"] = serde_json::json!(1);
+        assert!(serde_json::from_value::<RawWithdrawalRequest>(withdrawal).is_err());
+    }
+
+    #[test]
+    fn test_tolerate_unknown_fields_of_responses() {
+        let mut receipt = serde_json::to_value(TxReceipt::default()).unwrap();
+        receipt["extra"] = serde_json::json!(1);
+        assert!(serde_json::from_value::<TxReceipt>(receipt).is_ok());
+
+        let mut tx_view = serde_json::to_value(L2TransactionView::default()).unwrap();
+        tx_view["extra"] = serde_json::json!(1);
+        assert!(serde_json::from_value::<L2TransactionView>(tx_view).is_ok());
+    }
+
+    #[test]
+    fn test_byte32_json_h256_byte_order() {
+        let mut bytes = [0u8; 32];
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        let hash = H256(bytes);
+        let byte32 = json_h256_to_byte32(&hash);
+        assert_eq!(byte32.as_slice(), &bytes[..]);
+        assert_eq!(byte32_to_json_h256(&byte32), hash);
+        assert_eq!(
+            serde_json::to_value(&hash).unwrap(),
+            serde_json::json!("0x000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f")
+        );
+
+        // state checkpoint list round trip
+        let raw_block = RawL2Block {
+            state_checkpoint_list: vec![hash.clone(), H256([0xffu8; 32])],
+            ..Default::default()
+        };
+        let packed_block: packed::RawL2Block = raw_block.clone().into();
+        let first_checkpoint = packed_block.state_checkpoint_list().get(0).unwrap();
+        assert_eq!(first_checkpoint.as_slice(), &bytes[..]);
+        assert_eq!(RawL2Block::from(packed_block), raw_block);
+    }
+
+    #[test]
+    fn test_canonical_ordering() {
+        // keys are ordered from the highest byte, like SMT keys
+        let mut low = [0u8; 32];
+        low[0] = 0xff;
+        let mut high = [0u8; 32];
+        high[31] = 1;
+        let hashes = vec![H256(high), H256([0u8; 32]), H256(low)];
+        let sorted = vec![H256([0u8; 32]), H256(low), H256(high)];
+
+        let packed_receipt: packed::TxReceipt =
+            build_tx_receipt(H256::default(), H256::default(), 0, hashes.clone(), vec![]).into();
+        assert_eq!(TxReceipt::from(packed_receipt).read_data_hashes, sorted);
+        let packed_receipt = packed::TxReceipt::new_builder()
+            .read_data_hashes(hashes.iter().map(|hash| hash.0).collect::<Vec<_>>().pack())
+            .build();
+        assert_eq!(TxReceipt::from(packed_receipt).read_data_hashes, sorted);
+
+        let kv_state: Vec<packed::KVPair> = hashes
+            .iter()
+            .map(|k| packed::KVPair::new_builder().k(k.pack()).build())
+            .collect();
+        let block: L2Block = packed::L2Block::new_builder()
+            .kv_state(kv_state.pack())
+            .build()
+            .into();
+        let keys: Vec<H256> = block.kv_state.iter().map(|pair| pair.k.clone()).collect();
+        assert_eq!(keys, sorted);
+
+        let json_block = L2Block {
+            kv_state: hashes
+                .iter()
+                .map(|k| KVPair {
+                    k: k.clone(),
+                    v: H256::default(),
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let packed_block = packed::L2Block::try_from(json_block).unwrap();
+        let keys: Vec<H256> = packed_block
+            .kv_state()
+            .into_iter()
+            .map(|pair| pair.k().unpack())
+            .collect();
+        assert_eq!(keys, sorted);
+    }
+
+    #[test]
+    fn test_build_tx_receipt() {
+        let post_root = H256([3u8; 32]);
+        let post_count = 0x0102_0304u32;
+        let receipt = build_tx_receipt(
+            H256([1u8; 32]),
+            post_root.clone(),
+            post_count,
+            vec![],
+            vec![],
+        );
+        assert_eq!(receipt.post_state.merkle_root, post_root);
+        assert_eq!(u32::from(receipt.post_state.count), post_count);
+
+        let mut expected = [0u8; 32];
+        let mut hasher = gw_common::blake2b::new_blake2b();
+        hasher.update(&[3u8; 32]);
+        hasher.update(&[4, 3, 2, 1]);
+        hasher.finalize(&mut expected);
+        assert_eq!(receipt.compacted_post_account_root(), H256(expected));
+    }
+
+    #[test]
+    fn test_serialize_l2block_streaming() {
+        let build_tx = |nonce: u32| L2Transaction {
+            raw: RawL2Transaction {
+                nonce: nonce.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let block = L2Block {
+            transactions: (0..100).map(build_tx).collect(),
+            ..Default::default()
+        };
+        let mut streamed = Vec::new();
+        serialize_l2block_streaming(&block, &mut streamed).unwrap();
+        assert_eq!(
+            streamed,
+            serde_json::to_string(&block).unwrap().into_bytes()
+        );
+    }
+
+    #[test]
+    fn test_signature_length() {
+        // a JSON signature of 64 or 66 bytes is rejected before packing
+        for &len in &[64usize, 66] {
+            let tx = serde_json::json!({
+                "raw": RawL2Transaction::default(),
+                "signature": format!("0x{}", "01".repeat(len)),
+            });
+            assert!(serde_json::from_value::<L2Transaction>(tx).is_err());
+            let req = serde_json::json!({
+                "raw": RawWithdrawalRequest::default(),
+                "signature": format!("0x{}", "01".repeat(len)),
+            });
+            assert!(serde_json::from_value::<WithdrawalRequest>(req).is_err());
+        }
+        let req = serde_json::json!({
+            "raw": RawWithdrawalRequest::default(),
+            "signature": format!("0x{}", "01".repeat(65)),
+        });
+        let req: WithdrawalRequest = serde_json::from_value(req).unwrap();
+        let packed_req: packed::WithdrawalRequest = req.into();
+        assert_eq!(packed_req.signature().as_slice(), &[1u8; 65][..]);
+    }
+
+    #[test]
+    fn test_checked_sum_amounts() {
+        assert_eq!(checked_sum_amounts(&[]), Ok(Uint128::from(0u128)));
+        // just under and at the max
+        let amounts: Vec<Uint128> = vec![(u128::MAX - 2).into(), 1u128.into()];
+        assert_eq!(checked_sum_amounts(&amounts), Ok((u128::MAX - 1).into()));
+        let amounts: Vec<Uint128> = vec![(u128::MAX - 1).into(), 1u128.into()];
+        assert_eq!(checked_sum_amounts(&amounts), Ok(u128::MAX.into()));
+        // just over the max
+        let amounts: Vec<Uint128> = vec![u128::MAX.into(), 1u128.into()];
+        assert_eq!(
+            checked_sum_amounts(&amounts),
+            Err(ConversionError::AmountOverflow)
+        );
+    }
+}
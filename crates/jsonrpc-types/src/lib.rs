@@ -1,5 +1,17 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+
+// conversions of packed types, keep it no_std
+pub mod packed_conversion;
+
+#[cfg(feature = "std")]
 pub mod blockchain;
+#[cfg(feature = "std")]
 pub mod fixed_bytes;
+#[cfg(feature = "std")]
 pub mod godwoken;
 // re-exports
+#[cfg(feature = "std")]
 pub use ckb_jsonrpc_types;
@@ -0,0 +1,120 @@
+//! Conversions of untrusted bytes into packed types, without the JSON types
+//!
+//! The module doesn't depend on std, on-chain validators use it with `default-features = false`.
+//! The JSON conversions in `godwoken` are built on it.
+
+use core::fmt;
+use gw_types::{packed, prelude::*};
+
+#[cfg(not(feature = "std"))]
+use alloc::string::{String, ToString};
+
+/// Default max size of `RawL2Transaction.args` in bytes
+pub const DEFAULT_MAX_TX_ARGS_SIZE: usize = 128 * 1024;
+/// Default max number of transactions in a `L2Block`
+pub const DEFAULT_MAX_BLOCK_TXS: usize = 10_000;
+
+/// Size limits enforced when converting JSON requests into packed types,
+/// so an oversized request is rejected before any business logic runs
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SizeLimits {
+    pub max_tx_args_size: usize,
+    pub max_block_txs: usize,
+}
+
+impl Default for SizeLimits {
+    fn default() -> Self {
+        SizeLimits {
+            max_tx_args_size: DEFAULT_MAX_TX_ARGS_SIZE,
+            max_block_txs: DEFAULT_MAX_BLOCK_TXS,
+        }
+    }
+}
+
+impl SizeLimits {
+    pub(crate) fn check(field: &'static str, limit: usize, actual: usize) -> Result<(), TooLarge> {
+        if actual > limit {
+            return Err(TooLarge {
+                field,
+                limit,
+                actual,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// A field exceeds its size limit
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TooLarge {
+    pub field: &'static str,
+    pub limit: usize,
+    pub actual: usize,
+}
+
+impl fmt::Display for TooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} is too large, limit: {} actual: {}",
+            self.field, self.limit, self.actual
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TooLarge {}
+
+/// Error of converting untrusted molecule bytes
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ConversionError {
+    /// The bytes don't pass the molecule verification
+    InvalidMolecule(String),
+    TooLarge(TooLarge),
+    /// The sum of amounts overflows u128
+    AmountOverflow,
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversionError::InvalidMolecule(err) => write!(f, "invalid molecule bytes {}", err),
+            ConversionError::TooLarge(err) => fmt::Display::fmt(err, f),
+            ConversionError::AmountOverflow => write!(f, "amount overflow"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ConversionError {}
+
+impl From<TooLarge> for ConversionError {
+    fn from(err: TooLarge) -> Self {
+        ConversionError::TooLarge(err)
+    }
+}
+
+/// Verify untrusted molecule bytes of a block and check the size limits,
+/// the limits are checked on the reader, so nothing is unpacked before
+/// the declared lengths are known to be sane
+pub fn verify_l2block(
+    bytes: &[u8],
+    limits: &SizeLimits,
+) -> Result<packed::L2Block, ConversionError> {
+    let block = packed::L2BlockReader::from_slice(bytes)
+        .map_err(|err| ConversionError::InvalidMolecule(err.to_string()))?;
+    let txs = block.transactions();
+    SizeLimits::check("transactions", limits.max_block_txs, txs.len())?;
+    for tx in txs.iter() {
+        SizeLimits::check("args", limits.max_tx_args_size, tx.raw().args().len())?;
+    }
+    Ok(block.to_entity())
+}
+
+/// Sum amounts, returns `ConversionError::AmountOverflow` instead of wrapping around
+pub fn checked_sum<I: IntoIterator<Item = u128>>(amounts: I) -> Result<u128, ConversionError> {
+    amounts
+        .into_iter()
+        .try_fold(0u128, |sum, amount| sum.checked_add(amount))
+        .ok_or(ConversionError::AmountOverflow)
+}
@@ -14,7 +14,7 @@
 
 use anyhow::{anyhow, Result};
 use gw_common::{builtins::CKB_SUDT_ACCOUNT_ID, state::State, H256};
-use gw_generator::Generator;
+use gw_generator::{ensure_running, Generator};
 use gw_store::{
     chain_view::ChainView,
     state_db::{StateDBTransaction, StateDBVersion},
@@ -147,8 +147,19 @@ impl MemPool {
             .map_err(|err| anyhow!("err: {}", err))
     }
 
+    /// Reject new txs and withdrawals if the rollup of the tip block isn't running
+    fn ensure_chain_running(&self) -> Result<()> {
+        let tip_block_hash = self.store.get_tip_block_hash()?;
+        if let Some(global_state) = self.store.get_block_post_global_state(&tip_block_hash)? {
+            ensure_running(&global_state)?;
+        }
+        Ok(())
+    }
+
     /// Push a layer2 tx into pool
     pub fn push_transaction(&mut self, tx: L2Transaction) -> Result<()> {
+        self.ensure_chain_running()?;
+
         // check duplication
         let tx_hash: H256 = tx.raw().hash().into();
         if self.all_txs.contains_key(&tx_hash) {
@@ -252,6 +263,8 @@ impl MemPool {
 
     /// Push a withdrawal request into pool
     pub fn push_withdrawal_request(&mut self, withdrawal: WithdrawalRequest) -> Result<()> {
+        self.ensure_chain_running()?;
+
         // check withdrawal size
         if withdrawal.as_slice().len() > MAX_WITHDRAWAL_SIZE {
             return Err(anyhow!("withdrawal over size"));
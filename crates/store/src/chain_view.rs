@@ -3,6 +3,7 @@
 use gw_common::H256;
 use gw_db::error::Error;
 use gw_traits::ChainStore;
+use gw_types::packed::L2BlockCommittedInfo;
 
 use crate::transaction::StoreTransaction;
 
@@ -38,6 +39,13 @@ impl<'db> ChainStore for ChainView<'db> {
             .to_string()
             .into())
     }
+
+    fn get_block_committed_info(
+        &self,
+        block_hash: &H256,
+    ) -> Result<Option<L2BlockCommittedInfo>, Error> {
+        self.db.get_l2block_committed_info(block_hash)
+    }
 }
 
 fn is_number_in_a_valid_range(tip_number: u64, number: u64) -> bool {
@@ -1,8 +1,13 @@
 //! State DB
 
 use crate::{smt_store_impl::SMTStore, traits::KVStore, transaction::StoreTransaction};
-use anyhow::{anyhow, Result};
-use gw_common::{error::Error as StateError, smt::SMT, state::State, H256};
+use gw_common::{
+    error::Error as CommonError,
+    smt::SMT,
+    sparse_merkle_tree::{traits::Store as SMTStoreTrait, tree::NodeType},
+    state::State,
+    H256,
+};
 use gw_db::schema::{
     Col, COLUMN_ACCOUNT_SMT_BRANCH, COLUMN_ACCOUNT_SMT_LEAF, COLUMN_DATA, COLUMN_SCRIPT,
 };
@@ -13,10 +18,28 @@ use gw_types::{
     packed::{self, AccountMerkleState, TransactionKey},
     prelude::*,
 };
-use std::{cell::RefCell, collections::HashSet, fmt, mem::size_of_val};
+use parking_lot::Mutex;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fmt,
+    mem::size_of_val,
+};
+use thiserror::Error as ThisError;
 
 const FLAG_DELETE_VALUE: u8 = 0;
 
+#[derive(ThisError, Debug, Clone)]
+pub enum StateError {
+    #[error("{0}")]
+    State(#[from] CommonError),
+    #[error("{0}")]
+    Store(#[from] Error),
+    #[error("block isn't exist")]
+    MissingBlock,
+    #[error("Invalid tx index")]
+    InvalidTxIndex,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum StateDBVersion {
     Genesis,
@@ -33,15 +56,13 @@ impl StateDBVersion {
         db: &StoreTransaction,
         block_hash: H256,
         tx_index: Option<u32>,
-    ) -> Result<Self> {
-        let block = db
-            .get_block(&block_hash)?
-            .ok_or_else(|| anyhow!("block isn't exist"))?;
+    ) -> Result<Self, StateError> {
+        let block = db.get_block(&block_hash)?.ok_or(StateError::MissingBlock)?;
         let block_number: u64 = block.raw().number().unpack();
         let tx_index =
             tx_index.unwrap_or_else(|| block.transactions().len().saturating_sub(1) as u32);
         if tx_index != 0 && tx_index as usize >= block.transactions().len() {
-            return Err(anyhow!("Invalid tx index"));
+            return Err(StateError::InvalidTxIndex);
         }
         Ok(StateDBVersion::HistoryState {
             block_number,
@@ -285,7 +306,9 @@ impl<'db> StateDBTransaction<'db> {
 
 /// Tracker state changes
 pub struct StateTracker {
-    touched_keys: Option<RefCell<HashSet<H256>>>,
+    touched_keys: Option<Mutex<HashSet<H256>>>,
+    read_keys: Option<Mutex<HashSet<H256>>>,
+    read_data_hashes: Option<Mutex<HashSet<H256>>>,
 }
 
 impl Default for StateTracker {
@@ -296,7 +319,11 @@ impl Default for StateTracker {
 
 impl StateTracker {
     pub fn new() -> Self {
-        StateTracker { touched_keys: None }
+        StateTracker {
+            touched_keys: None,
+            read_keys: None,
+            read_data_hashes: None,
+        }
     }
 
     /// Enable state tracking
@@ -304,26 +331,107 @@ impl StateTracker {
         if self.touched_keys.is_none() {
             self.touched_keys = Some(Default::default())
         }
+        if self.read_keys.is_none() {
+            self.read_keys = Some(Default::default())
+        }
+        if self.read_data_hashes.is_none() {
+            self.read_data_hashes = Some(Default::default())
+        }
     }
 
-    /// Return touched keys
-    pub fn touched_keys(&self) -> Option<&RefCell<HashSet<H256>>> {
+    /// Return touched keys, include both read and written keys
+    pub fn touched_keys(&self) -> Option<&Mutex<HashSet<H256>>> {
         self.touched_keys.as_ref()
     }
 
+    /// Return read keys
+    pub fn read_keys(&self) -> Option<&Mutex<HashSet<H256>>> {
+        self.read_keys.as_ref()
+    }
+
+    /// Record a read key in the tracker
+    pub fn touch_read_key(&self, key: &H256) {
+        if let Some(read_keys) = self.read_keys.as_ref() {
+            read_keys.lock().insert(*key);
+        }
+        self.touch_key(key);
+    }
+
     /// Record a key in the tracker
     pub fn touch_key(&self, key: &H256) {
         if let Some(touched_keys) = self.touched_keys.as_ref() {
-            touched_keys.borrow_mut().insert(*key);
+            touched_keys.lock().insert(*key);
+        }
+    }
+
+    /// Record the hash of a data read from the code store
+    pub fn touch_data_hash(&self, data_hash: &H256) {
+        if let Some(read_data_hashes) = self.read_data_hashes.as_ref() {
+            read_data_hashes.lock().insert(*data_hash);
         }
     }
+
+    /// Take hashes of the data read since the last call, sorted ascending like the
+    /// `read_data_hashes` of a `TxReceipt`, returns empty if the tracking isn't enabled
+    pub fn take_read_data_hashes(&self) -> Vec<H256> {
+        let mut hashes: Vec<H256> = match self.read_data_hashes.as_ref() {
+            Some(read_data_hashes) => read_data_hashes.lock().drain().collect(),
+            None => return Vec::new(),
+        };
+        hashes.sort_unstable();
+        hashes
+    }
+}
+
+/// A bounded cache of scripts and data read through a `StateTree`,
+/// the oldest entry is evicted when the capacity is reached.
+pub(crate) struct CodeCache {
+    capacity: usize,
+    entries: HashMap<(Col, H256), Bytes>,
+    queue: VecDeque<(Col, H256)>,
 }
 
+impl CodeCache {
+    fn new(capacity: usize) -> Self {
+        CodeCache {
+            capacity,
+            entries: HashMap::with_capacity(capacity),
+            queue: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub(crate) fn contains(&self, col: Col, hash: &H256) -> bool {
+        self.entries.contains_key(&(col, *hash))
+    }
+
+    fn get(&self, col: Col, hash: &H256) -> Option<Bytes> {
+        self.entries.get(&(col, *hash)).cloned()
+    }
+
+    fn insert(&mut self, col: Col, hash: H256, code: Bytes) {
+        if self.capacity == 0 || self.entries.insert((col, hash), code).is_some() {
+            return;
+        }
+        self.queue.push_back((col, hash));
+        if self.queue.len() > self.capacity {
+            if let Some(evicted) = self.queue.pop_front() {
+                self.entries.remove(&evicted);
+            }
+        }
+    }
+}
+
+/// The account state of a `StateDBTransaction`
+///
+/// Reads take `&self` and record read keys in the `Mutex`es of the tracker, so a `StateTree`
+/// is `Sync` and threads reading concurrently (e.g. RPC handlers) can share one, writes require `&mut self`.
 pub struct StateTree<'a, 'db> {
     tree: SMT<SMTStore<'a, StateDBTransaction<'db>>>,
     account_count: u32,
     db: &'a StateDBTransaction<'db>,
     tracker: StateTracker,
+    dirty_keys: Option<HashMap<H256, H256>>,
+    pub(crate) code_cache: Option<Mutex<CodeCache>>,
 }
 
 impl<'a, 'db> StateTree<'a, 'db> {
@@ -337,13 +445,190 @@ impl<'a, 'db> StateTree<'a, 'db> {
             db,
             account_count,
             tracker: StateTracker::new(),
+            dirty_keys: None,
+            code_cache: None,
         }
     }
 
+    /// Cache at most `capacity` scripts and data read or written through the `CodeStore` of this tree,
+    /// the oldest entry is evicted when the capacity is reached. Missing scripts and data aren't cached.
+    pub fn enable_code_cache(&mut self, capacity: usize) {
+        self.code_cache = Some(Mutex::new(CodeCache::new(capacity)));
+    }
+
+    fn get_code(&self, col: Col, hash: &H256) -> Option<Bytes> {
+        if let Some(cache) = self.code_cache.as_ref() {
+            if let Some(code) = cache.lock().get(col, hash) {
+                return Some(code);
+            }
+        }
+        let code = Bytes::from(self.db.get(col, hash.as_slice())?.to_vec());
+        self.cache_code(col, *hash, code.clone());
+        Some(code)
+    }
+
+    fn cache_code(&self, col: Col, hash: H256, code: Bytes) {
+        if let Some(cache) = self.code_cache.as_ref() {
+            cache.lock().insert(col, hash, code);
+        }
+    }
+
+    /// Record keys changed by `update_raw` from now on, see `take_dirty_keys`
+    pub fn enable_dirty_tracking(&mut self) {
+        if self.dirty_keys.is_none() {
+            self.dirty_keys = Some(Default::default());
+        }
+    }
+
+    /// Take keys changed since the last call with their latest values, sorted in the SMT key
+    /// order (the `Ord` of `H256`) which is the order `kv_state` is compiled in, returns empty if the dirty tracking isn't enabled.
+    ///
+    /// A write is recorded only if it changes the current value, re-writing the same value
+    /// isn't. A key changed and then written back to its original value is still recorded.
+    pub fn take_dirty_keys(&mut self) -> Vec<(H256, H256)> {
+        let mut kvs: Vec<(H256, H256)> = match self.dirty_keys.as_mut() {
+            Some(dirty_keys) => dirty_keys.drain().collect(),
+            None => return Vec::new(),
+        };
+        kvs.sort_unstable_by_key(|(k, _)| *k);
+        kvs
+    }
+
     pub fn tracker_mut(&mut self) -> &mut StateTracker {
         &mut self.tracker
     }
 
+    /// Take hashes of the data read through `CodeStore::get_data`,
+    /// see `StateTracker::take_read_data_hashes`
+    pub fn take_read_data_hashes(&self) -> Vec<H256> {
+        self.tracker.take_read_data_hashes()
+    }
+
+    /// Return the first `limit` key-values of the current state whose raw keys start with `prefix`,
+    /// e.g. use `build_account_field_key(id, AccountField::Nonce)[..4]` to list fields of an account.
+    ///
+    /// Results are sorted by the bytes of the keys, so "first" is in byte order, unlike the SMT
+    /// key order (the `Ord` of `H256`, comparing from the last byte) used by `take_dirty_keys` and `kv_state`.
+    ///
+    /// Notice: SMT leaves are indexed by leaf hash rather than key, so this function scans
+    /// every leaf ever stored (including history versions) and checks it against the current tree,
+    /// the cost is O(number of stored leaves), do not call it in a hot path.
+    /// At most `limit` candidates are kept during the scan.
+    pub fn get_kv_range(
+        &self,
+        prefix: &[u8],
+        limit: usize,
+    ) -> Result<Vec<(H256, H256)>, CommonError> {
+        let mut kvs: BTreeMap<[u8; 32], H256> = BTreeMap::new();
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        for (_raw_key, value) in self
+            .db
+            .get_iter(COLUMN_ACCOUNT_SMT_LEAF, IteratorMode::Start)
+        {
+            // skip deleted leaves
+            let leaf = match packed::SMTLeafNodeReader::from_slice(&value) {
+                Ok(leaf) => leaf,
+                Err(_) => continue,
+            };
+            let key: [u8; 32] = leaf.key().unpack();
+            if !key.starts_with(prefix) || kvs.contains_key(&key) {
+                continue;
+            }
+            // once `limit` candidates are kept, only a smaller key can change the result
+            if kvs.len() >= limit && kvs.keys().next_back().map_or(false, |last| key > *last) {
+                continue;
+            }
+            let value = self.tree.get(&key.into())?;
+            if value.is_zero() {
+                continue;
+            }
+            kvs.insert(key, value);
+            if kvs.len() > limit {
+                let last = *kvs.keys().next_back().expect("exceeds limit");
+                kvs.remove(&last);
+            }
+        }
+        Ok(kvs.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
+
+    /// Return hashes of the scripts readable at the version of the underlying `StateDBTransaction`,
+    /// sorted by bytes. Like `get_kv_range` it scans every stored version, do not call it in a hot path.
+    pub fn get_script_hashes(&self) -> Vec<H256> {
+        self.get_stored_hashes(COLUMN_SCRIPT)
+    }
+
+    /// Return hashes of the data readable at the version of the underlying `StateDBTransaction`,
+    /// see `get_script_hashes`
+    pub fn get_data_hashes(&self) -> Vec<H256> {
+        self.get_stored_hashes(COLUMN_DATA)
+    }
+
+    fn get_stored_hashes(&self, col: Col) -> Vec<H256> {
+        let mut hashes: Vec<H256> = Vec::new();
+        for (raw_key, _value) in self.db.get_iter(col, IteratorMode::Start) {
+            // raw keys are `hash | block_number | tx_index`, so versions of a hash are adjacent
+            if raw_key.len() < 32 {
+                continue;
+            }
+            let mut hash = [0u8; 32];
+            hash.copy_from_slice(&raw_key[..32]);
+            let hash: H256 = hash.into();
+            if hashes.last() != Some(&hash) && self.db.get(col, hash.as_slice()).is_some() {
+                hashes.push(hash);
+            }
+        }
+        hashes
+    }
+
+    /// Read the current values of `keys` and prove them against the current root,
+    /// returns the compiled proof and the `(key, value)` leaves it proves.
+    /// The proof is empty if no key is given.
+    pub fn prove_keys(&self, keys: &[H256]) -> Result<(Vec<u8>, Vec<(H256, H256)>), CommonError> {
+        let leaves = keys
+            .iter()
+            .map(|key| Ok((*key, self.tree.get(key)?)))
+            .collect::<Result<Vec<(H256, H256)>, CommonError>>()?;
+        if leaves.is_empty() {
+            return Ok((Vec::new(), leaves));
+        }
+        let proof = self
+            .tree
+            .merkle_proof(keys.to_vec())?
+            .compile(leaves.clone())?;
+        Ok((proof.0, leaves))
+    }
+
+    /// Return the number of branch nodes traversed from the root to reach the leaf of `key`,
+    /// a key sharing a long prefix with other keys is deeper than a key in a sparse subtree.
+    ///
+    /// For an absent key it is the depth at which the lookup stops, 0 for an empty tree.
+    pub fn key_branch_depth(&self, key: &H256) -> Result<u8, CommonError> {
+        let store = self.tree.store();
+        let mut node = *self.tree.root();
+        let mut depth = 0u8;
+        while !node.is_zero() {
+            let branch = match store.get_branch(&node)? {
+                Some(branch) => branch,
+                None => break,
+            };
+            depth = depth.saturating_add(1);
+            node = match branch.node_type {
+                NodeType::Pair(left, right) => {
+                    if key.get_bit(branch.fork_height) {
+                        right
+                    } else {
+                        left
+                    }
+                }
+                NodeType::Single(child) if branch.key == *key => child,
+                NodeType::Single(_) => break,
+            };
+        }
+        Ok(depth)
+    }
+
     /// submit tree changes into transaction
     /// notice, this function do not commit the DBTransaction
     pub fn submit_tree(&self) -> Result<(), Error> {
@@ -360,59 +645,82 @@ impl<'a, 'db> StateTree<'a, 'db> {
 }
 
 impl<'a, 'db> State for StateTree<'a, 'db> {
-    fn get_raw(&self, key: &H256) -> Result<H256, StateError> {
-        self.tracker.touch_key(key);
+    fn get_raw(&self, key: &H256) -> Result<H256, CommonError> {
+        self.tracker.touch_read_key(key);
         let v = self.tree.get(key)?;
         Ok(v)
     }
 
-    fn update_raw(&mut self, key: H256, value: H256) -> Result<(), StateError> {
+    fn update_raw(&mut self, key: H256, value: H256) -> Result<(), CommonError> {
         self.tracker.touch_key(&key);
+        if let Some(dirty_keys) = self.dirty_keys.as_mut() {
+            if self.tree.get(&key)? != value {
+                dirty_keys.insert(key, value);
+            }
+        }
         self.tree.update(key, value)?;
         Ok(())
     }
 
-    fn get_account_count(&self) -> Result<u32, StateError> {
+    fn update_raws(&mut self, kvs: Vec<(H256, H256)>) -> Result<(), CommonError> {
+        for (key, value) in &kvs {
+            self.tracker.touch_key(key);
+            if let Some(dirty_keys) = self.dirty_keys.as_mut() {
+                if self.tree.get(key)? != *value {
+                    dirty_keys.insert(*key, *value);
+                }
+            }
+        }
+        self.tree.update_all(kvs)?;
+        Ok(())
+    }
+
+    fn get_account_count(&self) -> Result<u32, CommonError> {
         Ok(self.account_count)
     }
 
-    fn set_account_count(&mut self, count: u32) -> Result<(), StateError> {
+    fn set_account_count(&mut self, count: u32) -> Result<(), CommonError> {
         self.account_count = count;
         Ok(())
     }
 
-    fn calculate_root(&self) -> Result<H256, StateError> {
+    fn calculate_root(&self) -> Result<H256, CommonError> {
         let root = self.tree.root();
         Ok(*root)
     }
+
+    fn account_merkle_state(&self) -> Result<(H256, u32), CommonError> {
+        // read both fields under the same borrow
+        Ok((*self.tree.root(), self.account_count))
+    }
 }
 
+/// Scripts and data are read from and written to the underlying `StoreTransaction` directly,
+/// a `StateTree` doesn't keep its own copy, so creating trees per transaction is cheap.
+/// A bounded read cache is kept only if `enable_code_cache` is called.
 impl<'a, 'db> CodeStore for StateTree<'a, 'db> {
     fn insert_script(&mut self, script_hash: H256, script: packed::Script) {
         self.db
             .insert_raw(COLUMN_SCRIPT, script_hash.as_slice(), script.as_slice())
             .expect("insert script");
+        self.cache_code(COLUMN_SCRIPT, script_hash, script.as_bytes());
     }
 
     fn get_script(&self, script_hash: &H256) -> Option<packed::Script> {
-        match self.db.get(COLUMN_SCRIPT, script_hash.as_slice()) {
-            Some(slice) => {
-                Some(packed::ScriptReader::from_slice_should_be_ok(&slice.as_ref()).to_entity())
-            }
-            None => None,
-        }
+        self.get_code(COLUMN_SCRIPT, script_hash)
+            .map(|code| packed::ScriptReader::from_slice_should_be_ok(&code).to_entity())
     }
 
     fn insert_data(&mut self, data_hash: H256, code: Bytes) {
         self.db
             .insert_raw(COLUMN_DATA, data_hash.as_slice(), &code)
             .expect("insert data");
+        self.cache_code(COLUMN_DATA, data_hash, code);
     }
 
     fn get_data(&self, data_hash: &H256) -> Option<Bytes> {
-        match self.db.get(COLUMN_DATA, data_hash.as_slice()) {
-            Some(slice) => Some(Bytes::from(slice.to_vec())),
-            None => None,
-        }
+        let data = self.get_code(COLUMN_DATA, data_hash)?;
+        self.tracker.touch_data_hash(data_hash);
+        Some(data)
     }
 }
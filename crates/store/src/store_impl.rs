@@ -45,6 +45,7 @@ impl<'a> Store {
     pub fn begin_transaction(&self) -> StoreTransaction {
         StoreTransaction {
             inner: self.db.transaction(),
+            block_number_negative_cache: None,
         }
     }
 
@@ -0,0 +1,91 @@
+use crate::{chain_view::ChainView, traits::KVStore, Store};
+use gw_common::H256;
+use gw_db::schema::COLUMN_INDEX;
+use gw_traits::ChainStore;
+use gw_types::{packed, prelude::*};
+
+fn build_main_chain_index(store: &Store, blocks_count: u64) -> Vec<H256> {
+    let store_txn = store.begin_transaction();
+    let mut block_hashes = Vec::new();
+    for number in 0..blocks_count {
+        let block_hash = {
+            let mut buf = [0u8; 32];
+            buf[..8].copy_from_slice(&(number + 1).to_le_bytes());
+            buf
+        };
+        let block_number: packed::Uint64 = number.pack();
+        store_txn
+            .insert_raw(COLUMN_INDEX, block_number.as_slice(), &block_hash)
+            .unwrap();
+        store_txn
+            .insert_raw(COLUMN_INDEX, &block_hash, block_number.as_slice())
+            .unwrap();
+        block_hashes.push(block_hash.into());
+    }
+    store_txn.commit().unwrap();
+    block_hashes
+}
+
+#[test]
+fn get_block_hashes_by_range() {
+    let store = Store::open_tmp().unwrap();
+    // genesis and five blocks
+    let block_hashes = build_main_chain_index(&store, 6);
+    let tip_block_hash = *block_hashes.last().unwrap();
+
+    let store_txn = store.begin_transaction();
+    let chain_view = ChainView::new(&store_txn, tip_block_hash);
+
+    assert_eq!(
+        chain_view.get_block_hashes_by_range(1, 3).unwrap(),
+        block_hashes[1..=3].to_vec()
+    );
+    assert_eq!(
+        chain_view.get_block_hashes_by_range(2, 4).unwrap(),
+        block_hashes[2..=4].to_vec()
+    );
+    assert_eq!(
+        chain_view.get_block_hashes_by_range(3, 3).unwrap(),
+        vec![block_hashes[3]]
+    );
+
+    // stop at the tip, tip block can't be read from chain view
+    assert_eq!(
+        chain_view.get_block_hashes_by_range(3, 100).unwrap(),
+        block_hashes[3..=4].to_vec()
+    );
+    assert!(chain_view
+        .get_block_hashes_by_range(5, 100)
+        .unwrap()
+        .is_empty());
+
+    // invalid range
+    assert!(chain_view.get_block_hashes_by_range(4, 3).is_err());
+}
+
+#[test]
+fn get_block_committed_info() {
+    let store = Store::open_tmp().unwrap();
+    let block_hashes = build_main_chain_index(&store, 2);
+    let block_hash = block_hashes[0];
+    let committed_info = packed::L2BlockCommittedInfo::new_builder()
+        .number(42u64.pack())
+        .block_hash([1u8; 32].pack())
+        .transaction_hash([2u8; 32].pack())
+        .build();
+
+    let store_txn = store.begin_transaction();
+    store_txn
+        .insert_l2block_committed_info(&block_hash, &committed_info)
+        .unwrap();
+    let chain_view = ChainView::new(&store_txn, block_hashes[1]);
+    let info = chain_view
+        .get_block_committed_info(&block_hash)
+        .unwrap()
+        .expect("committed info");
+    assert_eq!(info.as_slice(), committed_info.as_slice());
+    assert!(chain_view
+        .get_block_committed_info(&block_hashes[1])
+        .unwrap()
+        .is_none());
+}
@@ -1,3 +1,4 @@
+mod chain_view;
 mod state_db;
 mod transaction;
 mod transaction_clear_block_state;
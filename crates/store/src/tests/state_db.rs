@@ -1,14 +1,25 @@
 use crate::{
-    state_db::{StateDBTransaction, StateDBVersion},
+    state_db::{StateDBTransaction, StateDBVersion, StateError},
     traits::KVStore,
     transaction::StoreTransaction,
     Store,
 };
-use gw_common::H256;
+use gw_common::{
+    smt::{Blake2bHasher, CompiledMerkleProof},
+    state::State,
+    H256,
+};
+use gw_db::schema::{COLUMN_DATA, COLUMN_SCRIPT};
+use gw_traits::CodeStore;
 use gw_types::{
-    packed::{GlobalState, L2Block, L2BlockCommittedInfo, L2Transaction, TxReceipt},
+    bytes::Bytes,
+    packed::{
+        AccountMerkleState, GlobalState, L2Block, L2BlockCommittedInfo, L2Transaction, Script,
+        TxReceipt,
+    },
     prelude::*,
 };
+use std::{sync::Arc, thread};
 
 fn get_state_db_from_mock_data(
     db: &StoreTransaction,
@@ -454,3 +465,376 @@ fn insert_special_value_0u8() {
         .insert_raw(1, &[2], &0u8.to_be_bytes())
         .unwrap();
 }
+
+#[test]
+fn missing_block_is_typed_error() {
+    let store = Store::open_tmp().unwrap();
+    let db = store.begin_transaction();
+    let state_db_version = StateDBVersion::from_history_state(&db, H256::zero(), None);
+    assert!(matches!(state_db_version, Err(StateError::MissingBlock)));
+}
+
+#[test]
+fn track_read_and_written_keys() {
+    let store = Store::open_tmp().unwrap();
+    let db = store.begin_transaction();
+    let state_db = StateDBTransaction::from_version(&db, StateDBVersion::from_genesis()).unwrap();
+    let mut tree = state_db.account_state_tree().unwrap();
+    tree.tracker_mut().enable();
+
+    let read_keys: Vec<H256> = (1u8..=3).map(|i| [i; 32].into()).collect();
+    for key in &read_keys {
+        tree.get_raw(key).unwrap();
+    }
+    let written_key: H256 = [4u8; 32].into();
+    tree.update_raw(written_key, [42u8; 32].into()).unwrap();
+
+    let tracked_read_keys = tree.tracker_mut().read_keys().unwrap().lock().clone();
+    assert_eq!(tracked_read_keys.len(), 3);
+    assert!(!tracked_read_keys.contains(&written_key));
+
+    let touched_keys = tree.tracker_mut().touched_keys().unwrap().lock().clone();
+    assert_eq!(touched_keys.len(), 4);
+    for key in read_keys.iter().chain(std::iter::once(&written_key)) {
+        assert!(touched_keys.contains(key));
+    }
+}
+
+#[test]
+fn concurrent_reads_share_a_state_tree() {
+    // spawned threads need 'static borrows, leak the store and transactions of this test
+    let store: &'static Store = Box::leak(Box::new(Store::open_tmp().unwrap()));
+    let db: &'static StoreTransaction = Box::leak(Box::new(store.begin_transaction()));
+    let state_db: &'static StateDBTransaction<'static> = Box::leak(Box::new(
+        StateDBTransaction::from_version(db, StateDBVersion::from_genesis()).unwrap(),
+    ));
+    let mut tree = state_db.account_state_tree().unwrap();
+    let keys: Vec<H256> = (1u8..=16).map(|i| [i; 32].into()).collect();
+    for key in &keys {
+        tree.update_raw(*key, *key).unwrap();
+    }
+    let root = tree.calculate_root().unwrap();
+    tree.tracker_mut().enable();
+
+    let tree = Arc::new(tree);
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let tree = Arc::clone(&tree);
+            let keys = keys.clone();
+            thread::spawn(move || {
+                for _ in 0..100 {
+                    for key in &keys {
+                        assert_eq!(tree.get_raw(key).unwrap(), *key);
+                    }
+                    assert_eq!(tree.calculate_root().unwrap(), root);
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    // reads of every thread are recorded in the shared tracker
+    let mut tree = Arc::try_unwrap(tree).ok().expect("threads are joined");
+    let read_keys = tree.tracker_mut().read_keys().unwrap().lock().clone();
+    assert_eq!(read_keys.len(), keys.len());
+}
+
+#[test]
+fn track_read_data_hashes() {
+    let store = Store::open_tmp().unwrap();
+    let db = store.begin_transaction();
+    let state_db = StateDBTransaction::from_version(&db, StateDBVersion::from_genesis()).unwrap();
+    let mut tree = state_db.account_state_tree().unwrap();
+    let data_hashes: Vec<H256> = (1u8..=3).map(|i| [i; 32].into()).collect();
+    for (i, data_hash) in data_hashes.iter().enumerate() {
+        tree.insert_data(*data_hash, Bytes::from(vec![i as u8; 4]));
+    }
+
+    // reads aren't recorded until the tracker is enabled
+    tree.get_data(&data_hashes[0]).unwrap();
+    assert!(tree.take_read_data_hashes().is_empty());
+
+    tree.tracker_mut().enable();
+    tree.get_data(&data_hashes[2]).unwrap();
+    tree.get_data(&data_hashes[0]).unwrap();
+    tree.get_data(&data_hashes[2]).unwrap();
+    // missing data is not recorded
+    assert!(tree.get_data(&[9u8; 32].into()).is_none());
+    assert_eq!(
+        tree.take_read_data_hashes(),
+        vec![data_hashes[0], data_hashes[2]]
+    );
+    // the recorded hashes are taken
+    assert!(tree.take_read_data_hashes().is_empty());
+}
+
+#[test]
+fn track_dirty_keys() {
+    let store = Store::open_tmp().unwrap();
+    let db = store.begin_transaction();
+    let state_db = StateDBTransaction::from_version(&db, StateDBVersion::from_genesis()).unwrap();
+    let mut tree = state_db.account_state_tree().unwrap();
+    let keys: Vec<H256> = (1u8..=3).map(|i| [i; 32].into()).collect();
+
+    // writes aren't recorded until the dirty tracking is enabled
+    tree.update_raw(keys[0], [10u8; 32].into()).unwrap();
+    assert!(tree.take_dirty_keys().is_empty());
+
+    tree.enable_dirty_tracking();
+    // re-writing the same value isn't a change
+    tree.update_raw(keys[0], [10u8; 32].into()).unwrap();
+    tree.update_raw(keys[2], [1u8; 32].into()).unwrap();
+    tree.update_raw(keys[1], [2u8; 32].into()).unwrap();
+    tree.update_raw(keys[2], [3u8; 32].into()).unwrap();
+    assert_eq!(
+        tree.take_dirty_keys(),
+        vec![(keys[1], [2u8; 32].into()), (keys[2], [3u8; 32].into())]
+    );
+    // the recorded keys are taken
+    assert!(tree.take_dirty_keys().is_empty());
+
+    // deleting a key is a change
+    tree.update_raw(keys[0], H256::zero()).unwrap();
+    assert_eq!(tree.take_dirty_keys(), vec![(keys[0], H256::zero())]);
+
+    // keys are sorted in the SMT key order, the highest byte is compared first
+    let mut low = [0u8; 32];
+    low[0] = 2;
+    low[31] = 1;
+    let mut high = [0u8; 32];
+    high[0] = 1;
+    high[31] = 2;
+    let (low, high): (H256, H256) = (low.into(), high.into());
+    tree.update_raw(high, [4u8; 32].into()).unwrap();
+    tree.update_raw(low, [5u8; 32].into()).unwrap();
+    assert_eq!(
+        tree.take_dirty_keys(),
+        vec![(low, [5u8; 32].into()), (high, [4u8; 32].into())]
+    );
+}
+
+#[test]
+fn prove_keys() {
+    let store = Store::open_tmp().unwrap();
+    let db = store.begin_transaction();
+    let state_db = StateDBTransaction::from_version(&db, StateDBVersion::from_genesis()).unwrap();
+    let mut tree = state_db.account_state_tree().unwrap();
+    for i in 1u8..=4 {
+        tree.update_raw([i; 32].into(), [i + 10; 32].into())
+            .unwrap();
+    }
+    let root = tree.calculate_root().unwrap();
+
+    // an absent key is proved as zero
+    let keys: Vec<H256> = vec![[2u8; 32].into(), [3u8; 32].into(), [9u8; 32].into()];
+    let (proof, leaves) = tree.prove_keys(&keys).unwrap();
+    assert_eq!(
+        leaves,
+        vec![
+            ([2u8; 32].into(), [12u8; 32].into()),
+            ([3u8; 32].into(), [13u8; 32].into()),
+            ([9u8; 32].into(), H256::zero()),
+        ]
+    );
+    let valid = CompiledMerkleProof(proof.clone())
+        .verify::<Blake2bHasher>(&root, leaves)
+        .unwrap();
+    assert!(valid);
+
+    // the proof is stale once a proved value changes
+    tree.update_raw([2u8; 32].into(), [42u8; 32].into())
+        .unwrap();
+    let new_root = tree.calculate_root().unwrap();
+    let (new_proof, new_leaves) = tree.prove_keys(&keys).unwrap();
+    assert!(CompiledMerkleProof(new_proof)
+        .verify::<Blake2bHasher>(&new_root, new_leaves.clone())
+        .unwrap());
+    assert!(!CompiledMerkleProof(proof)
+        .verify::<Blake2bHasher>(&new_root, new_leaves)
+        .unwrap());
+
+    let (proof, leaves) = tree.prove_keys(&[]).unwrap();
+    assert!(proof.is_empty());
+    assert!(leaves.is_empty());
+}
+
+#[test]
+fn get_kv_range() {
+    let store = Store::open_tmp().unwrap();
+    let db = store.begin_transaction();
+    let state_db = StateDBTransaction::from_version(&db, StateDBVersion::from_genesis()).unwrap();
+    let mut tree = state_db.account_state_tree().unwrap();
+
+    let build_key = |prefix: u8, i: u8| {
+        let mut key = [i; 32];
+        key[0] = prefix;
+        H256::from(key)
+    };
+    for i in 1u8..=3 {
+        tree.update_raw(build_key(1, i), [i; 32].into()).unwrap();
+        tree.update_raw(build_key(2, i), [i; 32].into()).unwrap();
+    }
+    // overwrite and delete
+    tree.update_raw(build_key(1, 1), [42u8; 32].into()).unwrap();
+    tree.update_raw(build_key(1, 3), H256::zero()).unwrap();
+
+    let kvs = tree.get_kv_range(&[1], 10).unwrap();
+    assert_eq!(
+        kvs,
+        vec![
+            (build_key(1, 1), [42u8; 32].into()),
+            (build_key(1, 2), [2u8; 32].into())
+        ]
+    );
+    let kvs = tree.get_kv_range(&[2], 10).unwrap();
+    assert_eq!(kvs.len(), 3);
+    assert!(kvs.iter().all(|(k, _)| k.as_slice()[0] == 2));
+    // limit
+    let kvs = tree.get_kv_range(&[2], 1).unwrap();
+    assert_eq!(kvs, vec![(build_key(2, 1), [1u8; 32].into())]);
+    assert!(tree.get_kv_range(&[3], 10).unwrap().is_empty());
+    assert!(tree.get_kv_range(&[2], 0).unwrap().is_empty());
+
+    // the limit keeps the first keys in byte order rather than the SMT order
+    let mut low_bytes_key = [0u8; 32];
+    low_bytes_key[0] = 4;
+    low_bytes_key[31] = 9;
+    let mut high_bytes_key = [0u8; 32];
+    high_bytes_key[0] = 4;
+    high_bytes_key[1] = 1;
+    let (low_bytes_key, high_bytes_key) = (H256::from(low_bytes_key), H256::from(high_bytes_key));
+    assert!(high_bytes_key < low_bytes_key);
+    tree.update_raw(low_bytes_key, [1u8; 32].into()).unwrap();
+    tree.update_raw(high_bytes_key, [2u8; 32].into()).unwrap();
+    let kvs = tree.get_kv_range(&[4], 1).unwrap();
+    assert_eq!(kvs, vec![(low_bytes_key, [1u8; 32].into())]);
+}
+
+#[test]
+fn code_store_is_shared_between_trees() {
+    let store = Store::open_tmp().unwrap();
+    let db = store.begin_transaction();
+    let state_db = StateDBTransaction::from_version(&db, StateDBVersion::from_genesis()).unwrap();
+    let mut tree = state_db.account_state_tree().unwrap();
+
+    let script = Script::new_builder()
+        .args(Bytes::from(vec![42u8; 32]).pack())
+        .build();
+    let script_hash: H256 = script.hash().into();
+    let data = Bytes::from(vec![7u8; 1024]);
+    let data_hash = H256::from([1u8; 32]);
+    tree.insert_script(script_hash, script.clone());
+    tree.insert_data(data_hash, data.clone());
+
+    // a new tree reads through the same transaction instead of a copy
+    let other_tree = state_db.account_state_tree().unwrap();
+    assert_eq!(other_tree.get_script(&script_hash), Some(script));
+    assert_eq!(other_tree.get_data(&data_hash), Some(data));
+    let other_state_db =
+        StateDBTransaction::from_version(&db, StateDBVersion::from_genesis()).unwrap();
+    let other_tree = other_state_db.account_state_tree().unwrap();
+    assert!(other_tree.get_script(&script_hash).is_some());
+    assert!(tree.get_script(&H256::from([2u8; 32])).is_none());
+}
+
+#[test]
+fn code_cache_is_bounded() {
+    let store = Store::open_tmp().unwrap();
+    let db = store.begin_transaction();
+    let state_db = StateDBTransaction::from_version(&db, StateDBVersion::from_genesis()).unwrap();
+    let mut tree = state_db.account_state_tree().unwrap();
+    let data_hashes: Vec<H256> = (1u8..=3).map(|i| [i; 32].into()).collect();
+    for (i, data_hash) in data_hashes.iter().enumerate() {
+        tree.insert_data(*data_hash, Bytes::from(vec![i as u8; 4]));
+    }
+    // nothing is cached by default
+    assert!(tree.code_cache.is_none());
+
+    tree.enable_code_cache(2);
+    for (i, data_hash) in data_hashes.iter().enumerate() {
+        assert_eq!(
+            tree.get_data(data_hash),
+            Some(Bytes::from(vec![i as u8; 4]))
+        );
+    }
+    {
+        // the oldest entry is evicted
+        let cache = tree.code_cache.as_ref().unwrap().lock();
+        assert!(!cache.contains(COLUMN_DATA, &data_hashes[0]));
+        assert!(cache.contains(COLUMN_DATA, &data_hashes[1]));
+        assert!(cache.contains(COLUMN_DATA, &data_hashes[2]));
+    }
+    // reads served by the cache are still tracked
+    tree.tracker_mut().enable();
+    tree.get_data(&data_hashes[2]).unwrap();
+    assert_eq!(tree.take_read_data_hashes(), vec![data_hashes[2]]);
+
+    // written scripts are cached, missing data isn't
+    let script = Script::new_builder()
+        .args(Bytes::from(vec![42u8; 32]).pack())
+        .build();
+    let script_hash: H256 = script.hash().into();
+    tree.insert_script(script_hash, script.clone());
+    let missing_hash: H256 = [9u8; 32].into();
+    assert!(tree.get_data(&missing_hash).is_none());
+    let cache = tree.code_cache.as_ref().unwrap().lock();
+    assert!(cache.contains(COLUMN_SCRIPT, &script_hash));
+    assert!(!cache.contains(COLUMN_DATA, &missing_hash));
+    drop(cache);
+    assert_eq!(tree.get_script(&script_hash), Some(script));
+}
+
+#[test]
+fn account_merkle_state() {
+    let store = Store::open_tmp().unwrap();
+    let db = store.begin_transaction();
+    let state_db = StateDBTransaction::from_version(&db, StateDBVersion::from_genesis()).unwrap();
+    let mut tree = state_db.account_state_tree().unwrap();
+    tree.create_account([1u8; 32].into()).unwrap();
+    tree.create_account([2u8; 32].into()).unwrap();
+
+    let (root, count) = tree.account_merkle_state().unwrap();
+    let expected = AccountMerkleState::new_builder()
+        .merkle_root(tree.calculate_root().unwrap().pack())
+        .count(tree.get_account_count().unwrap().pack())
+        .build();
+    let state = AccountMerkleState::new_builder()
+        .merkle_root(root.pack())
+        .count(count.pack())
+        .build();
+    assert_eq!(state, expected);
+    assert_eq!(count, 2);
+}
+
+#[test]
+fn key_branch_depth() {
+    let store = Store::open_tmp().unwrap();
+    let db = store.begin_transaction();
+    let state_db = StateDBTransaction::from_version(&db, StateDBVersion::from_genesis()).unwrap();
+    let mut tree = state_db.account_state_tree().unwrap();
+    let key = H256::from([0u8; 32]);
+    // flip the bit `height` of the key, forking from the path of the key at the height
+    let fork_key = |height: u8| {
+        let mut buf = [0u8; 32];
+        buf[height as usize / 8] |= 1 << (height % 8);
+        H256::from(buf)
+    };
+    assert_eq!(tree.key_branch_depth(&key).unwrap(), 0);
+
+    // sparse tree, the only other key forks at the top
+    tree.update_raw(key, [1u8; 32].into()).unwrap();
+    tree.update_raw(fork_key(255), [1u8; 32].into()).unwrap();
+    let sparse_depth = tree.key_branch_depth(&key).unwrap();
+    assert!(sparse_depth > 0);
+
+    // denser tree, every key forks from the path of the key at a different height
+    for height in 0u8..8 {
+        tree.update_raw(fork_key(height), [1u8; 32].into()).unwrap();
+    }
+    let dense_depth = tree.key_branch_depth(&key).unwrap();
+    assert!(dense_depth >= sparse_depth + 8);
+    // the key forked at the top stays shallow
+    assert_eq!(tree.key_branch_depth(&fork_key(255)).unwrap(), sparse_depth);
+}
@@ -1,5 +1,18 @@
 use crate::{traits::KVStore, Store};
-use gw_db::{DBRawIterator, Direction::Forward, IteratorMode};
+use gw_common::{smt::Blake2bHasher, H256};
+use gw_db::{
+    schema::{COLUMN_ACCOUNT_SMT_LEAF, COLUMN_BLOCK_STATE_RECORD},
+    DBRawIterator,
+    Direction::Forward,
+    IteratorMode,
+};
+use gw_types::{
+    packed::{
+        GlobalState, L2Block, L2BlockCommittedInfo, L2Transaction, RawL2Block, RawL2Transaction,
+        RollupConfig, TransactionKey, TxReceipt,
+    },
+    prelude::*,
+};
 use std::collections::HashMap;
 
 #[test]
@@ -369,3 +382,392 @@ fn range_search_and_delete() {
 
     assert!(store_txn.get(1, &[6, 0]).is_some());
 }
+
+#[test]
+fn block_number_negative_cache() {
+    let store = Store::open_tmp().unwrap();
+    let mut store_txn = store.begin_transaction();
+    store_txn.enable_block_number_negative_cache(2);
+    store_txn.set_block_smt_root(H256::zero()).unwrap();
+
+    let block = L2Block::new_builder()
+        .raw(RawL2Block::new_builder().number(0u64.pack()).build())
+        .build();
+    let block_hash: H256 = block.hash().into();
+    // query a not-yet-seen block
+    assert_eq!(store_txn.get_block_number(&block_hash).unwrap(), None);
+    assert_eq!(store_txn.get_block_number(&block_hash).unwrap(), None);
+
+    store_txn
+        .attach_block(block, &RollupConfig::default())
+        .unwrap();
+    assert_eq!(store_txn.get_block_number(&block_hash).unwrap(), Some(0));
+
+    // the cache is bounded, evicted hashes are queried from DB again
+    for i in 1u8..=3 {
+        let unknown_hash = H256::from([i; 32]);
+        assert_eq!(store_txn.get_block_number(&unknown_hash).unwrap(), None);
+    }
+    let cache = store_txn.block_number_negative_cache.as_ref().unwrap();
+    assert!(!cache.lock().contains(&H256::from([1u8; 32])));
+    assert!(cache.lock().contains(&H256::from([3u8; 32])));
+}
+
+#[test]
+fn block_merkle_state() {
+    let store = Store::open_tmp().unwrap();
+    let store_txn = store.begin_transaction();
+    store_txn.set_block_smt_root(H256::zero()).unwrap();
+    assert_eq!(store_txn.block_count().unwrap(), 0);
+    assert_eq!(store_txn.block_merkle_state().unwrap(), (H256::zero(), 0));
+
+    let rollup_config = RollupConfig::new_builder()
+        .finality_blocks(100u64.pack())
+        .build();
+    let mut prev_root = H256::zero();
+    for number in 0u64..3 {
+        let block = L2Block::new_builder()
+            .raw(RawL2Block::new_builder().number(number.pack()).build())
+            .build();
+        store_txn.attach_block(block, &rollup_config).unwrap();
+
+        let (root, count) = store_txn.block_merkle_state().unwrap();
+        assert_eq!(count, number + 1);
+        assert_eq!(store_txn.block_count().unwrap(), number + 1);
+        assert_eq!(root, store_txn.get_block_smt_root().unwrap());
+        assert_ne!(root, prev_root);
+        prev_root = root;
+    }
+}
+
+#[test]
+fn block_merkle_proof_multi() {
+    let store = Store::open_tmp().unwrap();
+    let store_txn = store.begin_transaction();
+    store_txn.set_block_smt_root(H256::zero()).unwrap();
+    let rollup_config = RollupConfig::new_builder()
+        .finality_blocks(100u64.pack())
+        .build();
+    let blocks: Vec<L2Block> = (0u64..5)
+        .map(|number| {
+            L2Block::new_builder()
+                .raw(RawL2Block::new_builder().number(number.pack()).build())
+                .build()
+        })
+        .collect();
+    for block in &blocks {
+        store_txn
+            .attach_block(block.clone(), &rollup_config)
+            .unwrap();
+    }
+
+    let numbers = [1u64, 3, 4];
+    let proof = store_txn.block_merkle_proof_multi(&numbers).unwrap();
+    let leaves: Vec<(H256, H256)> = numbers
+        .iter()
+        .map(|number| {
+            let block = &blocks[*number as usize];
+            (block.smt_key().into(), block.hash().into())
+        })
+        .collect();
+    let block_root = store_txn.get_block_smt_root().unwrap();
+    assert!(proof
+        .verify::<Blake2bHasher>(&block_root, leaves.clone())
+        .unwrap());
+    // the proof doesn't prove a different block hash
+    let mut wrong_leaves = leaves;
+    wrong_leaves[1].1 = blocks[2].hash().into();
+    assert!(!proof
+        .verify::<Blake2bHasher>(&block_root, wrong_leaves)
+        .unwrap());
+
+    // blocks not on the main chain can't be proved
+    assert!(store_txn.block_merkle_proof_multi(&[1, 5]).is_err());
+}
+
+#[test]
+fn prune_reverted_blocks() {
+    let store = Store::open_tmp().unwrap();
+    let mut store_txn = store.begin_transaction();
+    store_txn.enable_block_number_negative_cache(16);
+    store_txn.set_block_smt_root(H256::zero()).unwrap();
+    let rollup_config = RollupConfig::new_builder()
+        .finality_blocks(100u64.pack())
+        .build();
+
+    let build_block = |number: u64, timestamp: u64, txs: Vec<L2Transaction>| {
+        let raw = RawL2Block::new_builder()
+            .number(number.pack())
+            .timestamp(timestamp.pack())
+            .build();
+        L2Block::new_builder()
+            .raw(raw)
+            .transactions(txs.pack())
+            .build()
+    };
+    let insert_block = |block: &L2Block| {
+        let receipts = vec![TxReceipt::default(); block.transactions().len()];
+        store_txn
+            .insert_block(
+                block.clone(),
+                L2BlockCommittedInfo::default(),
+                GlobalState::default(),
+                receipts,
+                Vec::new(),
+            )
+            .unwrap();
+    };
+
+    // main chain
+    let main_blocks: Vec<L2Block> = (0..3).map(|n| build_block(n, 0, Vec::new())).collect();
+    for block in &main_blocks {
+        insert_block(block);
+        store_txn
+            .attach_block(block.clone(), &rollup_config)
+            .unwrap();
+    }
+    // reverted blocks
+    let tx = L2Transaction::new_builder()
+        .raw(RawL2Transaction::new_builder().nonce(1u32.pack()).build())
+        .build();
+    let old_reverted = build_block(1, 1, vec![tx]);
+    let new_reverted = build_block(2, 1, Vec::new());
+    let orphan_reverted = build_block(5, 1, Vec::new());
+    insert_block(&old_reverted);
+    insert_block(&new_reverted);
+    insert_block(&orphan_reverted);
+
+    // block state records, block 1 is shared with the main chain
+    let has_state_record = |number: u64| {
+        store_txn
+            .get_iter(COLUMN_BLOCK_STATE_RECORD, IteratorMode::Start)
+            .any(|(key, _)| key[..8] == number.to_be_bytes())
+    };
+    for &number in &[1u64, 5] {
+        store_txn
+            .record_block_state(number, 0, COLUMN_ACCOUNT_SMT_LEAF, &[number as u8])
+            .unwrap();
+    }
+
+    let block_smt_root = store_txn.get_block_smt_root().unwrap();
+    assert_eq!(store_txn.prune_reverted_blocks(2).unwrap(), 1);
+    assert_eq!(store_txn.get_block_smt_root().unwrap(), block_smt_root);
+
+    let old_reverted_hash: H256 = old_reverted.hash().into();
+    assert!(store_txn.get_block(&old_reverted_hash).unwrap().is_none());
+    assert!(store_txn
+        .get_l2block_committed_info(&old_reverted_hash)
+        .unwrap()
+        .is_none());
+    let tx_key = TransactionKey::build_transaction_key(old_reverted.hash().pack(), 0);
+    assert!(store_txn
+        .get_transaction_receipt_by_key(&tx_key)
+        .unwrap()
+        .is_none());
+    // newer reverted block and main chain blocks are kept
+    assert!(store_txn
+        .get_block(&new_reverted.hash().into())
+        .unwrap()
+        .is_some());
+    for block in &main_blocks {
+        assert!(store_txn.get_block(&block.hash().into()).unwrap().is_some());
+    }
+    // nothing left to prune
+    assert_eq!(store_txn.prune_reverted_blocks(2).unwrap(), 0);
+    // the main chain block 1 is in the confirmation window, its records are kept
+    assert!(has_state_record(1));
+
+    // records of a number without main chain block are pruned
+    assert_eq!(store_txn.prune_reverted_blocks(6).unwrap(), 2);
+    assert!(store_txn
+        .get_block(&orphan_reverted.hash().into())
+        .unwrap()
+        .is_none());
+    assert!(!has_state_record(5));
+    assert!(has_state_record(1));
+    assert_eq!(store_txn.get_block_smt_root().unwrap(), block_smt_root);
+    // the pruning doesn't fill the block number negative cache
+    let cache = store_txn.block_number_negative_cache.as_ref().unwrap();
+    for block in &[&old_reverted, &new_reverted, &orphan_reverted] {
+        assert!(!cache.lock().contains(&block.hash().into()));
+    }
+}
+
+#[test]
+fn get_transactions_by_sender() {
+    let store = Store::open_tmp().unwrap();
+    let store_txn = store.begin_transaction();
+    store_txn.set_block_smt_root(H256::zero()).unwrap();
+    let rollup_config = RollupConfig::new_builder()
+        .finality_blocks(100u64.pack())
+        .build();
+
+    let build_tx = |from_id: u32, nonce: u32| {
+        let raw = RawL2Transaction::new_builder()
+            .from_id(from_id.pack())
+            .nonce(nonce.pack())
+            .build();
+        L2Transaction::new_builder().raw(raw).build()
+    };
+    let blocks: Vec<L2Block> = vec![
+        vec![build_tx(2, 0), build_tx(3, 0), build_tx(2, 1)],
+        vec![build_tx(3, 1), build_tx(2, 2)],
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(number, txs)| {
+        let raw = RawL2Block::new_builder()
+            .number((number as u64).pack())
+            .build();
+        L2Block::new_builder()
+            .raw(raw)
+            .transactions(txs.pack())
+            .build()
+    })
+    .collect();
+    for block in &blocks {
+        let receipts = vec![TxReceipt::default(); block.transactions().len()];
+        store_txn
+            .insert_block(
+                block.clone(),
+                L2BlockCommittedInfo::default(),
+                GlobalState::default(),
+                receipts,
+                Vec::new(),
+            )
+            .unwrap();
+        store_txn
+            .attach_block(block.clone(), &rollup_config)
+            .unwrap();
+    }
+
+    let tx_hash = |block: usize, index: usize| -> H256 {
+        blocks[block]
+            .transactions()
+            .get(index)
+            .unwrap()
+            .hash()
+            .into()
+    };
+    assert_eq!(
+        store_txn.get_transactions_by_sender(2, 10).unwrap(),
+        vec![tx_hash(0, 0), tx_hash(0, 2), tx_hash(1, 1)]
+    );
+    assert_eq!(
+        store_txn.get_transactions_by_sender(3, 10).unwrap(),
+        vec![tx_hash(0, 1), tx_hash(1, 0)]
+    );
+    assert_eq!(
+        store_txn.get_transactions_by_sender(2, 2).unwrap(),
+        vec![tx_hash(0, 0), tx_hash(0, 2)]
+    );
+    assert!(store_txn
+        .get_transactions_by_sender(4, 10)
+        .unwrap()
+        .is_empty());
+
+    // transactions of a detached block are removed from the index
+    store_txn.detach_block(&blocks[1], &rollup_config).unwrap();
+    assert_eq!(
+        store_txn.get_transactions_by_sender(3, 10).unwrap(),
+        vec![tx_hash(0, 1)]
+    );
+}
+
+#[test]
+fn block_post_global_state() {
+    let store = Store::open_tmp().unwrap();
+    let store_txn = store.begin_transaction();
+    let block = L2Block::new_builder()
+        .raw(RawL2Block::new_builder().number(1u64.pack()).build())
+        .build();
+    let block_hash: H256 = block.hash().into();
+    assert!(store_txn
+        .get_block_post_global_state(&block_hash)
+        .unwrap()
+        .is_none());
+
+    let global_state = GlobalState::new_builder()
+        .tip_block_hash(block_hash.pack())
+        .status(1u8.into())
+        .build();
+    store_txn
+        .insert_block(
+            block,
+            L2BlockCommittedInfo::default(),
+            global_state.clone(),
+            Vec::new(),
+            Vec::new(),
+        )
+        .unwrap();
+    assert_eq!(
+        store_txn
+            .get_block_post_global_state(&block_hash)
+            .unwrap()
+            .map(|s| s.as_bytes()),
+        Some(global_state.as_bytes())
+    );
+    store_txn.commit().unwrap();
+    assert_eq!(
+        store
+            .get_block_post_global_state(&block_hash)
+            .unwrap()
+            .map(|s| s.as_bytes()),
+        Some(global_state.as_bytes())
+    );
+}
+
+#[test]
+fn handle_l1_reorg() {
+    let store = Store::open_tmp().unwrap();
+    let store_txn = store.begin_transaction();
+    let l1_block_hashes: Vec<H256> = (1u8..=2).map(|i| [i; 32].into()).collect();
+    // block 1 and 3 are committed by the first layer1 block, block 2 by the second one
+    let blocks: Vec<L2Block> = (1u64..=3)
+        .map(|number| {
+            L2Block::new_builder()
+                .raw(RawL2Block::new_builder().number(number.pack()).build())
+                .build()
+        })
+        .collect();
+    for block in &blocks {
+        let number: u64 = block.raw().number().unpack();
+        let l1_block_hash = l1_block_hashes[(number % 2 == 0) as usize];
+        let committed_info = L2BlockCommittedInfo::new_builder()
+            .block_hash(l1_block_hash.pack())
+            .build();
+        store_txn
+            .insert_block(
+                block.clone(),
+                committed_info,
+                GlobalState::default(),
+                Vec::new(),
+                Vec::new(),
+            )
+            .unwrap();
+    }
+
+    assert_eq!(
+        store_txn.handle_l1_reorg(&[l1_block_hashes[0]]).unwrap(),
+        vec![1, 3]
+    );
+    let committed_info = |index: usize| {
+        store_txn
+            .get_l2block_committed_info(&blocks[index].hash().into())
+            .unwrap()
+    };
+    assert!(committed_info(0).is_none());
+    assert!(committed_info(1).is_some());
+    assert!(committed_info(2).is_none());
+    // the blocks are kept for re-processing
+    assert!(store_txn
+        .get_block(&blocks[0].hash().into())
+        .unwrap()
+        .is_some());
+
+    // nothing left to un-confirm
+    assert!(store_txn
+        .handle_l1_reorg(&[l1_block_hashes[0]])
+        .unwrap()
+        .is_empty());
+}
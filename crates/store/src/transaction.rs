@@ -1,12 +1,15 @@
 use crate::{smt_store_impl::SMTStore, traits::KVStore};
-use gw_common::{smt::SMT, CKB_SUDT_SCRIPT_ARGS, H256};
+use gw_common::{
+    smt::{CompiledMerkleProof, SMT},
+    CKB_SUDT_SCRIPT_ARGS, H256,
+};
 use gw_db::schema::{
     Col, COLUMN_BLOCK, COLUMN_BLOCK_DEPOSITION_REQUESTS, COLUMN_BLOCK_GLOBAL_STATE,
     COLUMN_BLOCK_SMT_BRANCH, COLUMN_BLOCK_SMT_LEAF, COLUMN_BLOCK_STATE_RECORD,
     COLUMN_CUSTODIAN_ASSETS, COLUMN_INDEX, COLUMN_L2BLOCK_COMMITTED_INFO, COLUMN_META,
-    COLUMN_TRANSACTION, COLUMN_TRANSACTION_INFO, COLUMN_TRANSACTION_RECEIPT,
-    META_ACCOUNT_SMT_COUNT_KEY, META_ACCOUNT_SMT_ROOT_KEY, META_BLOCK_SMT_ROOT_KEY,
-    META_CHAIN_ID_KEY, META_TIP_BLOCK_HASH_KEY,
+    COLUMN_SENDER_TRANSACTION, COLUMN_TRANSACTION, COLUMN_TRANSACTION_INFO,
+    COLUMN_TRANSACTION_RECEIPT, META_ACCOUNT_SMT_COUNT_KEY, META_ACCOUNT_SMT_ROOT_KEY,
+    META_BLOCK_SMT_ROOT_KEY, META_CHAIN_ID_KEY, META_TIP_BLOCK_HASH_KEY,
 };
 use gw_db::{
     error::Error, iter::DBIter, DBIterator, Direction::Forward, IteratorMode, RocksDBTransaction,
@@ -15,12 +18,57 @@ use gw_types::{
     packed::{self, RollupConfig, TransactionKey},
     prelude::*,
 };
-use std::{borrow::BorrowMut, collections::HashMap};
+use parking_lot::Mutex;
+use std::{
+    borrow::BorrowMut,
+    collections::{HashMap, HashSet, VecDeque},
+};
 
 const NUMBER_OF_CONFIRMATION: u64 = 100;
 
 pub struct StoreTransaction {
     pub(crate) inner: RocksDBTransaction,
+    pub(crate) block_number_negative_cache: Option<Mutex<NegativeCache>>,
+}
+
+/// A bounded set of recently queried keys which are not found,
+/// the oldest key is evicted when the capacity is reached.
+pub(crate) struct NegativeCache {
+    capacity: usize,
+    keys: HashSet<H256>,
+    queue: VecDeque<H256>,
+}
+
+impl NegativeCache {
+    fn new(capacity: usize) -> Self {
+        NegativeCache {
+            capacity,
+            keys: HashSet::with_capacity(capacity),
+            queue: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub(crate) fn contains(&self, key: &H256) -> bool {
+        self.keys.contains(key)
+    }
+
+    fn insert(&mut self, key: H256) {
+        if self.capacity == 0 || !self.keys.insert(key) {
+            return;
+        }
+        self.queue.push_back(key);
+        if self.queue.len() > self.capacity {
+            if let Some(evicted) = self.queue.pop_front() {
+                self.keys.remove(&evicted);
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &H256) {
+        if self.keys.remove(key) {
+            self.queue.retain(|k| k != key);
+        }
+    }
 }
 
 impl KVStore for StoreTransaction {
@@ -55,6 +103,12 @@ impl StoreTransaction {
         self.inner.rollback()
     }
 
+    /// Remember at most `capacity` recently queried block hashes which aren't found by `get_block_number`,
+    /// so repeated lookups of unknown blocks don't hit the DB, a hash is evicted once its block is attached.
+    pub fn enable_block_number_negative_cache(&mut self, capacity: usize) {
+        self.block_number_negative_cache = Some(Mutex::new(NegativeCache::new(capacity)));
+    }
+
     pub fn setup_chain_id(&self, chain_id: H256) -> Result<(), Error> {
         self.insert_raw(COLUMN_META, META_CHAIN_ID_KEY, chain_id.as_slice())?;
         Ok(())
@@ -75,12 +129,52 @@ impl StoreTransaction {
         Ok(())
     }
 
+    /// Return the number of blocks on the main chain, which is the tip block number + 1,
+    /// or 0 if no block is attached yet
+    pub fn block_count(&self) -> Result<u64, Error> {
+        let tip_block_hash: H256 = match self.get(COLUMN_META, META_TIP_BLOCK_HASH_KEY) {
+            Some(slice) => packed::Byte32Reader::from_slice_should_be_ok(&slice.as_ref())
+                .to_entity()
+                .unpack(),
+            None => return Ok(0),
+        };
+        let tip_block_number = self
+            .get_block_number(&tip_block_hash)?
+            .ok_or_else(|| Error::from("tip block number not found".to_string()))?;
+        Ok(tip_block_number + 1)
+    }
+
+    /// Return the block merkle root and block count as a pair, used to build `BlockMerkleState`
+    pub fn block_merkle_state(&self) -> Result<(H256, u64), Error> {
+        let root = self.get_block_smt_root()?;
+        let count = self.block_count()?;
+        Ok((root, count))
+    }
+
     pub fn block_smt(&self) -> Result<SMT<SMTStore<'_, Self>>, Error> {
         let root = self.get_block_smt_root()?;
         let smt_store = SMTStore::new(COLUMN_BLOCK_SMT_LEAF, COLUMN_BLOCK_SMT_BRANCH, self);
         Ok(SMT::new(root, smt_store))
     }
 
+    /// Build one compiled proof of the main chain blocks of `numbers` under the block SMT root,
+    /// the proved leaves are `(RawL2Block::compute_smt_key(number), block_hash)`
+    pub fn block_merkle_proof_multi(&self, numbers: &[u64]) -> Result<CompiledMerkleProof, Error> {
+        let mut leaves = Vec::with_capacity(numbers.len());
+        for number in numbers {
+            let block_hash = self
+                .get_block_hash_by_number(*number)?
+                .ok_or_else(|| Error::from(format!("block {} hash not found", number)))?;
+            let key: H256 = packed::RawL2Block::compute_smt_key(*number).into();
+            leaves.push((key, block_hash));
+        }
+        let block_smt = self.block_smt()?;
+        block_smt
+            .merkle_proof(leaves.iter().map(|(key, _)| *key).collect())
+            .and_then(|proof| proof.compile(leaves))
+            .map_err(|err| Error::from(format!("SMT error {}", err)))
+    }
+
     pub fn get_account_smt_root(&self) -> Result<H256, Error> {
         let slice = self
             .get(COLUMN_META, META_ACCOUNT_SMT_ROOT_KEY)
@@ -140,13 +234,23 @@ impl StoreTransaction {
     }
 
     pub fn get_block_number(&self, block_hash: &H256) -> Result<Option<u64>, Error> {
+        if let Some(cache) = self.block_number_negative_cache.as_ref() {
+            if cache.lock().contains(block_hash) {
+                return Ok(None);
+            }
+        }
         match self.get(COLUMN_INDEX, block_hash.as_slice()) {
             Some(slice) => Ok(Some(
                 packed::Uint64Reader::from_slice_should_be_ok(&slice.as_ref())
                     .to_entity()
                     .unpack(),
             )),
-            None => Ok(None),
+            None => {
+                if let Some(cache) = self.block_number_negative_cache.as_ref() {
+                    cache.lock().insert(*block_hash);
+                }
+                Ok(None)
+            }
         }
     }
 
@@ -175,6 +279,30 @@ impl StoreTransaction {
         }
     }
 
+    /// Returns hashes of main chain transactions sent by `from_id`,
+    /// in the order they are committed, at most `limit` hashes are returned.
+    pub fn get_transactions_by_sender(
+        &self,
+        from_id: u32,
+        limit: usize,
+    ) -> Result<Vec<H256>, Error> {
+        let start_key = SenderTransactionKey::new(from_id, 0, 0);
+        let tx_hashes = self
+            .get_iter(
+                COLUMN_SENDER_TRANSACTION,
+                IteratorMode::From(start_key.as_slice(), Forward),
+            )
+            .take_while(|(key, _value)| SenderTransactionKey::is_same_sender(key, from_id))
+            .take(limit)
+            .map(|(_key, value)| {
+                let mut tx_hash = [0u8; 32];
+                tx_hash.copy_from_slice(&value);
+                H256::from(tx_hash)
+            })
+            .collect();
+        Ok(tx_hashes)
+    }
+
     pub fn get_transaction_receipt(
         &self,
         tx_hash: &H256,
@@ -213,6 +341,48 @@ impl StoreTransaction {
         }
     }
 
+    /// Record the layer1 info of the transaction which committed the layer2 block
+    pub fn insert_l2block_committed_info(
+        &self,
+        block_hash: &H256,
+        committed_info: &packed::L2BlockCommittedInfo,
+    ) -> Result<(), Error> {
+        self.insert_raw(
+            COLUMN_L2BLOCK_COMMITTED_INFO,
+            block_hash.as_slice(),
+            committed_info.as_slice(),
+        )
+    }
+
+    /// Un-confirm layer2 blocks committed by the orphaned layer1 blocks of a layer1 reorg,
+    /// the committed info of these blocks is removed,
+    /// returns the sorted numbers of the blocks which need to be re-processed.
+    pub fn handle_l1_reorg(&self, orphaned_l1_hashes: &[H256]) -> Result<Vec<u64>, Error> {
+        let orphaned: HashSet<H256> = orphaned_l1_hashes.iter().copied().collect();
+        let mut block_hashes = Vec::new();
+        for (block_hash, value) in self.get_iter(COLUMN_L2BLOCK_COMMITTED_INFO, IteratorMode::Start)
+        {
+            let committed_info =
+                packed::L2BlockCommittedInfoReader::from_slice_should_be_ok(&value);
+            let l1_block_hash: H256 = committed_info.block_hash().unpack();
+            if orphaned.contains(&l1_block_hash) {
+                let mut hash = [0u8; 32];
+                hash.copy_from_slice(&block_hash);
+                block_hashes.push(H256::from(hash));
+            }
+        }
+
+        let mut block_numbers = Vec::with_capacity(block_hashes.len());
+        for block_hash in block_hashes {
+            if let Some(block) = self.get_block(&block_hash)? {
+                block_numbers.push(block.raw().number().unpack());
+            }
+            self.delete(COLUMN_L2BLOCK_COMMITTED_INFO, block_hash.as_slice())?;
+        }
+        block_numbers.sort_unstable();
+        Ok(block_numbers)
+    }
+
     pub fn get_block_deposition_requests(
         &self,
         block_hash: &H256,
@@ -272,11 +442,7 @@ impl StoreTransaction {
         debug_assert_eq!(block.transactions().len(), tx_receipts.len());
         let block_hash = block.hash();
         self.insert_raw(COLUMN_BLOCK, &block_hash, block.as_slice())?;
-        self.insert_raw(
-            COLUMN_L2BLOCK_COMMITTED_INFO,
-            &block_hash,
-            committed_info.as_slice(),
-        )?;
+        self.insert_l2block_committed_info(&block_hash.into(), &committed_info)?;
         self.insert_raw(
             COLUMN_BLOCK_GLOBAL_STATE,
             &block_hash,
@@ -392,7 +558,7 @@ impl StoreTransaction {
         let raw_number = raw.number();
         let block_hash = raw.hash();
 
-        // build tx info
+        // build tx info and sender index
         for (index, tx) in block.transactions().into_iter().enumerate() {
             let key = TransactionKey::build_transaction_key(block_hash.pack(), index as u32);
             let info = packed::TransactionInfo::new_builder()
@@ -401,6 +567,12 @@ impl StoreTransaction {
                 .build();
             let tx_hash = tx.hash();
             self.insert_raw(COLUMN_TRANSACTION_INFO, &tx_hash, info.as_slice())?;
+            let sender_key = SenderTransactionKey::new(
+                tx.raw().from_id().unpack(),
+                raw_number.unpack(),
+                index as u32,
+            );
+            self.insert_raw(COLUMN_SENDER_TRANSACTION, sender_key.as_slice(), &tx_hash)?;
         }
 
         // update finalized custodian assets
@@ -449,6 +621,9 @@ impl StoreTransaction {
         // build main chain index
         self.insert_raw(COLUMN_INDEX, raw_number.as_slice(), &block_hash)?;
         self.insert_raw(COLUMN_INDEX, &block_hash, raw_number.as_slice())?;
+        if let Some(cache) = self.block_number_negative_cache.as_ref() {
+            cache.lock().remove(&block_hash.into());
+        }
 
         // update block tree
         let mut block_smt = self.block_smt()?;
@@ -463,15 +638,52 @@ impl StoreTransaction {
         Ok(())
     }
 
+    /// Rebuild the main chain index, the block tree and the tip of a store without blocks,
+    /// from the hashes of the main chain blocks in number order. Used to restore a state snapshot,
+    /// block bodies aren't restored, so only the block merkle state and the index are available.
+    pub fn restore_main_chain(&self, block_hashes: &[H256]) -> Result<(), Error> {
+        let mut leaves = Vec::with_capacity(block_hashes.len());
+        for (number, block_hash) in block_hashes.iter().enumerate() {
+            let block_number: packed::Uint64 = (number as u64).pack();
+            self.insert_raw(COLUMN_INDEX, block_number.as_slice(), block_hash.as_slice())?;
+            self.insert_raw(COLUMN_INDEX, block_hash.as_slice(), block_number.as_slice())?;
+            if let Some(cache) = self.block_number_negative_cache.as_ref() {
+                cache.lock().remove(block_hash);
+            }
+            let key: H256 = packed::RawL2Block::compute_smt_key(number as u64).into();
+            leaves.push((key, *block_hash));
+        }
+
+        self.set_block_smt_root(H256::zero())?;
+        let mut block_smt = self.block_smt()?;
+        block_smt
+            .update_all(leaves)
+            .map_err(|err| Error::from(format!("SMT error {}", err)))?;
+        let root = *block_smt.root();
+        self.set_block_smt_root(root)?;
+        if let Some(tip_block_hash) = block_hashes.last() {
+            self.insert_raw(
+                COLUMN_META,
+                META_TIP_BLOCK_HASH_KEY,
+                tip_block_hash.as_slice(),
+            )?;
+        }
+        Ok(())
+    }
+
     pub fn detach_block(
         &self,
         block: &packed::L2Block,
         rollup_config: &RollupConfig,
     ) -> Result<(), Error> {
-        // remove transaction info
-        for tx in block.transactions().into_iter() {
+        // remove transaction info and sender index
+        let block_number: u64 = block.raw().number().unpack();
+        for (index, tx) in block.transactions().into_iter().enumerate() {
             let tx_hash = tx.hash();
             self.delete(COLUMN_TRANSACTION_INFO, &tx_hash)?;
+            let sender_key =
+                SenderTransactionKey::new(tx.raw().from_id().unpack(), block_number, index as u32);
+            self.delete(COLUMN_SENDER_TRANSACTION, sender_key.as_slice())?;
         }
 
         let block_hash: H256 = block.hash().into();
@@ -557,6 +769,60 @@ impl StoreTransaction {
         Ok(())
     }
 
+    /// Remove bodies of reverted blocks whose number is below `older_than`,
+    /// returns the number of pruned blocks.
+    ///
+    /// A reverted block is a block stored by `insert_block` but not on the main chain,
+    /// it has already been removed from the block SMT by `detach_block`,
+    /// so the pruning doesn't change any SMT root.
+    ///
+    /// Block state records are keyed by block number and shared with the main chain block of
+    /// the same number, they're cleared only if the main chain doesn't need them anymore:
+    /// there is no main chain block of the number, or the number is out of the confirmation window.
+    pub fn prune_reverted_blocks(&self, older_than: u64) -> Result<usize, Error> {
+        let mut reverted_blocks = Vec::new();
+        for (block_hash, value) in self.get_iter(COLUMN_BLOCK, IteratorMode::Start) {
+            let block = packed::L2BlockReader::from_slice_should_be_ok(&value).to_entity();
+            let block_number: u64 = block.raw().number().unpack();
+            if block_number >= older_than {
+                continue;
+            }
+            // query the index directly, unknown hashes would fill the negative cache
+            if self.get(COLUMN_INDEX, &block_hash).is_none() {
+                reverted_blocks.push(block);
+            }
+        }
+        if reverted_blocks.is_empty() {
+            return Ok(0);
+        }
+
+        for block in &reverted_blocks {
+            let block_hash = block.hash();
+            for index in 0..block.transactions().len() {
+                let key = TransactionKey::build_transaction_key(block_hash.pack(), index as u32);
+                self.delete(COLUMN_TRANSACTION, &key.as_slice())?;
+                self.delete(COLUMN_TRANSACTION_RECEIPT, &key.as_slice())?;
+            }
+            self.delete(COLUMN_BLOCK_DEPOSITION_REQUESTS, &block_hash)?;
+            self.delete(COLUMN_BLOCK_GLOBAL_STATE, &block_hash)?;
+            self.delete(COLUMN_L2BLOCK_COMMITTED_INFO, &block_hash)?;
+            self.delete(COLUMN_BLOCK, &block_hash)?;
+        }
+
+        let tip_number: u64 = self.get_tip_block()?.raw().number().unpack();
+        let numbers: HashSet<u64> = reverted_blocks
+            .iter()
+            .map(|block| block.raw().number().unpack())
+            .collect();
+        for number in numbers {
+            let out_of_window = tip_number > number.saturating_add(NUMBER_OF_CONFIRMATION);
+            if out_of_window || self.get_block_hash_by_number(number)?.is_none() {
+                self.clear_block_state_record(number)?;
+            }
+        }
+        Ok(reverted_blocks.len())
+    }
+
     pub fn record_block_state(
         &self,
         block_number: u64,
@@ -617,6 +883,27 @@ struct CustodianChange {
     amount: u128,
 }
 
+// from_id(4 bytes) | block_number(8 bytes) | tx_index(4 bytes)
+struct SenderTransactionKey([u8; 16]);
+
+impl SenderTransactionKey {
+    fn new(from_id: u32, block_number: u64, tx_index: u32) -> Self {
+        let mut key = [0u8; 16];
+        key[..4].copy_from_slice(&from_id.to_be_bytes());
+        key[4..12].copy_from_slice(&block_number.to_be_bytes());
+        key[12..].copy_from_slice(&tx_index.to_be_bytes());
+        SenderTransactionKey(key)
+    }
+
+    fn is_same_sender(key: &[u8], from_id: u32) -> bool {
+        key.len() == 16 && key[..4] == from_id.to_be_bytes()
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+}
+
 // block_number(8 bytes) | tx_index(4 bytes) | col (1 byte) | key (n bytes)
 struct BlockStateRecordKey(Vec<u8>);
 
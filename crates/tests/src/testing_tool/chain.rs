@@ -102,10 +102,8 @@ pub fn setup_chain_with_account_lock_manage(
     };
     let genesis_committed_info = L2BlockCommittedInfo::default();
     let backend_manage = build_backend_manage(&rollup_config);
-    let rollup_context = RollupContext {
-        rollup_script_hash: rollup_script_hash.into(),
-        rollup_config: rollup_config.clone(),
-    };
+    let rollup_context =
+        RollupContext::new_unchecked(rollup_script_hash.into(), rollup_config.clone());
     let generator = Arc::new(Generator::new(
         backend_manage,
         account_lock_manage,
@@ -306,7 +306,7 @@ fn test_layer1_revert() {
     let default_eoa_code_hash = chain
         .generator()
         .rollup_context()
-        .rollup_config
+        .rollup_config()
         .allowed_eoa_type_hashes()
         .get(0)
         .expect("get default EoA hash");
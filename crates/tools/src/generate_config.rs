@@ -128,6 +128,8 @@ pub fn generate_config(
     let chain: ChainConfig = ChainConfig {
         genesis_committed_info,
         rollup_type_script,
+        // TODO: set to the id of the polyjuice creator account once it's created
+        polyjuice_chain_id: None,
     };
     let rpc_client: RPCClientConfig = RPCClientConfig {
         indexer_url,
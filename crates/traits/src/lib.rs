@@ -1,6 +1,10 @@
 use gw_common::H256;
 use gw_db::error::Error as DBError;
-use gw_types::{bytes::Bytes, packed::Script};
+use gw_types::{
+    bytes::Bytes,
+    packed::{L2BlockCommittedInfo, Script},
+};
+use std::collections::HashMap;
 
 pub trait CodeStore {
     fn insert_script(&mut self, script_hash: H256, script: Script);
@@ -9,6 +13,77 @@ pub trait CodeStore {
     fn get_data(&self, data_hash: &H256) -> Option<Bytes>;
 }
 
+/// A `CodeStore` backed by memory, useful in tests which don't need a state
+///
+/// ```
+/// use gw_common::H256;
+/// use gw_traits::{CodeStore, InMemoryCodeStore};
+/// use gw_types::{packed::Script, prelude::*};
+///
+/// let script = Script::new_builder().code_hash([1u8; 32].pack()).build();
+/// let script_hash: H256 = script.hash().into();
+/// let code_store = InMemoryCodeStore::default().with_script(script.clone());
+/// assert_eq!(code_store.get_script(&script_hash), Some(script));
+/// assert_eq!(code_store.get_script(&H256::zero()), None);
+/// ```
+#[derive(Default, Clone)]
+pub struct InMemoryCodeStore {
+    scripts: HashMap<H256, Script>,
+    data: HashMap<H256, Bytes>,
+}
+
+impl InMemoryCodeStore {
+    /// Preload a script, the key is the hash of the script
+    pub fn with_script(mut self, script: Script) -> Self {
+        self.scripts.insert(script.hash().into(), script);
+        self
+    }
+
+    /// Preload data under `data_hash`
+    pub fn with_data(mut self, data_hash: H256, data: Bytes) -> Self {
+        self.data.insert(data_hash, data);
+        self
+    }
+}
+
+impl CodeStore for InMemoryCodeStore {
+    fn insert_script(&mut self, script_hash: H256, script: Script) {
+        self.scripts.insert(script_hash, script);
+    }
+    fn get_script(&self, script_hash: &H256) -> Option<Script> {
+        self.scripts.get(script_hash).cloned()
+    }
+    fn insert_data(&mut self, data_hash: H256, code: Bytes) {
+        self.data.insert(data_hash, code);
+    }
+    fn get_data(&self, data_hash: &H256) -> Option<Bytes> {
+        self.data.get(data_hash).cloned()
+    }
+}
+
 pub trait ChainStore {
     fn get_block_hash_by_number(&self, number: u64) -> Result<Option<H256>, DBError>;
+
+    /// Return the layer1 info (number, block hash and transaction hash)
+    /// of the transaction which committed the layer2 block
+    fn get_block_committed_info(
+        &self,
+        block_hash: &H256,
+    ) -> Result<Option<L2BlockCommittedInfo>, DBError>;
+
+    /// Return block hashes of numbers in `from..=to`,
+    /// stop at the first block which can't be read, e.g. the tip.
+    fn get_block_hashes_by_range(&self, from: u64, to: u64) -> Result<Vec<H256>, DBError> {
+        if from > to {
+            return Err(format!("invalid block range {}..={}", from, to).into());
+        }
+        let mut hashes = Vec::new();
+        for number in from..=to {
+            match self.get_block_hash_by_number(number)? {
+                Some(block_hash) => hashes.push(block_hash),
+                None => break,
+            }
+        }
+        Ok(hashes)
+    }
 }
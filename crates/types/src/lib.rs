@@ -8,6 +8,8 @@ pub mod core;
 mod extension;
 mod generated;
 pub mod prelude;
+// signing messages are also calculated by on-chain scripts, keep it no_std
+mod signature_message;
 mod std_traits;
 
 pub use generated::packed;
@@ -21,7 +23,6 @@ cfg_if::cfg_if! {
         use std::string;
 
         pub mod offchain;
-        mod signature_message;
     } else {
         use alloc::vec;
         use alloc::borrow;
@@ -1,6 +1,30 @@
 use crate::packed::LogItem;
 use sparse_merkle_tree::H256;
 use std::collections::HashMap;
+use std::fmt;
+
+/// Max bytes of return data displayed
+const DISPLAY_RETURN_DATA_PREFIX_LEN: usize = 32;
+
+/// Service flag of the system log emitted by polyjuice
+pub const GW_LOG_POLYJUICE_SYSTEM: u8 = 2;
+
+/// Parse `gas_used` from a polyjuice system log, returns `None` for other logs.
+///
+/// data: gas_used(u64 LE) | cumulative_gas_used(u64 LE) | created_address(20 bytes) | status_code(u32 LE)
+pub fn parse_polyjuice_gas_used(log: &LogItem) -> Option<u64> {
+    let service_flag: u8 = log.service_flag().into();
+    if service_flag != GW_LOG_POLYJUICE_SYSTEM {
+        return None;
+    }
+    let data = log.data().raw_data();
+    if data.len() != 40 {
+        return None;
+    }
+    let mut gas_used = [0u8; 8];
+    gas_used.copy_from_slice(&data[..8]);
+    Some(u64::from_le_bytes(gas_used))
+}
 
 #[derive(Debug, Clone, Default)]
 pub struct RunResult {
@@ -14,4 +38,188 @@ pub struct RunResult {
     pub read_data: HashMap<H256, usize>,
     // log data
     pub logs: Vec<LogItem>,
+    // gas used by polyjuice, 0 for non-EVM executions
+    pub gas_used: u64,
+}
+
+impl RunResult {
+    /// Merge the result of a subsequent execution into this one,
+    /// later writes override earlier ones and logs are appended in execution order
+    pub fn merge(&mut self, other: RunResult) {
+        let RunResult {
+            read_values,
+            write_values,
+            return_data,
+            account_count,
+            new_scripts,
+            write_data,
+            read_data,
+            logs,
+            gas_used,
+        } = other;
+        // keep the first read of a key, it is the value before the executions
+        for (key, value) in read_values {
+            self.read_values.entry(key).or_insert(value);
+        }
+        self.write_values.extend(write_values);
+        self.return_data = return_data;
+        if account_count.is_some() {
+            self.account_count = account_count;
+        }
+        self.new_scripts.extend(new_scripts);
+        self.write_data.extend(write_data);
+        self.read_data.extend(read_data);
+        self.logs.extend(logs);
+        self.gas_used = self.gas_used.saturating_add(gas_used);
+    }
+
+    /// Return hashes of the read data sorted ascending,
+    /// the `read_data_hashes` of a `TxReceipt` is built from this so it doesn't depend on the read order
+    pub fn read_data_hashes(&self) -> Vec<H256> {
+        let mut hashes: Vec<H256> = self.read_data.keys().copied().collect();
+        hashes.sort_unstable();
+        hashes
+    }
+
+    /// Set `gas_used` from the last polyjuice system log of the execution
+    pub fn set_gas_used_from_logs(&mut self) {
+        self.gas_used = self
+            .logs
+            .iter()
+            .filter_map(parse_polyjuice_gas_used)
+            .last()
+            .unwrap_or(0);
+    }
+}
+
+impl fmt::Display for RunResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RunResult {{ read_values: {}, write_values: {}, return_data: 0x",
+            self.read_values.len(),
+            self.write_values.len()
+        )?;
+        let prefix_len = std::cmp::min(self.return_data.len(), DISPLAY_RETURN_DATA_PREFIX_LEN);
+        for b in &self.return_data[..prefix_len] {
+            write!(f, "{:02x}", b)?;
+        }
+        if self.return_data.len() > prefix_len {
+            write!(f, "..({} bytes)", self.return_data.len())?;
+        }
+        write!(
+            f,
+            ", account_count: {:?}, new_scripts: {}, write_data: {}, read_data: {}, logs: {} }}",
+            self.account_count,
+            self.new_scripts.len(),
+            self.write_data.len(),
+            self.read_data.len(),
+            self.logs.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bytes::Bytes, prelude::*};
+
+    fn build_log(account_id: u32) -> LogItem {
+        LogItem::new_builder().account_id(account_id.pack()).build()
+    }
+
+    #[test]
+    fn test_read_data_hashes_are_canonical() {
+        let hashes: Vec<H256> = (1u8..=5).map(|i| [i; 32].into()).collect();
+        let mut forward = RunResult::default();
+        for (i, hash) in hashes.iter().enumerate() {
+            forward.read_data.insert(*hash, i);
+        }
+        let mut backward = RunResult::default();
+        for (i, hash) in hashes.iter().enumerate().rev() {
+            backward.read_data.insert(*hash, i);
+        }
+        // read the same data again
+        backward.read_data.insert(hashes[0], 0);
+
+        assert_eq!(forward.read_data_hashes(), hashes);
+        assert_eq!(backward.read_data_hashes(), hashes);
+    }
+
+    #[test]
+    fn test_merge_run_result() {
+        let key = H256::from([1u8; 32]);
+        let other_key = H256::from([2u8; 32]);
+
+        let mut result = RunResult::default();
+        result.read_values.insert(key, H256::zero());
+        result.write_values.insert(key, H256::from([10u8; 32]));
+        result.logs.push(build_log(1));
+
+        let mut next = RunResult::default();
+        next.read_values.insert(key, H256::from([10u8; 32]));
+        next.read_values.insert(other_key, H256::zero());
+        next.write_values.insert(key, H256::from([20u8; 32]));
+        next.write_values.insert(other_key, H256::from([30u8; 32]));
+        next.account_count = Some(5);
+        next.logs.push(build_log(2));
+        next.logs.push(build_log(3));
+
+        result.merge(next);
+
+        // last write wins
+        assert_eq!(result.write_values.len(), 2);
+        assert_eq!(result.write_values[&key], H256::from([20u8; 32]));
+        assert_eq!(result.write_values[&other_key], H256::from([30u8; 32]));
+        // reads are unioned, the earliest read is kept
+        assert_eq!(result.read_values.len(), 2);
+        assert_eq!(result.read_values[&key], H256::zero());
+        assert_eq!(result.account_count, Some(5));
+        // logs are in execution order
+        let log_ids: Vec<u32> = result
+            .logs
+            .iter()
+            .map(|log| log.account_id().unpack())
+            .collect();
+        assert_eq!(log_ids, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_display_run_result() {
+        let mut result = RunResult::default();
+        result.write_values.insert(H256::zero(), H256::zero());
+        result.return_data = vec![0xab; 40];
+        result.logs.push(build_log(1));
+
+        let s = result.to_string();
+        assert!(s.contains("read_values: 0"), "{}", s);
+        assert!(s.contains("write_values: 1"), "{}", s);
+        assert!(s.contains(&format!("return_data: 0x{}..(40 bytes)", "ab".repeat(32))));
+        assert!(s.contains("account_count: None"), "{}", s);
+        assert!(s.contains("logs: 1"), "{}", s);
+    }
+
+    #[test]
+    fn test_polyjuice_gas_used() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&21000u64.to_le_bytes());
+        data.extend_from_slice(&42000u64.to_le_bytes());
+        data.extend_from_slice(&[0u8; 20]);
+        data.extend_from_slice(&0u32.to_le_bytes());
+        let system_log = LogItem::new_builder()
+            .account_id(4u32.pack())
+            .service_flag(GW_LOG_POLYJUICE_SYSTEM.into())
+            .data(Bytes::from(data).pack())
+            .build();
+        assert_eq!(parse_polyjuice_gas_used(&system_log), Some(21000));
+        assert_eq!(parse_polyjuice_gas_used(&build_log(1)), None);
+
+        let mut result = RunResult::default();
+        result.logs.push(build_log(1));
+        result.set_gas_used_from_logs();
+        assert_eq!(result.gas_used, 0);
+        result.logs.push(system_log);
+        result.set_gas_used_from_logs();
+        assert_eq!(result.gas_used, 21000);
+    }
 }
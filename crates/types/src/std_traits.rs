@@ -38,6 +38,7 @@ impl_std_eq!(Script);
 impl_std_eq!(ChallengeLockArgs);
 impl_std_eq!(ChallengeWitness);
 impl_std_eq!(ChallengeTarget);
+impl_std_eq!(CancelChallenge);
 impl_std_eq!(Transaction);
 impl_std_eq!(DepositionRequest);
 impl_std_eq!(DepositionLockArgs);
@@ -48,6 +49,7 @@ impl_std_eq!(L2Transaction);
 impl_std_eq!(WithdrawalRequest);
 impl_std_eq!(VerifyTransactionWitness);
 impl_std_eq!(AccountMerkleState);
+impl_std_eq!(KVPair);
 
 cfg_if::cfg_if! {
     if #[cfg(feature = "std")] {